@@ -0,0 +1,75 @@
+//! Tokenizer throughput on real-world pages.
+//!
+//! The pages in `benches/data` are from the Rust documentation, which is
+//! dual-licensed under MIT and Apache-2.0: a text-heavy mdBook page and a
+//! markup-heavy rustdoc source view.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use rust_scraper::tokenizer::{self, Tokenizer};
+
+const PAGES: [(&str, &str); 2] = [
+    (
+        "rustc-warn-by-default-lints",
+        include_str!("data/rustc-warn-by-default-lints.html"),
+    ),
+    ("std-f64-source", include_str!("data/std-f64-source.html")),
+];
+
+/// The chunk size for streamed input, about that of a network read.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+fn tokenize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenize");
+
+    for (name, html) in PAGES {
+        group.throughput(Throughput::Bytes(html.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| tokenizer::tokenize(black_box(html)).len())
+        });
+    }
+
+    group.finish();
+}
+
+fn tokenize_streamed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenize_streamed");
+
+    for (name, html) in PAGES {
+        group.throughput(Throughput::Bytes(html.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut tokenizer = Tokenizer::new();
+                let mut count = 0;
+                for chunk in chunks(black_box(html)) {
+                    tokenizer.feed(chunk);
+                    count += tokenizer.by_ref().count();
+                }
+                tokenizer.end();
+                count + tokenizer.count()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// Splits `html` into chunks of about `CHUNK_SIZE` bytes.
+fn chunks(mut html: &str) -> impl Iterator<Item = &str> {
+    std::iter::from_fn(move || {
+        if html.is_empty() {
+            return None;
+        }
+
+        let mut end = CHUNK_SIZE.min(html.len());
+        while !html.is_char_boundary(end) {
+            end += 1;
+        }
+
+        let (chunk, rest) = html.split_at(end);
+        html = rest;
+        Some(chunk)
+    })
+}
+
+criterion_group!(benches, tokenize, tokenize_streamed);
+criterion_main!(benches);
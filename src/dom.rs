@@ -0,0 +1,663 @@
+//! The document tree built by the tree builder.
+//!
+//! Nodes are stored in an arena owned by their `Document` and referred to
+//! by `NodeId`s, so the tree builder can keep the stack of open elements
+//! and the head and form element pointers without holding references into
+//! the tree. Each node links to its parent, first and last child and
+//! previous and next sibling, which makes appending, inserting and
+//! detaching nodes constant-time.
+//!
+//! The contents of a `template` element and the shadow root of a host
+//! element are fragments of their own, outside the tree: they are reached
+//! through `Node::template_contents` and `Node::shadow_root` rather than
+//! as children, and `Document::descendants` does not enter them.
+
+use std::ops::Index;
+
+use crate::local_name::LocalName;
+use crate::parse_error::Span;
+
+/// A handle to a node in a `Document`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId(usize);
+
+/// https://dom.spec.whatwg.org/#concept-document-quirks
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QuirksMode {
+    NoQuirks,
+    LimitedQuirks,
+    Quirks,
+}
+
+/// The namespaces elements and attributes of an HTML document can be in.
+///
+/// https://infra.spec.whatwg.org/#namespaces
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Namespace {
+    Html,
+    MathMl,
+    Svg,
+    XLink,
+    Xml,
+    XmlNs,
+}
+
+impl Namespace {
+    pub fn url(&self) -> &'static str {
+        match self {
+            Namespace::Html => "http://www.w3.org/1999/xhtml",
+            Namespace::MathMl => "http://www.w3.org/1998/Math/MathML",
+            Namespace::Svg => "http://www.w3.org/2000/svg",
+            Namespace::XLink => "http://www.w3.org/1999/xlink",
+            Namespace::Xml => "http://www.w3.org/XML/1998/namespace",
+            Namespace::XmlNs => "http://www.w3.org/2000/xmlns/",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Attribute {
+    /// `None` for all attributes except some on foreign elements, like
+    /// `xlink:href`.
+    pub namespace: Option<Namespace>,
+    pub name: LocalName,
+    pub value: String,
+    pub name_span: Span,
+    pub value_span: Span,
+}
+
+/// The attributes of an element, in the order they appeared in the input.
+/// No two have the same namespace and name.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Attributes(Vec<Attribute>);
+
+impl Attributes {
+    pub fn iter(&self) -> std::slice::Iter<'_, Attribute> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The value of the attribute called `name` in no namespace.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_ns(None, name)
+    }
+
+    /// The value of the attribute called `name` in `namespace`.
+    pub fn get_ns(&self, namespace: Option<Namespace>, name: &str) -> Option<&str> {
+        self.position(namespace, name)
+            .map(|index| self.0[index].value.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(None, name).is_some()
+    }
+
+    /// Sets the value of the attribute called `name` in no namespace,
+    /// adding it if there is none.
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self.position(None, name) {
+            Some(index) => self.0[index].value = String::from(value),
+            None => self.0.push(Attribute {
+                namespace: None,
+                name: LocalName::new(name),
+                value: String::from(value),
+                name_span: Span::default(),
+                value_span: Span::default(),
+            }),
+        }
+    }
+
+    /// Removes the attribute called `name` in no namespace, returning it.
+    pub fn remove(&mut self, name: &str) -> Option<Attribute> {
+        self.position(None, name).map(|index| self.0.remove(index))
+    }
+
+    /// Adds `attribute` unless there is one with its namespace and name
+    /// already.
+    pub(crate) fn insert_if_missing(&mut self, attribute: Attribute) {
+        if self
+            .position(attribute.namespace, &attribute.name)
+            .is_none()
+        {
+            self.0.push(attribute);
+        }
+    }
+
+    fn position<N>(&self, namespace: Option<Namespace>, name: &N) -> Option<usize>
+    where
+        N: ?Sized,
+        LocalName: PartialEq<N>,
+    {
+        self.0
+            .iter()
+            .position(|attribute| attribute.namespace == namespace && attribute.name == *name)
+    }
+}
+
+impl<'a> IntoIterator for &'a Attributes {
+    type Item = &'a Attribute;
+    type IntoIter = std::slice::Iter<'a, Attribute>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<Attribute> for Attributes {
+    /// Collects attributes, keeping the first of any with the same
+    /// namespace and name.
+    fn from_iter<I: IntoIterator<Item = Attribute>>(iter: I) -> Attributes {
+        let mut attributes = Attributes::default();
+        for attribute in iter {
+            attributes.insert_if_missing(attribute);
+        }
+        attributes
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Element {
+    namespace: Namespace,
+    name: LocalName,
+    attributes: Attributes,
+}
+
+impl Element {
+    pub fn new(namespace: Namespace, name: LocalName, attributes: Attributes) -> Element {
+        Element {
+            namespace,
+            name,
+            attributes,
+        }
+    }
+
+    pub fn namespace(&self) -> Namespace {
+        self.namespace
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn local_name(&self) -> &LocalName {
+        &self.name
+    }
+
+    pub fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
+    pub fn attributes_mut(&mut self) -> &mut Attributes {
+        &mut self.attributes
+    }
+
+    /// The value of the attribute called `name`, if the element has one.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name)
+    }
+}
+
+/// https://dom.spec.whatwg.org/#shadowroot-mode
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShadowRootMode {
+    Open,
+    Closed,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NodeData {
+    Document,
+    /// The root of the contents of a `template` element.
+    DocumentFragment,
+    /// https://dom.spec.whatwg.org/#concept-shadow-root
+    ShadowRoot {
+        mode: ShadowRootMode,
+        delegates_focus: bool,
+        clonable: bool,
+        serializable: bool,
+    },
+    Doctype {
+        name: String,
+        public_id: String,
+        system_id: String,
+    },
+    Element(Element),
+    Text(String),
+    Comment(String),
+    ProcessingInstruction {
+        target: String,
+        data: String,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+    template_contents: Option<NodeId>,
+    shadow_root: Option<NodeId>,
+    host: Option<NodeId>,
+    data: NodeData,
+    span: Span,
+}
+
+impl Node {
+    fn new(data: NodeData, span: Span) -> Node {
+        Node {
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+            template_contents: None,
+            shadow_root: None,
+            host: None,
+            data,
+            span,
+        }
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn first_child(&self) -> Option<NodeId> {
+        self.first_child
+    }
+
+    pub fn last_child(&self) -> Option<NodeId> {
+        self.last_child
+    }
+
+    pub fn previous_sibling(&self) -> Option<NodeId> {
+        self.previous_sibling
+    }
+
+    pub fn next_sibling(&self) -> Option<NodeId> {
+        self.next_sibling
+    }
+
+    /// The fragment holding the contents of a `template` element.
+    pub fn template_contents(&self) -> Option<NodeId> {
+        self.template_contents
+    }
+
+    /// The shadow root attached to an element, like one declared with a
+    /// `template` element that has a `shadowrootmode` attribute.
+    ///
+    /// ```
+    /// let document = rust_scraper::parse_document(
+    ///     "<my-card><template shadowrootmode=open><h2>Title</h2></template></my-card>",
+    /// );
+    /// let card = document.elements_by_tag_name("my-card").next().unwrap();
+    /// let shadow_root = document.node(card).shadow_root().unwrap();
+    ///
+    /// assert_eq!(document.text_content(shadow_root), "Title");
+    /// assert_eq!(document.node(shadow_root).host(), Some(card));
+    /// assert_eq!(document.children(card).count(), 0);
+    /// ```
+    pub fn shadow_root(&self) -> Option<NodeId> {
+        self.shadow_root
+    }
+
+    /// The element a shadow root is attached to.
+    pub fn host(&self) -> Option<NodeId> {
+        self.host
+    }
+
+    pub fn data(&self) -> &NodeData {
+        &self.data
+    }
+
+    /// The span of the markup the node was built from: the start tag of an
+    /// element, which is empty for an element the parser implied, and all
+    /// of the text a text node was built from.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn as_element(&self) -> Option<&Element> {
+        match &self.data {
+            NodeData::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match &self.data {
+            NodeData::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// A document and all of its nodes, including those not in the tree.
+///
+/// ```
+/// use rust_scraper::dom::{Attributes, Element, Namespace, NodeData};
+/// use rust_scraper::local_name;
+/// use rust_scraper::parse_error::Span;
+///
+/// let mut document = rust_scraper::parse_document("<ul><li>one<li>three</ul>");
+/// let items: Vec<_> = document.elements_by_tag_name("li").collect();
+///
+/// let item = Element::new(Namespace::Html, local_name::li, Attributes::default());
+/// let two = document.create_node(NodeData::Element(item), Span::default());
+/// document.append_text(two, "two", Span::default());
+/// document.insert_before(items[1], two);
+///
+/// let list = document.node(two).parent().unwrap();
+/// let texts: Vec<_> = document
+///     .children(list)
+///     .map(|item| document.text_content(item))
+///     .collect();
+/// assert_eq!(texts, ["one", "two", "three"]);
+///
+/// document.detach(items[0]);
+/// assert_eq!(document.text_content(list), "twothree");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Document {
+    nodes: Vec<Node>,
+    quirks_mode: QuirksMode,
+}
+
+impl Document {
+    /// An empty document: just the document node.
+    pub fn new() -> Document {
+        Document {
+            nodes: vec![Node::new(NodeData::Document, Span::default())],
+            quirks_mode: QuirksMode::NoQuirks,
+        }
+    }
+
+    /// The document node itself.
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn quirks_mode(&self) -> QuirksMode {
+        self.quirks_mode
+    }
+
+    pub(crate) fn set_quirks_mode(&mut self, quirks_mode: QuirksMode) {
+        self.quirks_mode = quirks_mode;
+    }
+
+    /// The `html` element, i.e. the first element child of the document.
+    pub fn document_element(&self) -> Option<NodeId> {
+        self.children(self.root())
+            .find(|&child| self.node(child).as_element().is_some())
+    }
+
+    /// The node `id` as an element, if it is one.
+    pub fn element(&self, id: NodeId) -> Option<&Element> {
+        self.node(id).as_element()
+    }
+
+    pub fn element_mut(&mut self, id: NodeId) -> Option<&mut Element> {
+        match &mut self.nodes[id.0].data {
+            NodeData::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// The children of `id`, first to last.
+    pub fn children(&self, id: NodeId) -> Children<'_> {
+        Children {
+            document: self,
+            front: self.node(id).first_child,
+            back: self.node(id).last_child,
+        }
+    }
+
+    /// All nodes below `id` in tree order, not including `id` itself.
+    pub fn descendants(&self, id: NodeId) -> Descendants<'_> {
+        Descendants {
+            document: self,
+            root: id,
+            next: self.node(id).first_child,
+        }
+    }
+
+    /// All elements called `name` in tree order.
+    pub fn elements_by_tag_name<'a>(&'a self, name: &str) -> impl Iterator<Item = NodeId> + 'a {
+        let name = LocalName::new(name);
+        self.descendants(self.root())
+            .filter(move |&id| self.element(id).is_some_and(|element| element.name == name))
+    }
+
+    /// The concatenated text of all text nodes below `id`.
+    pub fn text_content(&self, id: NodeId) -> String {
+        self.descendants(id)
+            .filter_map(|id| self.node(id).as_text())
+            .collect()
+    }
+
+    /// Creates a node that is not in the tree yet.
+    pub fn create_node(&mut self, data: NodeData, span: Span) -> NodeId {
+        self.nodes.push(Node::new(data, span));
+        NodeId(self.nodes.len() - 1)
+    }
+
+    /// Appends `child` as the last child of `parent`, first detaching it
+    /// from where it was.
+    ///
+    /// Panics if `child` is `parent` or one of its ancestors.
+    pub fn append(&mut self, parent: NodeId, child: NodeId) {
+        self.assert_not_inclusive_ancestor(child, parent);
+        self.detach(child);
+
+        let previous = self.nodes[parent.0].last_child;
+        match previous {
+            Some(previous) => self.nodes[previous.0].next_sibling = Some(child),
+            None => self.nodes[parent.0].first_child = Some(child),
+        }
+        self.nodes[parent.0].last_child = Some(child);
+
+        let node = &mut self.nodes[child.0];
+        node.parent = Some(parent);
+        node.previous_sibling = previous;
+    }
+
+    /// Inserts `child` just before `sibling`, first detaching it from where
+    /// it was.
+    ///
+    /// Panics if `sibling` has no parent, if `child` is `sibling` or if it
+    /// is one of `sibling`'s ancestors.
+    pub fn insert_before(&mut self, sibling: NodeId, child: NodeId) {
+        let parent = self.nodes[sibling.0]
+            .parent
+            .expect("the sibling to insert before has a parent");
+        assert!(child != sibling, "cannot insert a node before itself");
+        self.assert_not_inclusive_ancestor(child, parent);
+
+        self.detach(child);
+        let previous = self.nodes[sibling.0].previous_sibling.replace(child);
+        match previous {
+            Some(previous) => self.nodes[previous.0].next_sibling = Some(child),
+            None => self.nodes[parent.0].first_child = Some(child),
+        }
+
+        let node = &mut self.nodes[child.0];
+        node.parent = Some(parent);
+        node.previous_sibling = previous;
+        node.next_sibling = Some(sibling);
+    }
+
+    /// Appends `text` to the text node that is the last child of `parent`,
+    /// or to a new text node if there is none. The node's span is extended
+    /// to the end of `span`.
+    pub fn append_text(&mut self, parent: NodeId, text: &str, span: Span) {
+        if let Some(last) = self.nodes[parent.0].last_child {
+            let node = &mut self.nodes[last.0];
+            if let NodeData::Text(existing) = &mut node.data {
+                existing.push_str(text);
+                node.span.end = span.end;
+                return;
+            }
+        }
+
+        let node = self.create_node(NodeData::Text(String::from(text)), span);
+        self.append(parent, node);
+    }
+
+    /// Inserts `text` just before `sibling`, appending it to the text node
+    /// there if there is one, like `append_text`.
+    pub fn insert_text_before(&mut self, sibling: NodeId, text: &str, span: Span) {
+        if let Some(previous) = self.nodes[sibling.0].previous_sibling {
+            let node = &mut self.nodes[previous.0];
+            if let NodeData::Text(existing) = &mut node.data {
+                existing.push_str(text);
+                node.span.end = span.end;
+                return;
+            }
+        }
+
+        let node = self.create_node(NodeData::Text(String::from(text)), span);
+        self.insert_before(sibling, node);
+    }
+
+    /// Makes `contents`, usually a `DocumentFragment`, the contents of the
+    /// `template` element `template`.
+    pub fn set_template_contents(&mut self, template: NodeId, contents: NodeId) {
+        self.nodes[template.0].template_contents = Some(contents);
+    }
+
+    /// Attaches the `ShadowRoot` node `shadow_root` to `host`, replacing
+    /// any shadow root it had.
+    pub fn attach_shadow_root(&mut self, host: NodeId, shadow_root: NodeId) {
+        self.nodes[host.0].shadow_root = Some(shadow_root);
+        self.nodes[shadow_root.0].host = Some(host);
+    }
+
+    /// Panics if inserting `child` into `parent` would make a cycle. Only a
+    /// node with children can be an ancestor, so new nodes are inserted
+    /// without walking up the tree.
+    fn assert_not_inclusive_ancestor(&self, child: NodeId, parent: NodeId) {
+        let is_ancestor = child == parent
+            || self.nodes[child.0].first_child.is_some()
+                && std::iter::successors(Some(parent), |id| self.nodes[id.0].parent)
+                    .any(|id| id == child);
+        assert!(
+            !is_ancestor,
+            "cannot insert a node into itself or one of its descendants"
+        );
+    }
+
+    /// Removes `child` from its parent, if it has one. The node and its
+    /// descendants stay in the arena and can be inserted again.
+    pub fn detach(&mut self, child: NodeId) {
+        let node = &mut self.nodes[child.0];
+        let Some(parent) = node.parent.take() else {
+            return;
+        };
+        let previous = node.previous_sibling.take();
+        let next = node.next_sibling.take();
+
+        match previous {
+            Some(previous) => self.nodes[previous.0].next_sibling = next,
+            None => self.nodes[parent.0].first_child = next,
+        }
+        match next {
+            Some(next) => self.nodes[next.0].previous_sibling = previous,
+            None => self.nodes[parent.0].last_child = previous,
+        }
+    }
+}
+
+impl Default for Document {
+    fn default() -> Document {
+        Document::new()
+    }
+}
+
+impl Index<NodeId> for Document {
+    type Output = Node;
+
+    fn index(&self, id: NodeId) -> &Node {
+        self.node(id)
+    }
+}
+
+/// Iterator returned by `Document::children`.
+pub struct Children<'a> {
+    document: &'a Document,
+    front: Option<NodeId>,
+    back: Option<NodeId>,
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.front?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.front = self.document.node(id).next_sibling;
+        }
+        Some(id)
+    }
+}
+
+impl<'a> DoubleEndedIterator for Children<'a> {
+    fn next_back(&mut self) -> Option<NodeId> {
+        let id = self.back?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.back = self.document.node(id).previous_sibling;
+        }
+        Some(id)
+    }
+}
+
+/// Iterator returned by `Document::descendants`.
+pub struct Descendants<'a> {
+    document: &'a Document,
+    root: NodeId,
+    next: Option<NodeId>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        let node = self.document.node(id);
+
+        // The first child, or else the next sibling of the closest
+        // inclusive ancestor below the root that has one.
+        self.next = node.first_child.or_else(|| {
+            let mut ancestor = id;
+            loop {
+                if ancestor == self.root {
+                    return None;
+                }
+                let node = self.document.node(ancestor);
+                if node.next_sibling.is_some() {
+                    return node.next_sibling;
+                }
+                ancestor = node.parent?;
+            }
+        });
+        Some(id)
+    }
+}
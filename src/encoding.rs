@@ -0,0 +1,330 @@
+//! Determining the character encoding of a byte stream.
+//!
+//! https://html.spec.whatwg.org/multipage/parsing.html#determining-the-character-encoding
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252, X_USER_DEFINED};
+
+/// How sure the parser is about the encoding it decodes the input with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Confidence {
+    /// The encoding was guessed and may be changed by a later `<meta>`.
+    Tentative,
+    Certain,
+}
+
+/// Why an encoding was chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodingSource {
+    /// The input starts with a UTF-8 or UTF-16 byte order mark.
+    ByteOrderMark,
+    /// The charset given by the transport layer, e.g. the `Content-Type`
+    /// header.
+    TransportLayer,
+    /// A `<meta charset>` or `<meta http-equiv>` found by the prescan of
+    /// the first 1024 bytes.
+    Prescan,
+    /// A `<meta>` found while tokenizing, after which the input was decoded
+    /// again.
+    MetaChange,
+    /// Nothing declared an encoding, so windows-1252 was assumed.
+    Default,
+}
+
+/// The outcome of the encoding sniffing algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EncodingDecision {
+    pub encoding: &'static Encoding,
+    pub confidence: Confidence,
+    pub source: EncodingSource,
+}
+
+/// The number of bytes the prescan looks at.
+const PRESCAN_LENGTH: usize = 1024;
+
+/// Runs the encoding sniffing algorithm on `bytes`: the byte order mark
+/// first, then the transport layer's charset label, then a prescan for
+/// `<meta>` declarations, falling back to windows-1252.
+pub fn sniff(bytes: &[u8], transport_charset: Option<&str>) -> EncodingDecision {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return EncodingDecision {
+            encoding,
+            confidence: Confidence::Certain,
+            source: EncodingSource::ByteOrderMark,
+        };
+    }
+
+    if let Some(encoding) =
+        transport_charset.and_then(|label| Encoding::for_label(label.as_bytes()))
+    {
+        return EncodingDecision {
+            encoding,
+            confidence: Confidence::Certain,
+            source: EncodingSource::TransportLayer,
+        };
+    }
+
+    if let Some(encoding) = prescan(&bytes[..bytes.len().min(PRESCAN_LENGTH)]) {
+        return EncodingDecision {
+            encoding,
+            confidence: Confidence::Tentative,
+            source: EncodingSource::Prescan,
+        };
+    }
+
+    EncodingDecision {
+        encoding: WINDOWS_1252,
+        confidence: Confidence::Tentative,
+        source: EncodingSource::Default,
+    }
+}
+
+/// Decodes `bytes` with the sniffed encoding, skipping a byte order mark.
+/// Malformed sequences are replaced with U+FFFD.
+pub fn decode(bytes: &[u8], decision: &EncodingDecision) -> String {
+    let bytes = match decision.source {
+        EncodingSource::ByteOrderMark => match Encoding::for_bom(bytes) {
+            Some((_, bom_length)) => &bytes[bom_length..],
+            None => bytes,
+        },
+        _ => bytes,
+    };
+
+    decision
+        .encoding
+        .decode_without_bom_handling(bytes)
+        .0
+        .into_owned()
+}
+
+/// The encoding a `<meta>` declares, adjusted as the "change the encoding"
+/// and prescan algorithms require: a document cannot declare itself UTF-16,
+/// since it was parsed as an ASCII-compatible encoding to find the `<meta>`.
+pub fn adjust_declared_encoding(encoding: &'static Encoding) -> &'static Encoding {
+    if encoding == UTF_16BE || encoding == UTF_16LE {
+        UTF_8
+    } else if encoding == X_USER_DEFINED {
+        WINDOWS_1252
+    } else {
+        encoding
+    }
+}
+
+/// Extracts the encoding label from the value of a `<meta>` element's
+/// `content` attribute, e.g. `text/html; charset=utf-8`.
+///
+/// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#algorithm-for-extracting-a-character-encoding-from-a-meta-element
+pub fn extract_charset_from_content(content: &[u8]) -> Option<&[u8]> {
+    let mut position = 0;
+
+    loop {
+        let found = find_ignore_ascii_case(&content[position..], b"charset")?;
+        position += found + b"charset".len();
+        position = skip_whitespace(content, position);
+
+        if content.get(position) == Some(&b'=') {
+            position += 1;
+            break;
+        }
+    }
+
+    position = skip_whitespace(content, position);
+
+    match content.get(position) {
+        Some(quote @ (b'"' | b'\'')) => {
+            let value = &content[position + 1..];
+            let end = value.iter().position(|b| b == quote)?;
+            Some(&value[..end])
+        }
+        Some(_) => {
+            let value = &content[position..];
+            let end = value
+                .iter()
+                .position(|b| is_whitespace(*b) || *b == b';')
+                .unwrap_or(value.len());
+            Some(&value[..end])
+        }
+        None => None,
+    }
+}
+
+/// Prescans the start of a byte stream for a `<meta>` declaring its
+/// encoding.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding
+fn prescan(bytes: &[u8]) -> Option<&'static Encoding> {
+    let mut position = 0;
+
+    while position < bytes.len() {
+        let rest = &bytes[position..];
+
+        if rest.starts_with(b"<!--") {
+            // Skip to the end of a "-->" that starts after the "<!".
+            let end = find(&rest[2..], b"-->")?;
+            position += 2 + end + 3;
+        } else if starts_with_ignore_ascii_case(rest, b"<meta")
+            && rest.get(5).is_some_and(|b| is_whitespace(*b) || *b == b'/')
+        {
+            position += 6;
+
+            match prescan_meta(bytes, &mut position)? {
+                Some(encoding) => return Some(encoding),
+                None => continue,
+            }
+        } else if rest.starts_with(b"<")
+            && (rest.get(1).is_some_and(u8::is_ascii_alphabetic)
+                || rest.starts_with(b"</") && rest.get(2).is_some_and(u8::is_ascii_alphabetic))
+        {
+            let end = rest.iter().position(|b| is_whitespace(*b) || *b == b'>')?;
+            position += end;
+
+            while get_attribute(bytes, &mut position)?.is_some() {}
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            position += rest.iter().position(|b| *b == b'>')? + 1;
+        } else {
+            position += 1;
+        }
+    }
+
+    None
+}
+
+/// Processes the attributes of a `<meta>` found by the prescan. Returns
+/// `None` when the input ends first, and `Some(None)` when the element does
+/// not declare a usable encoding.
+fn prescan_meta(bytes: &[u8], position: &mut usize) -> Option<Option<&'static Encoding>> {
+    let mut attribute_names: Vec<Vec<u8>> = Vec::new();
+    let mut got_pragma = false;
+    let mut need_pragma: Option<bool> = None;
+    // `Some(None)` is a `charset` attribute with an unknown label.
+    let mut charset: Option<Option<&'static Encoding>> = None;
+
+    while let Some((name, value)) = get_attribute(bytes, position)? {
+        if attribute_names.contains(&name) {
+            continue;
+        }
+
+        // Whichever of `content` and `charset` comes first wins.
+        match name.as_slice() {
+            b"http-equiv" => got_pragma |= value == b"content-type",
+            b"content" if charset.is_none() => {
+                if let Some(encoding) =
+                    extract_charset_from_content(&value).and_then(Encoding::for_label)
+                {
+                    charset = Some(Some(encoding));
+                    need_pragma = Some(true);
+                }
+            }
+            b"charset" if charset.is_none() => {
+                charset = Some(Encoding::for_label(&value));
+                need_pragma = Some(false);
+            }
+            _ => {}
+        }
+
+        attribute_names.push(name);
+    }
+
+    let encoding = match need_pragma {
+        None => None,
+        Some(true) if !got_pragma => None,
+        _ => charset.flatten().map(adjust_declared_encoding),
+    };
+
+    Some(encoding)
+}
+
+/// Gets the next attribute of a tag for the prescan, lowercasing both its
+/// name and value. Returns `None` when the input ends first, and
+/// `Some(None)` at the end of the tag.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#concept-get-attributes-when-sniffing
+fn get_attribute(bytes: &[u8], position: &mut usize) -> Option<Option<(Vec<u8>, Vec<u8>)>> {
+    while is_whitespace(*bytes.get(*position)?) || bytes[*position] == b'/' {
+        *position += 1;
+    }
+
+    if bytes[*position] == b'>' {
+        return Some(None);
+    }
+
+    let mut name = Vec::new();
+    let mut value = Vec::new();
+
+    // Attribute name.
+    loop {
+        match *bytes.get(*position)? {
+            b'=' if !name.is_empty() => {
+                *position += 1;
+                break;
+            }
+            b if is_whitespace(b) => {
+                *position = skip_whitespace(bytes, *position);
+
+                if *bytes.get(*position)? != b'=' {
+                    return Some(Some((name, value)));
+                }
+
+                *position += 1;
+                break;
+            }
+            b'/' | b'>' => return Some(Some((name, value))),
+            b => name.push(b.to_ascii_lowercase()),
+        }
+
+        *position += 1;
+    }
+
+    // Attribute value.
+    *position = skip_whitespace(bytes, *position);
+
+    match *bytes.get(*position)? {
+        quote @ (b'"' | b'\'') => loop {
+            *position += 1;
+
+            match *bytes.get(*position)? {
+                b if b == quote => {
+                    *position += 1;
+                    return Some(Some((name, value)));
+                }
+                b => value.push(b.to_ascii_lowercase()),
+            }
+        },
+        b'>' => Some(Some((name, value))),
+        _ => loop {
+            match *bytes.get(*position)? {
+                b if is_whitespace(b) || b == b'>' => return Some(Some((name, value))),
+                b => value.push(b.to_ascii_lowercase()),
+            }
+
+            *position += 1;
+        },
+    }
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+}
+
+fn skip_whitespace(bytes: &[u8], mut position: usize) -> usize {
+    while bytes.get(position).is_some_and(|b| is_whitespace(*b)) {
+        position += 1;
+    }
+
+    position
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn find_ignore_ascii_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+fn starts_with_ignore_ascii_case(bytes: &[u8], prefix: &[u8]) -> bool {
+    bytes.len() >= prefix.len() && bytes[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
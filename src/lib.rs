@@ -0,0 +1,9 @@
+pub mod dom;
+pub mod encoding;
+mod entities;
+pub mod local_name;
+pub mod parse_error;
+pub mod tokenizer;
+pub mod tree_builder;
+
+pub use tree_builder::parse_document;
@@ -0,0 +1,1259 @@
+//! Element and attribute names.
+//!
+//! The names of HTML, SVG and MathML elements and attributes are predefined
+//! as constants in this module, e.g. `local_name::div`. A predefined
+//! `LocalName` is a small integer, so comparing names and matching on them
+//! is cheap; any other name is a reference-counted string of its own,
+//! which needs no global table and is freed when no longer used.
+
+use std::sync::Arc;
+
+/// An element or attribute name.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct LocalName(Repr);
+
+/// Predefined names are an index into `STATIC_NAMES`; any other name is
+/// reference-counted and freed along with the last node or token using it.
+/// A name is only ever dynamic if it is not predefined, so names compare
+/// equal exactly when their strings do.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Repr {
+    Static(u32),
+    Dynamic(Arc<str>),
+}
+
+// The implementation lives in its own module, where the name constants
+// below are not in scope and cannot be mistaken for bindings like `name`.
+mod interning {
+    use std::fmt;
+    use std::ops::Deref;
+    use std::sync::Arc;
+
+    use super::{LocalName, Repr, STATIC_NAMES};
+
+    impl LocalName {
+        /// The predefined name `name`, or a new dynamic one.
+        pub fn new(name: &str) -> LocalName {
+            match STATIC_NAMES.binary_search(&name) {
+                Ok(index) => LocalName(Repr::Static(index as u32)),
+                Err(_) => LocalName(Repr::Dynamic(Arc::from(name))),
+            }
+        }
+
+        pub fn as_str(&self) -> &str {
+            match &self.0 {
+                Repr::Static(index) => STATIC_NAMES[*index as usize],
+                Repr::Dynamic(name) => name,
+            }
+        }
+    }
+
+    impl From<&str> for LocalName {
+        fn from(name: &str) -> LocalName {
+            LocalName::new(name)
+        }
+    }
+
+    impl Deref for LocalName {
+        type Target = str;
+
+        fn deref(&self) -> &str {
+            self.as_str()
+        }
+    }
+
+    impl PartialEq<str> for LocalName {
+        fn eq(&self, other: &str) -> bool {
+            self.as_str() == other
+        }
+    }
+
+    impl PartialEq<&str> for LocalName {
+        fn eq(&self, other: &&str) -> bool {
+            self.as_str() == *other
+        }
+    }
+
+    impl fmt::Debug for LocalName {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt::Debug::fmt(self.as_str(), f)
+        }
+    }
+
+    impl fmt::Display for LocalName {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
+}
+
+macro_rules! static_names {
+    ($($ident:ident = $name:literal,)*) => {
+        /// The predefined names, sorted by their UTF-8 bytes so that lookups
+        /// can binary search them. A name's index is its id.
+        static STATIC_NAMES: &[&str] = &[$($name,)*];
+
+        #[allow(non_camel_case_types)]
+        enum StaticName {
+            $($ident,)*
+        }
+
+        $(
+            #[allow(non_upper_case_globals)]
+            pub const $ident: LocalName = LocalName(Repr::Static(StaticName::$ident as u32));
+        )*
+    };
+}
+
+// The names of HTML, SVG and MathML elements and attributes, both as
+// written in the specifications and lowercased, as the tokenizer emits
+// them before the tree builder adjusts them in foreign content.
+static_names! {
+    a = "a",
+    abbr = "abbr",
+    abs = "abs",
+    accent = "accent",
+    accent_height = "accent-height",
+    accentunder = "accentunder",
+    accept = "accept",
+    accept_charset = "accept-charset",
+    accesskey = "accesskey",
+    accumulate = "accumulate",
+    acronym = "acronym",
+    action = "action",
+    actiontype = "actiontype",
+    active = "active",
+    actuate = "actuate",
+    additive = "additive",
+    address = "address",
+    align = "align",
+    alignment_baseline = "alignment-baseline",
+    alignmentscope = "alignmentscope",
+    alink = "alink",
+    allowfullscreen = "allowfullscreen",
+    alphabetic = "alphabetic",
+    alt = "alt",
+    altGlyph = "altGlyph",
+    altGlyphDef = "altGlyphDef",
+    altGlyphItem = "altGlyphItem",
+    altglyph = "altglyph",
+    altglyphdef = "altglyphdef",
+    altglyphitem = "altglyphitem",
+    altimg = "altimg",
+    alttext = "alttext",
+    amplitude = "amplitude",
+    and = "and",
+    animate = "animate",
+    animateColor = "animateColor",
+    animateMotion = "animateMotion",
+    animateTransform = "animateTransform",
+    animatecolor = "animatecolor",
+    animatemotion = "animatemotion",
+    animatetransform = "animatetransform",
+    animation = "animation",
+    annotation = "annotation",
+    annotation_xml = "annotation-xml",
+    applet = "applet",
+    apply = "apply",
+    approx = "approx",
+    arabic_form = "arabic-form",
+    arccos = "arccos",
+    arccosh = "arccosh",
+    arccot = "arccot",
+    arccoth = "arccoth",
+    arccsc = "arccsc",
+    arccsch = "arccsch",
+    archive = "archive",
+    arcrole = "arcrole",
+    arcsec = "arcsec",
+    arcsech = "arcsech",
+    arcsin = "arcsin",
+    arcsinh = "arcsinh",
+    arctan = "arctan",
+    arctanh = "arctanh",
+    area = "area",
+    arg = "arg",
+    aria_activedescendant = "aria-activedescendant",
+    aria_atomic = "aria-atomic",
+    aria_autocomplete = "aria-autocomplete",
+    aria_braillelabel = "aria-braillelabel",
+    aria_brailleroledescription = "aria-brailleroledescription",
+    aria_busy = "aria-busy",
+    aria_channel = "aria-channel",
+    aria_checked = "aria-checked",
+    aria_colcount = "aria-colcount",
+    aria_colindex = "aria-colindex",
+    aria_colindextext = "aria-colindextext",
+    aria_colspan = "aria-colspan",
+    aria_controls = "aria-controls",
+    aria_current = "aria-current",
+    aria_datatype = "aria-datatype",
+    aria_describedby = "aria-describedby",
+    aria_description = "aria-description",
+    aria_details = "aria-details",
+    aria_disabled = "aria-disabled",
+    aria_dropeffect = "aria-dropeffect",
+    aria_errormessage = "aria-errormessage",
+    aria_expanded = "aria-expanded",
+    aria_flowto = "aria-flowto",
+    aria_grab = "aria-grab",
+    aria_haspopup = "aria-haspopup",
+    aria_hidden = "aria-hidden",
+    aria_invalid = "aria-invalid",
+    aria_keyshortcuts = "aria-keyshortcuts",
+    aria_label = "aria-label",
+    aria_labelledby = "aria-labelledby",
+    aria_level = "aria-level",
+    aria_live = "aria-live",
+    aria_modal = "aria-modal",
+    aria_multiline = "aria-multiline",
+    aria_multiselectable = "aria-multiselectable",
+    aria_orientation = "aria-orientation",
+    aria_owns = "aria-owns",
+    aria_placeholder = "aria-placeholder",
+    aria_posinset = "aria-posinset",
+    aria_pressed = "aria-pressed",
+    aria_readonly = "aria-readonly",
+    aria_relevant = "aria-relevant",
+    aria_required = "aria-required",
+    aria_roledescription = "aria-roledescription",
+    aria_rowcount = "aria-rowcount",
+    aria_rowindex = "aria-rowindex",
+    aria_rowindextext = "aria-rowindextext",
+    aria_rowspan = "aria-rowspan",
+    aria_secret = "aria-secret",
+    aria_selected = "aria-selected",
+    aria_setsize = "aria-setsize",
+    aria_sort = "aria-sort",
+    aria_templateid = "aria-templateid",
+    aria_valuemax = "aria-valuemax",
+    aria_valuemin = "aria-valuemin",
+    aria_valuenow = "aria-valuenow",
+    aria_valuetext = "aria-valuetext",
+    article = "article",
+    ascent = "ascent",
+    aside = "aside",
+    r#async = "async",
+    attributeName = "attributeName",
+    attributeType = "attributeType",
+    attributename = "attributename",
+    attributetype = "attributetype",
+    audio = "audio",
+    autocomplete = "autocomplete",
+    autofocus = "autofocus",
+    autoplay = "autoplay",
+    autosubmit = "autosubmit",
+    axis = "axis",
+    azimuth = "azimuth",
+    b = "b",
+    background = "background",
+    base = "base",
+    baseFrequency = "baseFrequency",
+    baseProfile = "baseProfile",
+    basefont = "basefont",
+    basefrequency = "basefrequency",
+    baseline = "baseline",
+    baseline_shift = "baseline-shift",
+    baseprofile = "baseprofile",
+    bbox = "bbox",
+    bdi = "bdi",
+    bdo = "bdo",
+    begin = "begin",
+    bevelled = "bevelled",
+    bgcolor = "bgcolor",
+    bgsound = "bgsound",
+    bias = "bias",
+    big = "big",
+    blink = "blink",
+    blockquote = "blockquote",
+    body = "body",
+    border = "border",
+    br = "br",
+    button = "button",
+    bvar = "bvar",
+    by = "by",
+    calcMode = "calcMode",
+    calcmode = "calcmode",
+    canvas = "canvas",
+    cap_height = "cap-height",
+    caption = "caption",
+    card = "card",
+    cartesianproduct = "cartesianproduct",
+    ceiling = "ceiling",
+    cellpadding = "cellpadding",
+    cellspacing = "cellspacing",
+    center = "center",
+    char = "char",
+    charoff = "charoff",
+    charset = "charset",
+    checked = "checked",
+    ci = "ci",
+    circle = "circle",
+    cite = "cite",
+    class = "class",
+    classid = "classid",
+    clear = "clear",
+    clip = "clip",
+    clip_path = "clip-path",
+    clip_rule = "clip-rule",
+    clipPath = "clipPath",
+    clipPathUnits = "clipPathUnits",
+    clippath = "clippath",
+    clippathunits = "clippathunits",
+    close = "close",
+    closure = "closure",
+    cn = "cn",
+    code = "code",
+    codebase = "codebase",
+    codetype = "codetype",
+    codomain = "codomain",
+    col = "col",
+    colgroup = "colgroup",
+    color = "color",
+    color_interpolation = "color-interpolation",
+    color_interpolation_filters = "color-interpolation-filters",
+    color_profile = "color-profile",
+    color_rendering = "color-rendering",
+    cols = "cols",
+    colspan = "colspan",
+    columnalign = "columnalign",
+    columnlines = "columnlines",
+    columnspacing = "columnspacing",
+    columnspan = "columnspan",
+    columnwidth = "columnwidth",
+    compact = "compact",
+    complexes = "complexes",
+    compose = "compose",
+    condition = "condition",
+    conjugate = "conjugate",
+    content = "content",
+    contentScriptType = "contentScriptType",
+    contentStyleType = "contentStyleType",
+    contenteditable = "contenteditable",
+    contentscripttype = "contentscripttype",
+    contentstyletype = "contentstyletype",
+    contextmenu = "contextmenu",
+    controllerchange = "controllerchange",
+    controls = "controls",
+    coords = "coords",
+    cos = "cos",
+    cosh = "cosh",
+    cot = "cot",
+    coth = "coth",
+    crossorigin = "crossorigin",
+    csc = "csc",
+    csch = "csch",
+    csymbol = "csymbol",
+    curl = "curl",
+    cursor = "cursor",
+    cx = "cx",
+    cy = "cy",
+    d = "d",
+    data = "data",
+    datafld = "datafld",
+    dataformatas = "dataformatas",
+    datalist = "datalist",
+    datasrc = "datasrc",
+    datatemplate = "datatemplate",
+    datetime = "datetime",
+    dd = "dd",
+    declare = "declare",
+    decoding = "decoding",
+    default = "default",
+    defer = "defer",
+    definition_src = "definition-src",
+    definitionURL = "definitionURL",
+    definitionurl = "definitionurl",
+    defs = "defs",
+    degree = "degree",
+    del = "del",
+    depth = "depth",
+    desc = "desc",
+    descent = "descent",
+    details = "details",
+    determinant = "determinant",
+    dfn = "dfn",
+    dialog = "dialog",
+    diff = "diff",
+    diffuseConstant = "diffuseConstant",
+    diffuseconstant = "diffuseconstant",
+    dir = "dir",
+    direction = "direction",
+    dirname = "dirname",
+    disabled = "disabled",
+    discard = "discard",
+    display = "display",
+    displaystyle = "displaystyle",
+    div = "div",
+    divergence = "divergence",
+    divide = "divide",
+    divisor = "divisor",
+    dl = "dl",
+    domain = "domain",
+    domainofapplication = "domainofapplication",
+    dominant_baseline = "dominant-baseline",
+    download = "download",
+    draggable = "draggable",
+    dt = "dt",
+    dur = "dur",
+    dx = "dx",
+    dy = "dy",
+    edge = "edge",
+    edgeMode = "edgeMode",
+    edgemode = "edgemode",
+    elevation = "elevation",
+    ellipse = "ellipse",
+    em = "em",
+    embed = "embed",
+    emptyset = "emptyset",
+    enable_background = "enable-background",
+    encoding = "encoding",
+    enctype = "enctype",
+    end = "end",
+    eq = "eq",
+    equalcolumns = "equalcolumns",
+    equalrows = "equalrows",
+    equivalent = "equivalent",
+    eulergamma = "eulergamma",
+    event = "event",
+    exists = "exists",
+    exp = "exp",
+    exponent = "exponent",
+    exponentiale = "exponentiale",
+    externalResourcesRequired = "externalResourcesRequired",
+    externalresourcesrequired = "externalresourcesrequired",
+    face = "face",
+    factorial = "factorial",
+    factorof = "factorof",
+    r#false = "false",
+    feBlend = "feBlend",
+    feColorMatrix = "feColorMatrix",
+    feComponentTransfer = "feComponentTransfer",
+    feComposite = "feComposite",
+    feConvolveMatrix = "feConvolveMatrix",
+    feDiffuseLighting = "feDiffuseLighting",
+    feDisplacementMap = "feDisplacementMap",
+    feDistantLight = "feDistantLight",
+    feDropShadow = "feDropShadow",
+    feFlood = "feFlood",
+    feFuncA = "feFuncA",
+    feFuncB = "feFuncB",
+    feFuncG = "feFuncG",
+    feFuncR = "feFuncR",
+    feGaussianBlur = "feGaussianBlur",
+    feImage = "feImage",
+    feMerge = "feMerge",
+    feMergeNode = "feMergeNode",
+    feMorphology = "feMorphology",
+    feOffset = "feOffset",
+    fePointLight = "fePointLight",
+    feSpecularLighting = "feSpecularLighting",
+    feSpotLight = "feSpotLight",
+    feTile = "feTile",
+    feTurbulence = "feTurbulence",
+    feblend = "feblend",
+    fecolormatrix = "fecolormatrix",
+    fecomponenttransfer = "fecomponenttransfer",
+    fecomposite = "fecomposite",
+    feconvolvematrix = "feconvolvematrix",
+    fediffuselighting = "fediffuselighting",
+    fedisplacementmap = "fedisplacementmap",
+    fedistantlight = "fedistantlight",
+    fedropshadow = "fedropshadow",
+    feflood = "feflood",
+    fefunca = "fefunca",
+    fefuncb = "fefuncb",
+    fefuncg = "fefuncg",
+    fefuncr = "fefuncr",
+    fegaussianblur = "fegaussianblur",
+    feimage = "feimage",
+    femerge = "femerge",
+    femergenode = "femergenode",
+    femorphology = "femorphology",
+    fence = "fence",
+    feoffset = "feoffset",
+    fepointlight = "fepointlight",
+    fespecularlighting = "fespecularlighting",
+    fespotlight = "fespotlight",
+    fetch = "fetch",
+    fetchpriority = "fetchpriority",
+    fetile = "fetile",
+    feturbulence = "feturbulence",
+    fieldset = "fieldset",
+    figcaption = "figcaption",
+    figure = "figure",
+    fill = "fill",
+    fill_opacity = "fill-opacity",
+    fill_rule = "fill-rule",
+    filter = "filter",
+    filterRes = "filterRes",
+    filterUnits = "filterUnits",
+    filterres = "filterres",
+    filterunits = "filterunits",
+    flood_color = "flood-color",
+    flood_opacity = "flood-opacity",
+    floor = "floor",
+    r#fn = "fn",
+    font = "font",
+    font_face = "font-face",
+    font_face_format = "font-face-format",
+    font_face_name = "font-face-name",
+    font_face_src = "font-face-src",
+    font_face_uri = "font-face-uri",
+    font_family = "font-family",
+    font_size = "font-size",
+    font_size_adjust = "font-size-adjust",
+    font_stretch = "font-stretch",
+    font_style = "font-style",
+    font_variant = "font-variant",
+    font_weight = "font-weight",
+    fontfamily = "fontfamily",
+    fontsize = "fontsize",
+    fontstyle = "fontstyle",
+    fontweight = "fontweight",
+    footer = "footer",
+    r#for = "for",
+    forall = "forall",
+    foreignObject = "foreignObject",
+    foreignobject = "foreignobject",
+    form = "form",
+    formaction = "formaction",
+    format = "format",
+    formenctype = "formenctype",
+    formmethod = "formmethod",
+    formnovalidate = "formnovalidate",
+    formtarget = "formtarget",
+    frame = "frame",
+    frameborder = "frameborder",
+    frameset = "frameset",
+    framespacing = "framespacing",
+    from = "from",
+    fx = "fx",
+    fy = "fy",
+    g = "g",
+    g1 = "g1",
+    g2 = "g2",
+    gcd = "gcd",
+    geq = "geq",
+    glyph = "glyph",
+    glyph_name = "glyph-name",
+    glyph_orientation_horizontal = "glyph-orientation-horizontal",
+    glyph_orientation_vertical = "glyph-orientation-vertical",
+    glyphRef = "glyphRef",
+    glyphref = "glyphref",
+    grad = "grad",
+    gradientTransform = "gradientTransform",
+    gradientUnits = "gradientUnits",
+    gradienttransform = "gradienttransform",
+    gradientunits = "gradientunits",
+    groupalign = "groupalign",
+    gt = "gt",
+    h1 = "h1",
+    h2 = "h2",
+    h3 = "h3",
+    h4 = "h4",
+    h5 = "h5",
+    h6 = "h6",
+    handler = "handler",
+    hanging = "hanging",
+    head = "head",
+    header = "header",
+    headers = "headers",
+    height = "height",
+    hgroup = "hgroup",
+    hidden = "hidden",
+    hidefocus = "hidefocus",
+    high = "high",
+    hkern = "hkern",
+    horiz_adv_x = "horiz-adv-x",
+    horiz_origin_x = "horiz-origin-x",
+    horiz_origin_y = "horiz-origin-y",
+    hr = "hr",
+    href = "href",
+    hreflang = "hreflang",
+    hspace = "hspace",
+    html = "html",
+    http_equiv = "http-equiv",
+    i = "i",
+    icon = "icon",
+    id = "id",
+    ident = "ident",
+    ideographic = "ideographic",
+    iframe = "iframe",
+    image = "image",
+    image_rendering = "image-rendering",
+    imaginary = "imaginary",
+    imaginaryi = "imaginaryi",
+    img = "img",
+    implies = "implies",
+    r#in = "in",
+    in2 = "in2",
+    index = "index",
+    infinity = "infinity",
+    input = "input",
+    inputmode = "inputmode",
+    ins = "ins",
+    int = "int",
+    integers = "integers",
+    integrity = "integrity",
+    intercept = "intercept",
+    intersect = "intersect",
+    interval = "interval",
+    inverse = "inverse",
+    irrelevant = "irrelevant",
+    isindex = "isindex",
+    ismap = "ismap",
+    itemid = "itemid",
+    itemprop = "itemprop",
+    itemref = "itemref",
+    itemscope = "itemscope",
+    itemtype = "itemtype",
+    k = "k",
+    k1 = "k1",
+    k2 = "k2",
+    k3 = "k3",
+    k4 = "k4",
+    kbd = "kbd",
+    kernelMatrix = "kernelMatrix",
+    kernelUnitLength = "kernelUnitLength",
+    kernelmatrix = "kernelmatrix",
+    kernelunitlength = "kernelunitlength",
+    kerning = "kerning",
+    keyPoints = "keyPoints",
+    keySplines = "keySplines",
+    keyTimes = "keyTimes",
+    keygen = "keygen",
+    keypoints = "keypoints",
+    keysplines = "keysplines",
+    keytimes = "keytimes",
+    kind = "kind",
+    label = "label",
+    lambda = "lambda",
+    lang = "lang",
+    language = "language",
+    laplacian = "laplacian",
+    largeop = "largeop",
+    lcm = "lcm",
+    legend = "legend",
+    lengthAdjust = "lengthAdjust",
+    lengthadjust = "lengthadjust",
+    leq = "leq",
+    letter_spacing = "letter-spacing",
+    li = "li",
+    lighting_color = "lighting-color",
+    limit = "limit",
+    limitingConeAngle = "limitingConeAngle",
+    limitingconeangle = "limitingconeangle",
+    line = "line",
+    line_height = "line-height",
+    linearGradient = "linearGradient",
+    lineargradient = "lineargradient",
+    linebreak = "linebreak",
+    linethickness = "linethickness",
+    link = "link",
+    list = "list",
+    listener = "listener",
+    listing = "listing",
+    ln = "ln",
+    loading = "loading",
+    local = "local",
+    log = "log",
+    logbase = "logbase",
+    longdesc = "longdesc",
+    r#loop = "loop",
+    low = "low",
+    lowlimit = "lowlimit",
+    lowsrc = "lowsrc",
+    lquote = "lquote",
+    lspace = "lspace",
+    lt = "lt",
+    macros = "macros",
+    maction = "maction",
+    main = "main",
+    maligngroup = "maligngroup",
+    malignmark = "malignmark",
+    manifest = "manifest",
+    map = "map",
+    marginheight = "marginheight",
+    marginwidth = "marginwidth",
+    mark = "mark",
+    marker = "marker",
+    marker_end = "marker-end",
+    marker_mid = "marker-mid",
+    marker_start = "marker-start",
+    markerHeight = "markerHeight",
+    markerUnits = "markerUnits",
+    markerWidth = "markerWidth",
+    markerheight = "markerheight",
+    markerunits = "markerunits",
+    markerwidth = "markerwidth",
+    marquee = "marquee",
+    mask = "mask",
+    maskContentUnits = "maskContentUnits",
+    maskUnits = "maskUnits",
+    maskcontentunits = "maskcontentunits",
+    maskunits = "maskunits",
+    math = "math",
+    mathbackground = "mathbackground",
+    mathcolor = "mathcolor",
+    mathematical = "mathematical",
+    mathsize = "mathsize",
+    mathvariant = "mathvariant",
+    matrix = "matrix",
+    matrixrow = "matrixrow",
+    max = "max",
+    maxlength = "maxlength",
+    maxsize = "maxsize",
+    mean = "mean",
+    media = "media",
+    median = "median",
+    mediummathspace = "mediummathspace",
+    menclose = "menclose",
+    menu = "menu",
+    menuitem = "menuitem",
+    merror = "merror",
+    meta = "meta",
+    metadata = "metadata",
+    meter = "meter",
+    method = "method",
+    mfenced = "mfenced",
+    mfrac = "mfrac",
+    mglyph = "mglyph",
+    mi = "mi",
+    min = "min",
+    minlength = "minlength",
+    minsize = "minsize",
+    minus = "minus",
+    missing_glyph = "missing-glyph",
+    mlabeledtr = "mlabeledtr",
+    mmultiscripts = "mmultiscripts",
+    mn = "mn",
+    mo = "mo",
+    mode = "mode",
+    moment = "moment",
+    momentabout = "momentabout",
+    movablelimits = "movablelimits",
+    mover = "mover",
+    mozbrowser = "mozbrowser",
+    mpadded = "mpadded",
+    mpath = "mpath",
+    mphantom = "mphantom",
+    mprescripts = "mprescripts",
+    mroot = "mroot",
+    mrow = "mrow",
+    ms = "ms",
+    mspace = "mspace",
+    msqrt = "msqrt",
+    mstyle = "mstyle",
+    msub = "msub",
+    msubsup = "msubsup",
+    msup = "msup",
+    mtable = "mtable",
+    mtd = "mtd",
+    mtext = "mtext",
+    mtr = "mtr",
+    multicol = "multicol",
+    multiple = "multiple",
+    munder = "munder",
+    munderover = "munderover",
+    muted = "muted",
+    name = "name",
+    nargs = "nargs",
+    naturalnumbers = "naturalnumbers",
+    nav = "nav",
+    neq = "neq",
+    nest = "nest",
+    nextid = "nextid",
+    nobr = "nobr",
+    noembed = "noembed",
+    noframes = "noframes",
+    nohref = "nohref",
+    nomodule = "nomodule",
+    nonce = "nonce",
+    none = "none",
+    noresize = "noresize",
+    noscript = "noscript",
+    noshade = "noshade",
+    not = "not",
+    notanumber = "notanumber",
+    notation = "notation",
+    notin = "notin",
+    notprsubset = "notprsubset",
+    notsubset = "notsubset",
+    novalidate = "novalidate",
+    nowrap = "nowrap",
+    numOctaves = "numOctaves",
+    numoctaves = "numoctaves",
+    object = "object",
+    occurrence = "occurrence",
+    offset = "offset",
+    ol = "ol",
+    onabort = "onabort",
+    onactivate = "onactivate",
+    onafterprint = "onafterprint",
+    onafterupdate = "onafterupdate",
+    onbefordeactivate = "onbefordeactivate",
+    onbeforeactivate = "onbeforeactivate",
+    onbeforecopy = "onbeforecopy",
+    onbeforecut = "onbeforecut",
+    onbeforeeditfocus = "onbeforeeditfocus",
+    onbeforepaste = "onbeforepaste",
+    onbeforeprint = "onbeforeprint",
+    onbeforeunload = "onbeforeunload",
+    onbeforeupdate = "onbeforeupdate",
+    onbegin = "onbegin",
+    onblur = "onblur",
+    onbounce = "onbounce",
+    oncellchange = "oncellchange",
+    onchange = "onchange",
+    onclick = "onclick",
+    oncontextmenu = "oncontextmenu",
+    oncontrolselect = "oncontrolselect",
+    oncopy = "oncopy",
+    oncut = "oncut",
+    ondataavailable = "ondataavailable",
+    ondatasetchanged = "ondatasetchanged",
+    ondatasetcomplete = "ondatasetcomplete",
+    ondblclick = "ondblclick",
+    ondeactivate = "ondeactivate",
+    ondrag = "ondrag",
+    ondragdrop = "ondragdrop",
+    ondragend = "ondragend",
+    ondragenter = "ondragenter",
+    ondragleave = "ondragleave",
+    ondragover = "ondragover",
+    ondragstart = "ondragstart",
+    ondrop = "ondrop",
+    onend = "onend",
+    onerror = "onerror",
+    onerrorupdate = "onerrorupdate",
+    onfilterchange = "onfilterchange",
+    onfinish = "onfinish",
+    onfocus = "onfocus",
+    onfocusin = "onfocusin",
+    onfocusout = "onfocusout",
+    onformchange = "onformchange",
+    onforminput = "onforminput",
+    onhashchange = "onhashchange",
+    onhelp = "onhelp",
+    oninput = "oninput",
+    oninvalid = "oninvalid",
+    onkeydown = "onkeydown",
+    onkeypress = "onkeypress",
+    onkeyup = "onkeyup",
+    onlanguagechange = "onlanguagechange",
+    onload = "onload",
+    onlosecapture = "onlosecapture",
+    onmessage = "onmessage",
+    onmousedown = "onmousedown",
+    onmouseenter = "onmouseenter",
+    onmouseleave = "onmouseleave",
+    onmousemove = "onmousemove",
+    onmouseout = "onmouseout",
+    onmouseover = "onmouseover",
+    onmouseup = "onmouseup",
+    onmousewheel = "onmousewheel",
+    onmove = "onmove",
+    onmoveend = "onmoveend",
+    onmovestart = "onmovestart",
+    onoffline = "onoffline",
+    ononline = "ononline",
+    onpagehide = "onpagehide",
+    onpageshow = "onpageshow",
+    onpaste = "onpaste",
+    onpopstate = "onpopstate",
+    onpropertychange = "onpropertychange",
+    onreadystatechange = "onreadystatechange",
+    onrepeat = "onrepeat",
+    onreset = "onreset",
+    onresize = "onresize",
+    onrowenter = "onrowenter",
+    onrowexit = "onrowexit",
+    onrowsdelete = "onrowsdelete",
+    onrowsinserted = "onrowsinserted",
+    onscroll = "onscroll",
+    onselect = "onselect",
+    onselectstart = "onselectstart",
+    onstart = "onstart",
+    onstop = "onstop",
+    onstorage = "onstorage",
+    onsubmit = "onsubmit",
+    onunload = "onunload",
+    onzoom = "onzoom",
+    opacity = "opacity",
+    open = "open",
+    operator = "operator",
+    optgroup = "optgroup",
+    optimum = "optimum",
+    option = "option",
+    or = "or",
+    order = "order",
+    orient = "orient",
+    orientation = "orientation",
+    origin = "origin",
+    other = "other",
+    otherwise = "otherwise",
+    outerproduct = "outerproduct",
+    output = "output",
+    overflow = "overflow",
+    overline_position = "overline-position",
+    overline_thickness = "overline-thickness",
+    p = "p",
+    panose_1 = "panose-1",
+    param = "param",
+    parse = "parse",
+    partialdiff = "partialdiff",
+    path = "path",
+    pathLength = "pathLength",
+    pathlength = "pathlength",
+    pattern = "pattern",
+    patternContentUnits = "patternContentUnits",
+    patternTransform = "patternTransform",
+    patternUnits = "patternUnits",
+    patterncontentunits = "patterncontentunits",
+    patterntransform = "patterntransform",
+    patternunits = "patternunits",
+    pi = "pi",
+    picture = "picture",
+    piece = "piece",
+    piecewise = "piecewise",
+    ping = "ping",
+    placeholder = "placeholder",
+    plaintext = "plaintext",
+    plus = "plus",
+    pointer_events = "pointer-events",
+    points = "points",
+    pointsAtX = "pointsAtX",
+    pointsAtY = "pointsAtY",
+    pointsAtZ = "pointsAtZ",
+    pointsatx = "pointsatx",
+    pointsaty = "pointsaty",
+    pointsatz = "pointsatz",
+    polygon = "polygon",
+    polyline = "polyline",
+    poster = "poster",
+    power = "power",
+    pre = "pre",
+    prefetch = "prefetch",
+    preload = "preload",
+    preserveAlpha = "preserveAlpha",
+    preserveAspectRatio = "preserveAspectRatio",
+    preservealpha = "preservealpha",
+    preserveaspectratio = "preserveaspectratio",
+    primes = "primes",
+    primitiveUnits = "primitiveUnits",
+    primitiveunits = "primitiveunits",
+    product = "product",
+    profile = "profile",
+    progress = "progress",
+    prompt = "prompt",
+    property = "property",
+    prsubset = "prsubset",
+    q = "q",
+    quotient = "quotient",
+    r = "r",
+    radialGradient = "radialGradient",
+    radialgradient = "radialgradient",
+    radiogroup = "radiogroup",
+    radius = "radius",
+    rationals = "rationals",
+    rb = "rb",
+    readonly = "readonly",
+    real = "real",
+    reals = "reals",
+    rect = "rect",
+    refX = "refX",
+    refY = "refY",
+    referrerpolicy = "referrerpolicy",
+    refx = "refx",
+    refy = "refy",
+    rel = "rel",
+    reln = "reln",
+    rem = "rem",
+    rendering_intent = "rendering-intent",
+    repeat = "repeat",
+    repeat_max = "repeat-max",
+    repeat_min = "repeat-min",
+    repeat_start = "repeat-start",
+    repeat_template = "repeat-template",
+    repeatCount = "repeatCount",
+    repeatDur = "repeatDur",
+    repeatcount = "repeatcount",
+    repeatdur = "repeatdur",
+    replace = "replace",
+    required = "required",
+    requiredExtensions = "requiredExtensions",
+    requiredFeatures = "requiredFeatures",
+    requiredextensions = "requiredextensions",
+    requiredfeatures = "requiredfeatures",
+    restart = "restart",
+    result = "result",
+    rev = "rev",
+    reversed = "reversed",
+    role = "role",
+    root = "root",
+    rotate = "rotate",
+    rowalign = "rowalign",
+    rowlines = "rowlines",
+    rows = "rows",
+    rowspacing = "rowspacing",
+    rowspan = "rowspan",
+    rp = "rp",
+    rquote = "rquote",
+    rspace = "rspace",
+    rt = "rt",
+    rtc = "rtc",
+    ruby = "ruby",
+    rule = "rule",
+    rules = "rules",
+    rx = "rx",
+    ry = "ry",
+    s = "s",
+    samp = "samp",
+    sandbox = "sandbox",
+    scalarproduct = "scalarproduct",
+    scale = "scale",
+    scheme = "scheme",
+    scope = "scope",
+    scoped = "scoped",
+    script = "script",
+    scriptlevel = "scriptlevel",
+    scriptminsize = "scriptminsize",
+    scriptsizemultiplier = "scriptsizemultiplier",
+    scrolldelay = "scrolldelay",
+    scrolling = "scrolling",
+    sdev = "sdev",
+    seamless = "seamless",
+    search = "search",
+    sec = "sec",
+    sech = "sech",
+    section = "section",
+    seed = "seed",
+    select = "select",
+    selected = "selected",
+    selection = "selection",
+    selector = "selector",
+    semantics = "semantics",
+    sep = "sep",
+    separator = "separator",
+    separators = "separators",
+    set = "set",
+    setdiff = "setdiff",
+    shadowrootclonable = "shadowrootclonable",
+    shadowrootdelegatesfocus = "shadowrootdelegatesfocus",
+    shadowrootmode = "shadowrootmode",
+    shadowrootserializable = "shadowrootserializable",
+    shape = "shape",
+    shape_rendering = "shape-rendering",
+    show = "show",
+    sin = "sin",
+    sinh = "sinh",
+    size = "size",
+    sizes = "sizes",
+    slope = "slope",
+    slot = "slot",
+    small = "small",
+    solidcolor = "solidcolor",
+    source = "source",
+    space = "space",
+    spacer = "spacer",
+    spacing = "spacing",
+    span = "span",
+    specification = "specification",
+    specularConstant = "specularConstant",
+    specularExponent = "specularExponent",
+    specularconstant = "specularconstant",
+    specularexponent = "specularexponent",
+    speed = "speed",
+    spellcheck = "spellcheck",
+    spreadMethod = "spreadMethod",
+    spreadmethod = "spreadmethod",
+    src = "src",
+    srcdoc = "srcdoc",
+    srclang = "srclang",
+    srcset = "srcset",
+    standby = "standby",
+    start = "start",
+    startOffset = "startOffset",
+    startoffset = "startoffset",
+    statechange = "statechange",
+    stdDeviation = "stdDeviation",
+    stddeviation = "stddeviation",
+    stemh = "stemh",
+    stemv = "stemv",
+    step = "step",
+    stitchTiles = "stitchTiles",
+    stitchtiles = "stitchtiles",
+    stop = "stop",
+    stop_color = "stop-color",
+    stop_opacity = "stop-opacity",
+    stretchy = "stretchy",
+    strike = "strike",
+    strikethrough_position = "strikethrough-position",
+    strikethrough_thickness = "strikethrough-thickness",
+    string = "string",
+    stroke = "stroke",
+    stroke_dasharray = "stroke-dasharray",
+    stroke_dashoffset = "stroke-dashoffset",
+    stroke_linecap = "stroke-linecap",
+    stroke_linejoin = "stroke-linejoin",
+    stroke_miterlimit = "stroke-miterlimit",
+    stroke_opacity = "stroke-opacity",
+    stroke_width = "stroke-width",
+    strong = "strong",
+    style = "style",
+    sub = "sub",
+    subscriptshift = "subscriptshift",
+    subset = "subset",
+    sum = "sum",
+    summary = "summary",
+    sup = "sup",
+    superscriptshift = "superscriptshift",
+    surfaceScale = "surfaceScale",
+    surfacescale = "surfacescale",
+    svg = "svg",
+    switch = "switch",
+    symbol = "symbol",
+    symmetric = "symmetric",
+    systemLanguage = "systemLanguage",
+    systemlanguage = "systemlanguage",
+    tabindex = "tabindex",
+    table = "table",
+    tableValues = "tableValues",
+    tablevalues = "tablevalues",
+    tan = "tan",
+    tanh = "tanh",
+    target = "target",
+    targetX = "targetX",
+    targetY = "targetY",
+    targetx = "targetx",
+    targety = "targety",
+    tbody = "tbody",
+    tbreak = "tbreak",
+    td = "td",
+    template = "template",
+    tendsto = "tendsto",
+    text = "text",
+    text_anchor = "text-anchor",
+    text_decoration = "text-decoration",
+    text_rendering = "text-rendering",
+    textLength = "textLength",
+    textPath = "textPath",
+    textarea = "textarea",
+    textlength = "textlength",
+    textpath = "textpath",
+    tfoot = "tfoot",
+    th = "th",
+    thead = "thead",
+    thickmathspace = "thickmathspace",
+    thinmathspace = "thinmathspace",
+    time = "time",
+    times = "times",
+    title = "title",
+    to = "to",
+    toggle = "toggle",
+    tr = "tr",
+    track = "track",
+    transform = "transform",
+    translate = "translate",
+    transpose = "transpose",
+    tref = "tref",
+    r#true = "true",
+    tspan = "tspan",
+    tt = "tt",
+    r#type = "type",
+    u = "u",
+    u1 = "u1",
+    u2 = "u2",
+    ul = "ul",
+    underline_position = "underline-position",
+    underline_thickness = "underline-thickness",
+    unicode = "unicode",
+    unicode_bidi = "unicode-bidi",
+    unicode_range = "unicode-range",
+    r#union = "union",
+    units_per_em = "units-per-em",
+    unselectable = "unselectable",
+    uplimit = "uplimit",
+    r#use = "use",
+    usemap = "usemap",
+    v_alphabetic = "v-alphabetic",
+    v_hanging = "v-hanging",
+    v_ideographic = "v-ideographic",
+    v_mathematical = "v-mathematical",
+    valign = "valign",
+    value = "value",
+    values = "values",
+    valuetype = "valuetype",
+    var = "var",
+    variance = "variance",
+    vector = "vector",
+    vectorproduct = "vectorproduct",
+    version = "version",
+    vert_adv_y = "vert-adv-y",
+    vert_origin_x = "vert-origin-x",
+    vert_origin_y = "vert-origin-y",
+    verythickmathspace = "verythickmathspace",
+    verythinmathspace = "verythinmathspace",
+    veryverythickmathspace = "veryverythickmathspace",
+    veryverythinmathspace = "veryverythinmathspace",
+    video = "video",
+    view = "view",
+    viewBox = "viewBox",
+    viewTarget = "viewTarget",
+    viewbox = "viewbox",
+    viewtarget = "viewtarget",
+    visibility = "visibility",
+    vkern = "vkern",
+    vlink = "vlink",
+    vspace = "vspace",
+    wbr = "wbr",
+    when = "when",
+    width = "width",
+    widths = "widths",
+    word_spacing = "word-spacing",
+    wrap = "wrap",
+    writing_mode = "writing-mode",
+    x = "x",
+    x_height = "x-height",
+    x1 = "x1",
+    x2 = "x2",
+    xChannelSelector = "xChannelSelector",
+    xchannelselector = "xchannelselector",
+    xlink = "xlink",
+    xlink_actuate = "xlink:actuate",
+    xlink_arcrole = "xlink:arcrole",
+    xlink_href = "xlink:href",
+    xlink_role = "xlink:role",
+    xlink_show = "xlink:show",
+    xlink_title = "xlink:title",
+    xlink_type = "xlink:type",
+    xml_base = "xml:base",
+    xml_lang = "xml:lang",
+    xml_space = "xml:space",
+    xmlns = "xmlns",
+    xmlns_xlink = "xmlns:xlink",
+    xmp = "xmp",
+    xor = "xor",
+    xref = "xref",
+    y = "y",
+    y1 = "y1",
+    y2 = "y2",
+    yChannelSelector = "yChannelSelector",
+    ychannelselector = "ychannelselector",
+    z = "z",
+    zoomAndPan = "zoomAndPan",
+    zoomandpan = "zoomandpan",
+}
+
+#[cfg(test)]
+mod tests {
+    use super::STATIC_NAMES;
+
+    #[test]
+    fn static_names_are_sorted_and_unique() {
+        if let Some(pair) = STATIC_NAMES.windows(2).find(|pair| pair[0] >= pair[1]) {
+            panic!("{:?} must come after {:?}", pair[0], pair[1]);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-mod tokenizer;
+use rust_scraper::tokenizer;
 
 fn main() {
     // let response = reqwest::blocking::get("http://example.com/").unwrap();
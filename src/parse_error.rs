@@ -0,0 +1,259 @@
+//! Parse errors defined by the HTML Standard, and the positions and spans
+//! in the input they and tokens are reported at.
+//!
+//! https://html.spec.whatwg.org/multipage/parsing.html#parse-errors
+
+use std::fmt;
+
+/// A location in the input: a 1-based line and column (counted in
+/// characters) and a 0-based byte offset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Position {
+    /// The position of the first character of the input.
+    pub fn start() -> Position {
+        Position {
+            line: 1,
+            column: 1,
+            offset: 0,
+        }
+    }
+
+    /// Moves the position past `ch`.
+    pub fn advance(&mut self, ch: char) {
+        self.offset += ch.len_utf8();
+
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+
+    /// Moves the position past `text`, which has no CRs.
+    pub fn advance_str(&mut self, text: &str) {
+        self.offset += text.len();
+
+        match memchr::memrchr(b'\n', text.as_bytes()) {
+            Some(last_line_feed) => {
+                self.line += memchr::memchr_iter(b'\n', text.as_bytes()).count();
+                self.column = 1 + text[last_line_feed + 1..].chars().count();
+            }
+            None => self.column += text.chars().count(),
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Position {
+        Position::start()
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A range of the input, from `start` up to but not including `end`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// An empty span at `position`.
+    pub fn empty(position: Position) -> Span {
+        Span {
+            start: position,
+            end: position,
+        }
+    }
+
+    /// The byte range of the span in the input.
+    pub fn bytes(&self) -> std::ops::Range<usize> {
+        self.start.offset..self.end.offset
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start.offset == self.end.offset
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ParseError {
+    AbruptClosingOfEmptyComment,
+    AbruptDoctypePublicIdentifier,
+    AbruptDoctypeSystemIdentifier,
+    AbsenceOfDigitsInNumericCharacterReference,
+    CdataInHtmlContent,
+    CharacterReferenceOutsideUnicodeRange,
+    ControlCharacterInInputStream,
+    ControlCharacterReference,
+    DuplicateAttribute,
+    EndTagWithAttributes,
+    EndTagWithTrailingSolidus,
+    EofBeforeTagName,
+    EofInCdata,
+    EofInComment,
+    EofInDoctype,
+    EofInScriptHtmlCommentLikeText,
+    EofInTag,
+    IncorrectlyClosedComment,
+    IncorrectlyOpenedComment,
+    InvalidCharacterSequenceAfterDoctypeName,
+    InvalidFirstCharacterOfTagName,
+    MissingAttributeValue,
+    MissingDoctypeName,
+    MissingDoctypePublicIdentifier,
+    MissingDoctypeSystemIdentifier,
+    MissingEndTagName,
+    MissingQuoteBeforeDoctypePublicIdentifier,
+    MissingQuoteBeforeDoctypeSystemIdentifier,
+    MissingSemicolonAfterCharacterReference,
+    MissingWhitespaceAfterDoctypePublicKeyword,
+    MissingWhitespaceAfterDoctypeSystemKeyword,
+    MissingWhitespaceBeforeDoctypeName,
+    MissingWhitespaceBetweenAttributes,
+    MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers,
+    NestedComment,
+    NoncharacterCharacterReference,
+    NoncharacterInInputStream,
+    NonVoidHtmlElementStartTagWithTrailingSolidus,
+    NullCharacterReference,
+    SurrogateCharacterReference,
+    SurrogateInInputStream,
+    UnexpectedCharacterAfterDoctypeSystemIdentifier,
+    UnexpectedCharacterInAttributeName,
+    UnexpectedCharacterInUnquotedAttributeValue,
+    UnexpectedEqualsSignBeforeAttributeName,
+    UnexpectedNullCharacter,
+    UnexpectedQuestionMarkInsteadOfTagName,
+    UnexpectedSolidusInTag,
+    UnknownNamedCharacterReference,
+}
+
+impl ParseError {
+    /// The error code used by the HTML Standard, e.g. `eof-in-tag`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::AbruptClosingOfEmptyComment => "abrupt-closing-of-empty-comment",
+            ParseError::AbruptDoctypePublicIdentifier => "abrupt-doctype-public-identifier",
+            ParseError::AbruptDoctypeSystemIdentifier => "abrupt-doctype-system-identifier",
+            ParseError::AbsenceOfDigitsInNumericCharacterReference => {
+                "absence-of-digits-in-numeric-character-reference"
+            }
+            ParseError::CdataInHtmlContent => "cdata-in-html-content",
+            ParseError::CharacterReferenceOutsideUnicodeRange => {
+                "character-reference-outside-unicode-range"
+            }
+            ParseError::ControlCharacterInInputStream => "control-character-in-input-stream",
+            ParseError::ControlCharacterReference => "control-character-reference",
+            ParseError::DuplicateAttribute => "duplicate-attribute",
+            ParseError::EndTagWithAttributes => "end-tag-with-attributes",
+            ParseError::EndTagWithTrailingSolidus => "end-tag-with-trailing-solidus",
+            ParseError::EofBeforeTagName => "eof-before-tag-name",
+            ParseError::EofInCdata => "eof-in-cdata",
+            ParseError::EofInComment => "eof-in-comment",
+            ParseError::EofInDoctype => "eof-in-doctype",
+            ParseError::EofInScriptHtmlCommentLikeText => "eof-in-script-html-comment-like-text",
+            ParseError::EofInTag => "eof-in-tag",
+            ParseError::IncorrectlyClosedComment => "incorrectly-closed-comment",
+            ParseError::IncorrectlyOpenedComment => "incorrectly-opened-comment",
+            ParseError::InvalidCharacterSequenceAfterDoctypeName => {
+                "invalid-character-sequence-after-doctype-name"
+            }
+            ParseError::InvalidFirstCharacterOfTagName => "invalid-first-character-of-tag-name",
+            ParseError::MissingAttributeValue => "missing-attribute-value",
+            ParseError::MissingDoctypeName => "missing-doctype-name",
+            ParseError::MissingDoctypePublicIdentifier => "missing-doctype-public-identifier",
+            ParseError::MissingDoctypeSystemIdentifier => "missing-doctype-system-identifier",
+            ParseError::MissingEndTagName => "missing-end-tag-name",
+            ParseError::MissingQuoteBeforeDoctypePublicIdentifier => {
+                "missing-quote-before-doctype-public-identifier"
+            }
+            ParseError::MissingQuoteBeforeDoctypeSystemIdentifier => {
+                "missing-quote-before-doctype-system-identifier"
+            }
+            ParseError::MissingSemicolonAfterCharacterReference => {
+                "missing-semicolon-after-character-reference"
+            }
+            ParseError::MissingWhitespaceAfterDoctypePublicKeyword => {
+                "missing-whitespace-after-doctype-public-keyword"
+            }
+            ParseError::MissingWhitespaceAfterDoctypeSystemKeyword => {
+                "missing-whitespace-after-doctype-system-keyword"
+            }
+            ParseError::MissingWhitespaceBeforeDoctypeName => {
+                "missing-whitespace-before-doctype-name"
+            }
+            ParseError::MissingWhitespaceBetweenAttributes => {
+                "missing-whitespace-between-attributes"
+            }
+            ParseError::MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers => {
+                "missing-whitespace-between-doctype-public-and-system-identifiers"
+            }
+            ParseError::NestedComment => "nested-comment",
+            ParseError::NoncharacterCharacterReference => "noncharacter-character-reference",
+            ParseError::NoncharacterInInputStream => "noncharacter-in-input-stream",
+            ParseError::NonVoidHtmlElementStartTagWithTrailingSolidus => {
+                "non-void-html-element-start-tag-with-trailing-solidus"
+            }
+            ParseError::NullCharacterReference => "null-character-reference",
+            ParseError::SurrogateCharacterReference => "surrogate-character-reference",
+            ParseError::SurrogateInInputStream => "surrogate-in-input-stream",
+            ParseError::UnexpectedCharacterAfterDoctypeSystemIdentifier => {
+                "unexpected-character-after-doctype-system-identifier"
+            }
+            ParseError::UnexpectedCharacterInAttributeName => {
+                "unexpected-character-in-attribute-name"
+            }
+            ParseError::UnexpectedCharacterInUnquotedAttributeValue => {
+                "unexpected-character-in-unquoted-attribute-value"
+            }
+            ParseError::UnexpectedEqualsSignBeforeAttributeName => {
+                "unexpected-equals-sign-before-attribute-name"
+            }
+            ParseError::UnexpectedNullCharacter => "unexpected-null-character",
+            ParseError::UnexpectedQuestionMarkInsteadOfTagName => {
+                "unexpected-question-mark-instead-of-tag-name"
+            }
+            ParseError::UnexpectedSolidusInTag => "unexpected-solidus-in-tag",
+            ParseError::UnknownNamedCharacterReference => "unknown-named-character-reference",
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// A parse error together with the position of the input character that
+/// caused it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PositionedError {
+    pub error: ParseError,
+    pub position: Position,
+}
+
+impl fmt::Display for PositionedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.error)
+    }
+}
@@ -1,67 +1,351 @@
 #![allow(unused)]
 
-use reqwest::header::DATE;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+
+use encoding_rs::Encoding;
+use memchr::{memchr, memchr2, memchr3};
+
+use crate::encoding::{self, Confidence, EncodingDecision, EncodingSource};
+use crate::entities;
+use crate::local_name::{self, LocalName};
+use crate::parse_error::{ParseError, Position, PositionedError, Span};
+
+/// An attribute of a tag token.
+#[derive(Clone, Debug)]
+pub struct Attribute<'a> {
+    pub(crate) name: LocalName,
+    pub(crate) value: Cow<'a, str>,
+    pub(crate) name_span: Span,
+    /// Inside the quotes, if any. An attribute without a value has an empty
+    /// value span where its name ends.
+    pub(crate) value_span: Span,
+}
+
+impl<'a> Attribute<'a> {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn local_name(&self) -> &LocalName {
+        &self.name
+    }
+
+    /// The value, with character references decoded.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn name_span(&self) -> Span {
+        self.name_span
+    }
 
-pub struct Attribute {
-    name: String,
-    value: String,
+    pub fn value_span(&self) -> Span {
+        self.value_span
+    }
 }
 
-struct Doctype {
-    name: Option<String>,
-    public_id: Option<String>,
-    system_id: Option<String>,
-    force_quirks: bool,
+/// A DOCTYPE token. Missing names and identifiers are `None`, which is not
+/// the same as empty.
+#[derive(Clone, Debug, Default)]
+pub struct Doctype {
+    pub(crate) name: Option<String>,
+    pub(crate) public_id: Option<String>,
+    pub(crate) system_id: Option<String>,
+    pub(crate) force_quirks: bool,
+    pub(crate) span: Span,
 }
-struct StartTag {
-    tag_name: String,
-    self_closing: bool,
-    attributes: Vec<Attribute>,
+
+impl Doctype {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn public_id(&self) -> Option<&str> {
+        self.public_id.as_deref()
+    }
+
+    pub fn system_id(&self) -> Option<&str> {
+        self.system_id.as_deref()
+    }
+
+    /// Whether the DOCTYPE was malformed enough to put the document in
+    /// quirks mode.
+    pub fn force_quirks(&self) -> bool {
+        self.force_quirks
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StartTag<'a> {
+    pub(crate) tag_name: LocalName,
+    pub(crate) self_closing: bool,
+    pub(crate) attributes: Vec<Attribute<'a>>,
+    pub(crate) span: Span,
+}
+
+impl<'a> StartTag<'a> {
+    pub fn name(&self) -> &str {
+        self.tag_name.as_str()
+    }
+
+    pub fn local_name(&self) -> &LocalName {
+        &self.tag_name
+    }
+
+    /// The attributes in source order, without duplicates.
+    pub fn attrs(&self) -> &[Attribute<'a>] {
+        &self.attributes
+    }
+
+    /// The value of the attribute called `name`, if the tag has one.
+    pub fn get_attr(&self, name: &str) -> Option<&str> {
+        get_attr(&self.attributes, name)
+    }
+
+    /// Whether the tag ends with `/>`.
+    pub fn is_self_closing(&self) -> bool {
+        self.self_closing
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// An end tag token. Attributes and a trailing `/` on an end tag are parse
+/// errors, and are dropped.
+#[derive(Clone, Debug)]
+pub struct EndTag<'a> {
+    pub(crate) tag_name: LocalName,
+    pub(crate) self_closing: bool,
+    pub(crate) attributes: Vec<Attribute<'a>>,
+    pub(crate) span: Span,
+}
+
+impl<'a> EndTag<'a> {
+    pub fn name(&self) -> &str {
+        self.tag_name.as_str()
+    }
+
+    pub fn local_name(&self) -> &LocalName {
+        &self.tag_name
+    }
+
+    /// Always empty, see `EndTag`.
+    pub fn attrs(&self) -> &[Attribute<'a>] {
+        &self.attributes
+    }
+
+    pub fn get_attr(&self, name: &str) -> Option<&str> {
+        get_attr(&self.attributes, name)
+    }
+
+    /// Always `false`, see `EndTag`.
+    pub fn is_self_closing(&self) -> bool {
+        self.self_closing
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
 }
-struct EndTag {
-    tag_name: String,
+
+/// The tag token being tokenized. Its name, and that of the attribute being
+/// tokenized, are collected in the tokenizer and made `LocalName`s once
+/// complete.
+struct Tag<'a> {
+    kind: TagKind,
+    self_closing: bool,
+    attributes: Vec<Attribute<'a>>,
 }
 
-enum Tag {
-    StartTag(StartTag),
-    EndTag(EndTag),
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TagKind {
+    Start,
+    End,
 }
 
-pub enum Token {
+/// A token, borrowing its text from the input where it can.
+///
+/// Attribute values, comments and text runs are slices of the input unless
+/// entity decoding or input preprocessing changed them, or the tokenizer
+/// was fed its input in chunks. Tag and attribute names are `LocalName`s.
+///
+/// Every token has the span of the markup it was tokenized from. The spans
+/// of consecutive tokens meet, except around end tags without a name and
+/// the ends of CDATA sections, which produce no token. Tokens compare equal
+/// regardless of their spans.
+///
+/// A token displays as HTML, escaped as the HTML Standard serializes it:
+///
+/// ```
+/// use rust_scraper::tokenizer;
+///
+/// let tokens = tokenizer::tokenize("<P id=x>Fish &amp; chips<br/>");
+/// let html: String = tokens.iter().map(|token| token.to_string()).collect();
+/// assert_eq!(html, r#"<p id="x">Fish &amp; chips<br />"#);
+/// ```
+#[derive(Clone, Debug)]
+pub enum Token<'a> {
     Doctype(Doctype),
-    StartTag(StartTag),
-    EndTag(EndTag),
-    Character(char),
-    Comment(String),
-    EndOfFile,
-}
-
-pub enum InsertionMode {
-    Initial,
-    BeforeHtml,
-    BeforeHead,
-    InHead,
-    InHeadNoscript,
-    AfterHead,
-    InBody,
-    Text,
-    InTable,
-    InTableText,
-    InCaption,
-    InColumnGroup,
-    InTableBody,
-    InRow,
-    InCell,
-    InSelect,
-    InSelectInTable,
-    InTemplate,
-    AfterBody,
-    InFrameset,
-    AfterFrameset,
-    AfterAfterBody,
-    AfterAfterFrameset,
+    StartTag(StartTag<'a>),
+    EndTag(EndTag<'a>),
+    Character(char, Span),
+    /// A run of characters, emitted instead of `Character` tokens unless
+    /// `TokenizerOptions::character_tokens` is set. A U+0000 is always a run
+    /// of its own, and a run is either all ASCII whitespace or starts with
+    /// a character that is not, so that tree construction can handle most
+    /// runs as a whole.
+    Text(Cow<'a, str>, Span),
+    Comment(Cow<'a, str>, Span),
+    /// Has an empty span at the end of the input.
+    EndOfFile(Span),
+}
+
+impl<'a> Token<'a> {
+    pub fn span(&self) -> Span {
+        match self {
+            Token::Doctype(doctype) => doctype.span,
+            Token::StartTag(tag) => tag.span,
+            Token::EndTag(tag) => tag.span,
+            Token::Character(_, span)
+            | Token::Text(_, span)
+            | Token::Comment(_, span)
+            | Token::EndOfFile(span) => *span,
+        }
+    }
+
+    fn span_mut(&mut self) -> &mut Span {
+        match self {
+            Token::Doctype(doctype) => &mut doctype.span,
+            Token::StartTag(tag) => &mut tag.span,
+            Token::EndTag(tag) => &mut tag.span,
+            Token::Character(_, span)
+            | Token::Text(_, span)
+            | Token::Comment(_, span)
+            | Token::EndOfFile(span) => span,
+        }
+    }
+}
+
+impl<'a> PartialEq for Attribute<'a> {
+    fn eq(&self, other: &Attribute<'a>) -> bool {
+        self.name == other.name && self.value == other.value
+    }
+}
+
+impl PartialEq for Doctype {
+    fn eq(&self, other: &Doctype) -> bool {
+        self.name == other.name
+            && self.public_id == other.public_id
+            && self.system_id == other.system_id
+            && self.force_quirks == other.force_quirks
+    }
+}
+
+impl<'a> PartialEq for StartTag<'a> {
+    fn eq(&self, other: &StartTag<'a>) -> bool {
+        self.tag_name == other.tag_name
+            && self.self_closing == other.self_closing
+            && self.attributes == other.attributes
+    }
+}
+
+impl<'a> PartialEq for EndTag<'a> {
+    fn eq(&self, other: &EndTag<'a>) -> bool {
+        self.tag_name == other.tag_name
+            && self.self_closing == other.self_closing
+            && self.attributes == other.attributes
+    }
+}
+
+impl<'a> PartialEq for Token<'a> {
+    fn eq(&self, other: &Token<'a>) -> bool {
+        match (self, other) {
+            (Token::Doctype(a), Token::Doctype(b)) => a == b,
+            (Token::StartTag(a), Token::StartTag(b)) => a == b,
+            (Token::EndTag(a), Token::EndTag(b)) => a == b,
+            (Token::Character(a, _), Token::Character(b, _)) => a == b,
+            (Token::Text(a, _), Token::Text(b, _)) => a == b,
+            (Token::Comment(a, _), Token::Comment(b, _)) => a == b,
+            (Token::EndOfFile(_), Token::EndOfFile(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<'a> fmt::Display for Attribute<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}=\"", self.name)?;
+        write_escaped(f, &self.value, true)?;
+        f.write_str("\"")
+    }
+}
+
+/// Whether the doctype forces quirks mode is not displayed, as the HTML
+/// Standard does not serialize it either.
+impl fmt::Display for Doctype {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<!DOCTYPE")?;
+        if let Some(name) = &self.name {
+            write!(f, " {}", name)?;
+        }
+        match (&self.public_id, &self.system_id) {
+            (Some(public_id), system_id) => {
+                write!(f, " PUBLIC {}", quoted_identifier(public_id))?;
+                if let Some(system_id) = system_id {
+                    write!(f, " {}", quoted_identifier(system_id))?;
+                }
+            }
+            (None, Some(system_id)) => write!(f, " SYSTEM {}", quoted_identifier(system_id))?,
+            (None, None) => {}
+        }
+        f.write_str(">")
+    }
+}
+
+impl<'a> fmt::Display for StartTag<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}", self.tag_name)?;
+        for attribute in &self.attributes {
+            write!(f, " {}", attribute)?;
+        }
+        if self.self_closing {
+            f.write_str(" /")?;
+        }
+        f.write_str(">")
+    }
+}
+
+impl<'a> fmt::Display for EndTag<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "</{}>", self.tag_name)
+    }
+}
+
+/// Text is escaped as in the data state, so the contents of `script` and
+/// other raw text elements, which HTML leaves unescaped, display escaped.
+impl<'a> fmt::Display for Token<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Doctype(doctype) => doctype.fmt(f),
+            Token::StartTag(tag) => tag.fmt(f),
+            Token::EndTag(tag) => tag.fmt(f),
+            Token::Character(ch, _) => write_escaped(f, ch.encode_utf8(&mut [0; 4]), false),
+            Token::Text(text, _) => write_escaped(f, text, false),
+            Token::Comment(text, _) => write!(f, "<!--{}-->", text),
+            Token::EndOfFile(_) => Ok(()),
+        }
+    }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum State {
     Data,
     Rcdata,
@@ -110,7 +394,7 @@ pub enum State {
     Comment,
     CommentLessThanSign,
     CommentLessThanSignBang,
-    CommentLessthanSignBangDash,
+    CommentLessThanSignBangDash,
     CommentLessThanSignBangDashDash,
     CommentEndDash,
     CommentEnd,
@@ -145,846 +429,2841 @@ pub enum State {
     NumericCharacterReferenceEnd,
 }
 
-pub fn tokenize(html: &String) -> Vec<Token> {
+/// How the tokenizer's state changes after it emits a start tag.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum StatePolicy {
+    /// Switch to the state the tree builder would after the start tags of
+    /// `title` and `textarea` (RCDATA), `style`, `xmp`, `iframe`, `noembed`
+    /// and `noframes` (RAWTEXT), `script` (script data) and `plaintext`
+    /// (PLAINTEXT), unless in foreign content.
+    #[default]
+    ByTagName,
+    /// Never switch by itself; the consumer calls `Tokenizer::set_state`.
+    Manual,
+}
+
+/// Options for `Tokenizer::with_options` and `tokenize_with_options`.
+#[derive(Default)]
+pub struct TokenizerOptions {
+    /// Whether the adjusted current node is an element in the SVG or MathML
+    /// namespace. CDATA sections are only recognized in foreign content; in
+    /// HTML content they are tokenized as bogus comments.
+    pub in_foreign_content: bool,
+    pub state_policy: StatePolicy,
+    /// Emit every character as its own `Token::Character` instead of
+    /// coalescing runs of text into `Token::Text`.
+    pub character_tokens: bool,
+}
+
+/// Receives tokens from `Tokenizer::run` one at a time, and tells the
+/// tokenizer how to go on.
+///
+/// ```
+/// use rust_scraper::tokenizer::{
+///     RunResult, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOptions,
+/// };
+///
+/// /// Collects the `content` of `meta` tags, stopping at `</head>`.
+/// struct Metadata(Vec<String>);
+///
+/// impl<'a> TokenSink<'a> for Metadata {
+///     fn process_token(&mut self, token: Token<'a>) -> TokenSinkResult {
+///         match token {
+///             Token::StartTag(tag) if tag.name() == "meta" => {
+///                 self.0.extend(tag.get_attr("content").map(String::from));
+///                 TokenSinkResult::Continue
+///             }
+///             Token::EndTag(tag) if tag.name() == "head" => TokenSinkResult::Abort,
+///             _ => TokenSinkResult::Continue,
+///         }
+///     }
+/// }
+///
+/// let html = r#"<head><meta name=description content="Fish"></head><body>…"#;
+/// let mut tokenizer = Tokenizer::borrowing(html, &TokenizerOptions::default());
+/// let mut metadata = Metadata(Vec::new());
+///
+/// assert_eq!(tokenizer.run(&mut metadata), RunResult::Aborted);
+/// assert_eq!(metadata.0, ["Fish"]);
+/// ```
+pub trait TokenSink<'a> {
+    fn process_token(&mut self, token: Token<'a>) -> TokenSinkResult;
+}
+
+/// Collects the tokens.
+impl<'a> TokenSink<'a> for Vec<Token<'a>> {
+    fn process_token(&mut self, token: Token<'a>) -> TokenSinkResult {
+        self.push(token);
+        TokenSinkResult::Continue
+    }
+}
+
+/// What the tokenizer does after a `TokenSink` has processed a token.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TokenSinkResult {
+    Continue,
+    /// Switch to a state, as the tree builder does after a `script` start
+    /// tag. Takes effect from the next input character.
+    SwitchTo(State),
+    /// Return from `Tokenizer::run`, to pick up after the token when it is
+    /// called again; e.g. to run a script at `</script>`.
+    Pause,
+    /// Stop tokenizing. Calling `Tokenizer::run` again produces no more
+    /// tokens.
+    Abort,
+}
+
+/// Why `Tokenizer::run` returned.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RunResult {
+    /// Every token up to and including `Token::EndOfFile` was processed.
+    Done,
+    /// Every token that can be produced from the input fed so far was
+    /// processed.
+    NeedsInput,
+    Paused,
+    Aborted,
+}
+
+pub fn tokenize(html: &str) -> Vec<Token<'_>> {
+    tokenize_with_options(html, &TokenizerOptions::default()).0
+}
+
+/// Tokenizes `html`, also returning the parse errors found along the way.
+pub fn tokenize_with_errors(html: &str) -> (Vec<Token<'_>>, Vec<PositionedError>) {
+    tokenize_with_options(html, &TokenizerOptions::default())
+}
+
+pub fn tokenize_with_options<'a>(
+    html: &'a str,
+    options: &TokenizerOptions,
+) -> (Vec<Token<'a>>, Vec<PositionedError>) {
+    let mut tokenizer = Tokenizer::borrowing(html, options);
+
+    let mut tokens = Vec::new();
+    tokenizer.run(&mut tokens);
+    (tokens, tokenizer.take_errors())
+}
+
+/// The result of `tokenize_bytes`.
+pub struct DecodedTokens {
+    pub tokens: Vec<Token<'static>>,
+    pub errors: Vec<PositionedError>,
+    /// The encoding the input was decoded with, and why it was chosen.
+    pub encoding: EncodingDecision,
+}
+
+/// Tokenizes a byte stream, determining its character encoding first.
+///
+/// `transport_charset` is the charset label from the transport layer, if
+/// any, e.g. the `charset` parameter of an HTTP `Content-Type` header. If
+/// the encoding is only tentative and a `<meta>` tag declares a different
+/// one, the input is decoded again with that encoding and tokenized from
+/// the start.
+pub fn tokenize_bytes(bytes: &[u8], transport_charset: Option<&str>) -> DecodedTokens {
+    let mut decision = encoding::sniff(bytes, transport_charset);
+
+    'decode: loop {
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.feed(&encoding::decode(bytes, &decision));
+        tokenizer.end();
+
+        let mut tokens = Vec::new();
+        for token in tokenizer.by_ref() {
+            if decision.confidence == Confidence::Tentative {
+                if let Some(declared) = declared_encoding(&token) {
+                    let declared = encoding::adjust_declared_encoding(declared);
+                    decision.confidence = Confidence::Certain;
+                    if declared != decision.encoding {
+                        decision.encoding = declared;
+                        decision.source = EncodingSource::MetaChange;
+                        continue 'decode;
+                    }
+                }
+            }
+            tokens.push(token);
+        }
+
+        return DecodedTokens {
+            tokens,
+            errors: tokenizer.take_errors(),
+            encoding: decision,
+        };
+    }
+}
+
+/// The encoding declared by a `<meta charset>` or
+/// `<meta http-equiv="content-type">` start tag.
+fn declared_encoding(token: &Token<'_>) -> Option<&'static Encoding> {
+    let Token::StartTag(tag) = token else {
+        return None;
+    };
+    if tag.tag_name != local_name::meta {
+        return None;
+    }
+
+    let attribute = |name: LocalName| {
+        tag.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| &*attribute.value)
+    };
+
+    // Unlike in the prescan, a `charset` with an unknown label leaves the
+    // decision to `content`.
+    let charset = attribute(local_name::charset);
+    if let Some(encoding) = charset.and_then(|charset| Encoding::for_label(charset.as_bytes())) {
+        return Some(encoding);
+    }
+    if attribute(local_name::http_equiv)
+        .is_some_and(|value| value.eq_ignore_ascii_case("content-type"))
+    {
+        let content = attribute(local_name::content)?;
+        let charset = encoding::extract_charset_from_content(content.as_bytes())?;
+        return Encoding::for_label(charset);
+    }
+    None
+}
+
+/// An incremental HTML tokenizer.
+///
+/// Input is pushed in chunks with `feed` and `end`, and tokens are pulled
+/// through `Iterator`, or pushed to a `TokenSink` with `run`. `next` returns
+/// `None` once every token that can be produced from the input fed so far
+/// has been yielded; after feeding more input, iteration can resume. Only
+/// input that has not been tokenized yet is kept in memory.
+///
+/// ```
+/// use rust_scraper::tokenizer::{Token, Tokenizer};
+///
+/// let mut tokenizer = Tokenizer::new();
+/// tokenizer.feed("<p>Hel");
+/// let first: Vec<_> = tokenizer.by_ref().map(|token| token.to_string()).collect();
+/// // The text may go on in the next chunk, so it is held back.
+/// assert_eq!(first, ["<p>"]);
+///
+/// tokenizer.feed("lo</p>");
+/// tokenizer.end();
+/// let rest: Vec<_> = tokenizer.by_ref().collect();
+/// assert_eq!(rest.len(), 3);
+/// assert_eq!(rest[0].to_string(), "Hello");
+/// assert_eq!(rest[0].span().bytes(), 3..8);
+/// assert_eq!(rest[1].to_string(), "</p>");
+/// assert!(matches!(rest[2], Token::EndOfFile(_)));
+/// ```
+pub struct Tokenizer<'a> {
     /*
     HTML Standard:
     https://html.spec.whatwg.org/multipage/parsing.html#tokenization
 
     Actions:
-    - Emit token => tokens.push()
-    - Switch state => current_state = State::
-    - Switch return state => return_state = State::
-    - Reconsume => i -= 1
-    - Temporary buffer => temporary_buffer
-        - Add => temporary_buffer.push()
-        - Clear => temporary_buffer.clear()
-    - Current tag token => current_tag
-        - New => current_tag = Tag::StartTag() | Tag::EndTag()
-        - Push tag name => current_tag.tag_name.push()
+    - Emit token => self.emit()
+    - Switch state => self.current_state = State::
+    - Switch return state => self.return_state = State::
+    - Reconsume => self.reconsume = true
+    - Consume extra characters (after a lookahead match) => self.skip()
+    - Temporary buffer => self.temporary_buffer
+        - Add => self.temporary_buffer.push()
+        - Clear => self.temporary_buffer.clear()
+    - Current tag token => self.current_tag
+        - New => self.start_new_tag(TagKind::Start | TagKind::End)
+        - Push tag name => self.push_to_tag_name()
 
     - Appropriate end tag token
-        - `start_tags` is a vector containing all created start tags
-        - End tag is appropriate if it matches the last emitted start tag name
-        - When a start tag is to be emitted, it is cloned into `tokens`.
+        - End tag is appropriate if it matches `last_start_tag_name`
     */
+    current_state: State,
+    return_state: State,
+    temporary_buffer: String,
+    character_reference_code: u32,
+    current_tag: Option<Tag<'a>>,
+    current_tag_name: String,
+    /// The name of the attribute in the attribute name state.
+    current_attribute_name: String,
+    current_comment: Cow<'a, str>,
+    current_doctype: Doctype,
+    last_start_tag_name: Option<LocalName>,
+    /// Whether the attribute being tokenized repeats an earlier name on the
+    /// current tag.
+    current_attribute_is_duplicate: bool,
+    in_foreign_content: bool,
+    state_policy: StatePolicy,
+    character_tokens: bool,
+
+    /// Input that has been fed but not dropped yet. Everything before
+    /// `char_start` has been fully tokenized. Borrowed input is never
+    /// dropped, and tokens borrow from it.
+    input: Cow<'a, str>,
+    /// Byte offset in `input` of the current input character.
+    char_start: usize,
+    /// Byte offset in `input` of the next input character.
+    next_char_start: usize,
+    input_ended: bool,
+    /// The current input character, or `None` at the end of the file.
+    current_char: Option<char>,
+    reconsume: bool,
+
+    /// The position of the current input character.
+    current_position: Position,
+    /// The position of the next input character.
+    next_position: Position,
+    /// Where the span of the next token starts, which is where that of the
+    /// last one ended.
+    token_start: Position,
+    /// Where the name of the attribute in the attribute name state starts.
+    attribute_name_start: Position,
+
+    tokens: VecDeque<Token<'a>>,
+    /// Set when the text run at the end of the queue cannot grow any more
+    /// but the input after it has not been tokenized yet.
+    text_run_ended: bool,
+    errors: Vec<PositionedError>,
+    emitted_eof: bool,
+}
+
+impl Tokenizer<'static> {
+    pub fn new() -> Tokenizer<'static> {
+        Tokenizer::with_options(&TokenizerOptions::default())
+    }
+
+    /// A tokenizer to be fed its input in chunks. Its tokens own their
+    /// text.
+    pub fn with_options(options: &TokenizerOptions) -> Tokenizer<'static> {
+        Tokenizer::with_input(Cow::Owned(String::new()), options)
+    }
+}
+
+impl<'a> Tokenizer<'a> {
+    /// A tokenizer for the whole of `html`, whose tokens borrow from it.
+    /// No more input can be fed to it.
+    pub fn borrowing(html: &'a str, options: &TokenizerOptions) -> Tokenizer<'a> {
+        let mut tokenizer = Tokenizer::with_input(Cow::Borrowed(html), options);
+        tokenizer.end();
+        tokenizer
+    }
+
+    fn with_input(input: Cow<'a, str>, options: &TokenizerOptions) -> Tokenizer<'a> {
+        Tokenizer {
+            current_state: State::Data,
+            return_state: State::Data,
+            temporary_buffer: String::new(),
+            character_reference_code: 0,
+            current_tag: None,
+            current_tag_name: String::new(),
+            current_attribute_name: String::new(),
+            current_comment: Cow::Borrowed(""),
+            current_doctype: Doctype::default(),
+            last_start_tag_name: None,
+            current_attribute_is_duplicate: false,
+            in_foreign_content: options.in_foreign_content,
+            state_policy: options.state_policy,
+            character_tokens: options.character_tokens,
+            input,
+            char_start: 0,
+            next_char_start: 0,
+            input_ended: false,
+            current_char: None,
+            reconsume: false,
+            current_position: Position::start(),
+            next_position: Position::start(),
+            token_start: Position::start(),
+            attribute_name_start: Position::start(),
+            tokens: VecDeque::new(),
+            text_run_ended: false,
+            errors: Vec::new(),
+            emitted_eof: false,
+        }
+    }
+
+    /// Appends a chunk of input.
+    pub fn feed(&mut self, chunk: &str) {
+        assert!(!self.input_ended, "input fed after the end of the input");
+
+        // Drop the tokenized input once it makes up most of the buffer.
+        if self.char_start > self.input.len() / 2 {
+            self.input.to_mut().drain(..self.char_start);
+            self.next_char_start -= self.char_start;
+            self.char_start = 0;
+        }
+
+        self.input.to_mut().push_str(chunk);
+    }
+
+    /// Marks the end of the input.
+    pub fn end(&mut self) {
+        self.input_ended = true;
+    }
+
+    /// Tokenizes as much of the input fed so far as it can, passing each
+    /// token to `sink`, until the sink pauses or aborts.
+    pub fn run<S: TokenSink<'a>>(&mut self, sink: &mut S) -> RunResult {
+        while let Some(token) = self.next() {
+            match sink.process_token(token) {
+                TokenSinkResult::Continue => {}
+                TokenSinkResult::SwitchTo(state) => self.set_state(state),
+                TokenSinkResult::Pause => return RunResult::Paused,
+                TokenSinkResult::Abort => {
+                    self.tokens.clear();
+                    self.emitted_eof = true;
+                    return RunResult::Aborted;
+                }
+            }
+        }
+
+        if self.emitted_eof {
+            RunResult::Done
+        } else {
+            RunResult::NeedsInput
+        }
+    }
+
+    /// Switches to `state`, as the tree builder does after inserting an
+    /// element whose contents are RCDATA, raw text or script data. Takes
+    /// effect from the next input character.
+    pub fn set_state(&mut self, state: State) {
+        self.current_state = state;
+    }
+
+    /// Sets the name of the last start tag, which end tags in the RCDATA,
+    /// RAWTEXT and script data states are matched against. This is normally
+    /// tracked by the tokenizer itself.
+    pub fn set_last_start_tag_name(&mut self, name: Option<&str>) {
+        self.last_start_tag_name = name.map(LocalName::new);
+    }
+
+    /// Sets whether the adjusted current node is in foreign content, see
+    /// `TokenizerOptions::in_foreign_content`.
+    pub fn set_in_foreign_content(&mut self, in_foreign_content: bool) {
+        self.in_foreign_content = in_foreign_content;
+    }
+
+    /// The parse errors found so far.
+    pub fn errors(&self) -> &[PositionedError] {
+        &self.errors
+    }
+
+    /// Takes the parse errors found so far, leaving none behind.
+    pub fn take_errors(&mut self) -> Vec<PositionedError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn emit(&mut self, token: Token<'a>) {
+        match token {
+            Token::Character(ch, span) if !self.character_tokens => {
+                return self.push_to_text_run(ch, span)
+            }
+            Token::EndOfFile(_) => self.emitted_eof = true,
+            _ => {}
+        }
+
+        self.tokens.push_back(token);
+    }
+
+    fn emit_char(&mut self, ch: char) {
+        let span = self.next_token_span();
+        self.emit(Token::Character(ch, span));
+    }
+
+    fn emit_eof(&mut self) {
+        self.emit(Token::EndOfFile(Span::empty(self.current_position)));
+    }
+
+    /// The span of a token emitted now, which ends after the current input
+    /// character. If the character is reconsumed, `step` moves the end back
+    /// to before it.
+    fn next_token_span(&mut self) -> Span {
+        let span = Span {
+            start: self.token_start,
+            end: self.next_position,
+        };
+        self.token_start = self.next_position;
+        span
+    }
+
+    /// Leaves the markup up to and including the current input character
+    /// out of the spans of tokens, for markup that produces none.
+    fn skip_token_span(&mut self) {
+        self.token_start = self.next_position;
+    }
+
+    /// Moves the spans of the tokens emitted since the queue held `queued`
+    /// tokens, and of a text run they were appended to, back to before the
+    /// current input character, which is to be reconsumed.
+    fn end_spans_before_reconsumed_char(&mut self, queued: usize) {
+        let end = self.current_position;
+
+        for token in self.tokens.range_mut(queued.saturating_sub(1)..) {
+            let span = token.span_mut();
+            if span.start.offset > end.offset {
+                span.start = end;
+            }
+            if span.end.offset > end.offset {
+                span.end = end;
+            }
+        }
+
+        if self.token_start.offset > end.offset {
+            self.token_start = end;
+        }
+    }
+
+    /// Appends `ch` to the text run at the end of the queue if it can join
+    /// it, see `Token::Text`, or starts a new run.
+    fn push_to_text_run(&mut self, ch: char, span: Span) {
+        let (input, at) = (self.borrowable_input(), self.char_start);
+
+        if let Some(Token::Text(run, run_span)) = self.tokens.back_mut() {
+            let whitespace_run = run.starts_with(|c: char| c.is_ascii_whitespace());
+            if ch != '\0' && run != "\0" && (!whitespace_run || ch.is_ascii_whitespace()) {
+                append(run, ch.encode_utf8(&mut [0; 4]), input, at);
+                run_span.end = span.end;
+                return;
+            }
+        }
+
+        let mut run = Cow::Borrowed("");
+        append(&mut run, ch.encode_utf8(&mut [0; 4]), input, at);
+        self.tokens.push_back(Token::Text(run, span));
+    }
+
+    /// The input, if tokens can borrow from it.
+    fn borrowable_input(&self) -> Option<&'a str> {
+        match self.input {
+            Cow::Borrowed(input) => Some(input),
+            Cow::Owned(_) => None,
+        }
+    }
+
+    fn push_to_tag_name(&mut self, ch: char) {
+        self.current_tag_name.push(ch);
+    }
+
+    fn push_to_attribute_name(&mut self, ch: char) {
+        self.current_attribute_name.push(ch);
+    }
+
+    fn push_to_attribute_value(&mut self, ch: char) {
+        let (input, at) = (self.borrowable_input(), self.char_start);
+        append(
+            &mut current_attribute(&mut self.current_tag).value,
+            ch.encode_utf8(&mut [0; 4]),
+            input,
+            at,
+        );
+    }
 
-    let chars = html.chars().collect::<Vec<char>>();
-
-    let mut tokens: Vec<Token> = Vec::new();
-    let mut current_state = State::Data;
-    let mut return_state = State::Data;
-    let mut temporary_buffer = String::new();
-    let mut current_tag: Option<Tag> = None;
-    let mut current_attribute: Option<&Attribute> = None;
-    let mut open_start_tags: Vec<&StartTag> = Vec::new();
-
-    let mut ch: char;
-    let mut eof = false; // End of file
-    let mut i = 0;
-    while i <= chars.len() {
-        if i >= chars.len() {
-            ch = '\0';
-            eof = true;
+    fn push_to_comment(&mut self, ch: char) {
+        let (input, at) = (self.borrowable_input(), self.char_start);
+        append(
+            &mut self.current_comment,
+            ch.encode_utf8(&mut [0; 4]),
+            input,
+            at,
+        );
+    }
+
+    fn push_str_to_comment(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.push_to_comment(ch);
+        }
+    }
+
+    fn emit_current_comment(&mut self) {
+        let comment = std::mem::replace(&mut self.current_comment, Cow::Borrowed(""));
+        let span = self.next_token_span();
+        self.emit(Token::Comment(comment, span));
+    }
+
+    fn emit_current_doctype(&mut self) {
+        let mut doctype = std::mem::take(&mut self.current_doctype);
+        doctype.span = self.next_token_span();
+        self.emit(Token::Doctype(doctype));
+    }
+
+    fn emit_temporary_buffer(&mut self) {
+        let temporary_buffer = std::mem::take(&mut self.temporary_buffer);
+        for c in temporary_buffer.chars() {
+            self.emit_char(c);
+        }
+        self.temporary_buffer = temporary_buffer;
+    }
+
+    /// Flushes code points consumed as a character reference: appended to
+    /// the current attribute's value when the reference is part of an
+    /// attribute, emitted as character tokens otherwise.
+    fn flush_character_reference(&mut self) {
+        if is_consumed_in_attribute(&self.return_state) {
+            let temporary_buffer = std::mem::take(&mut self.temporary_buffer);
+            for c in temporary_buffer.chars() {
+                self.push_to_attribute_value(c);
+            }
+            self.temporary_buffer = temporary_buffer;
         } else {
-            ch = chars[i];
+            self.emit_temporary_buffer();
+        }
+    }
+
+    fn start_new_tag(&mut self, kind: TagKind) {
+        self.current_tag_name.clear();
+        self.current_tag = Some(Tag {
+            kind,
+            self_closing: false,
+            attributes: Vec::new(),
+        });
+    }
+
+    /// Emits the current tag token. A start tag becomes the last start tag,
+    /// against which appropriate end tags are checked; an end tag loses
+    /// any attributes and self-closing flag it was given.
+    fn emit_current_tag(&mut self) {
+        self.drop_duplicate_attribute();
+
+        let tag = self.current_tag.take().unwrap();
+        let tag_name = LocalName::new(&self.current_tag_name);
+        let span = self.next_token_span();
+
+        match tag.kind {
+            TagKind::Start => {
+                if self.state_policy == StatePolicy::ByTagName && !self.in_foreign_content {
+                    if let Some(state) = state_after_start_tag(&tag_name) {
+                        self.current_state = state;
+                    }
+                }
+
+                self.last_start_tag_name = Some(tag_name.clone());
+                self.emit(Token::StartTag(StartTag {
+                    tag_name,
+                    self_closing: tag.self_closing,
+                    attributes: tag.attributes,
+                    span,
+                }));
+            }
+            TagKind::End => {
+                if !tag.attributes.is_empty() {
+                    self.parse_error(ParseError::EndTagWithAttributes);
+                }
+                if tag.self_closing {
+                    self.parse_error(ParseError::EndTagWithTrailingSolidus);
+                }
+                self.emit(Token::EndTag(EndTag {
+                    tag_name,
+                    self_closing: false,
+                    attributes: Vec::new(),
+                    span,
+                }));
+            }
+        }
+    }
+
+    /// Whether the current tag is an end tag named like the last start tag.
+    fn is_appropriate_end_tag(&self) -> bool {
+        let is_end_tag = matches!(&self.current_tag, Some(tag) if tag.kind == TagKind::End);
+        is_end_tag
+            && self
+                .last_start_tag_name
+                .as_ref()
+                .is_some_and(|name| name.as_str() == self.current_tag_name)
+    }
+
+    fn start_new_attribute(&mut self, name: &str) {
+        self.drop_duplicate_attribute();
+
+        self.current_attribute_name.clear();
+        self.current_attribute_name.push_str(name);
+        self.attribute_name_start = self.current_position;
+    }
+
+    /// Called on the first character after the attribute's name, to add the
+    /// attribute to the current tag. An attribute with the same name as an
+    /// earlier one on the tag is a parse error, and is dropped once its value
+    /// has been consumed.
+    fn finish_attribute_name(&mut self) {
+        let name = LocalName::new(&self.current_attribute_name);
+        let attributes = tag_attributes(&mut self.current_tag);
+
+        if attributes.iter().any(|other| other.name == name) {
+            self.parse_error(ParseError::DuplicateAttribute);
+            self.current_attribute_is_duplicate = true;
+        }
+
+        tag_attributes(&mut self.current_tag).push(Attribute {
+            name,
+            value: Cow::Borrowed(""),
+            name_span: Span {
+                start: self.attribute_name_start,
+                end: self.current_position,
+            },
+            value_span: Span::empty(self.current_position),
+        });
+    }
+
+    /// Starts the current attribute's value span at `start`.
+    fn start_attribute_value(&mut self, start: Position) {
+        current_attribute(&mut self.current_tag).value_span = Span::empty(start);
+    }
+
+    /// Called on the first character after the current attribute's value.
+    fn end_attribute_value(&mut self) {
+        current_attribute(&mut self.current_tag).value_span.end = self.current_position;
+    }
+
+    fn drop_duplicate_attribute(&mut self) {
+        if self.current_attribute_is_duplicate {
+            self.current_attribute_is_duplicate = false;
+            tag_attributes(&mut self.current_tag).pop();
         }
+    }
+
+    pub(crate) fn parse_error(&mut self, error: ParseError) {
+        self.errors.push(PositionedError {
+            error,
+            position: self.current_position,
+        });
+    }
 
-        match current_state {
-            State::Data => match ch {
-                '&' => {
-                    return_state = State::Data;
-                    current_state = State::CharacterReference;
+    /// Moves on to the next input character. Returns `false` if it has not
+    /// been fed yet.
+    fn consume(&mut self) -> bool {
+        self.char_start = self.next_char_start;
+        self.current_position = self.next_position;
+
+        let mut chars = self.input[self.next_char_start..].chars();
+
+        let ch = match chars.next() {
+            // A CR is normalized to LF, and a CRLF pair becomes a single LF.
+            // A CR at the end of the buffered input has to wait for the next
+            // chunk to see whether an LF follows.
+            Some('\r') => {
+                match chars.next() {
+                    None if !self.input_ended => return false,
+                    Some('\n') => {
+                        self.next_char_start += 2;
+                        self.next_position.offset += 1;
+                    }
+                    _ => self.next_char_start += 1,
+                }
+                '\n'
+            }
+            Some(ch) => {
+                self.next_char_start += ch.len_utf8();
+                ch
+            }
+            None if self.input_ended => {
+                self.current_char = None;
+                return true;
+            }
+            None => return false,
+        };
+
+        // Surrogates cannot occur in a `str`; lone surrogates in the
+        // original bytes were already replaced by U+FFFD when decoding.
+        if is_input_stream_error(ch) {
+            if is_noncharacter(ch as u32) {
+                self.parse_error(ParseError::NoncharacterInInputStream);
+            } else {
+                self.parse_error(ParseError::ControlCharacterInInputStream);
+            }
+        }
+
+        self.next_position.advance(ch);
+        self.current_char = Some(ch);
+        true
+    }
+
+    /// Consumes the run of input characters before the next one the current
+    /// state does anything but append to a token for, and appends them in
+    /// one go. The run is found by searching the input's bytes for those
+    /// the state looks for, and for those input preprocessing has to see.
+    /// Returns `false` if there is no such run.
+    fn consume_run(&mut self) -> bool {
+        let find_delimiter: fn(&[u8]) -> Option<usize> = match self.current_state {
+            State::Data | State::Rcdata if !self.character_tokens => {
+                |bytes| memchr3(b'<', b'&', b'\0', bytes)
+            }
+            State::Rawtext | State::ScriptData if !self.character_tokens => {
+                |bytes| memchr2(b'<', b'\0', bytes)
+            }
+            State::Plaintext if !self.character_tokens => |bytes| memchr(b'\0', bytes),
+            State::AttributeValueDoubleQuoted => |bytes| memchr3(b'"', b'&', b'\0', bytes),
+            State::AttributeValueSingleQuoted => |bytes| memchr3(b'\'', b'&', b'\0', bytes),
+            State::Comment => |bytes| memchr3(b'<', b'-', b'\0', bytes),
+            _ => return false,
+        };
+
+        let start = self.next_char_start;
+        let rest = &self.input[start..];
+        let mut length = 0;
+
+        // The input is searched a block at a time, so that a run cut short
+        // by a character that preprocessing has to see is not searched far
+        // past it; otherwise every CR could mean searching to the same
+        // distant delimiter again.
+        while length < rest.len() {
+            let block_end = rest.len().min(length + RUN_SEARCH_BLOCK_SIZE);
+            let delimiter = find_delimiter(&rest.as_bytes()[length..block_end]);
+            let limit = delimiter.map_or(block_end, |delimiter| length + delimiter);
+
+            length = unprocessed_prefix_end(rest, length, limit);
+            if length < limit || delimiter.is_some() {
+                break;
+            }
+        }
+
+        if length == 0 {
+            return false;
+        }
+
+        let end = start + length;
+
+        let input = self.borrowable_input();
+        let run = &self.input[start..end];
+        let run_position = self.next_position;
+        self.next_position.advance_str(run);
+
+        match self.current_state {
+            State::AttributeValueDoubleQuoted | State::AttributeValueSingleQuoted => {
+                let value = &mut current_attribute(&mut self.current_tag).value;
+                append(value, run, input, start);
+            }
+            State::Comment => append(&mut self.current_comment, run, input, start),
+            _ => {
+                let span_start = self.token_start;
+                push_str_to_text_runs(
+                    &mut self.tokens,
+                    run,
+                    input,
+                    start,
+                    span_start,
+                    run_position,
+                );
+                self.token_start = self.next_position;
+            }
+        }
+
+        self.current_position = self.next_position;
+        self.char_start = end;
+        self.next_char_start = end;
+        true
+    }
+
+    /// Consumes `count` more characters after a lookahead matched them.
+    fn skip(&mut self, count: usize) {
+        for _ in 0..count {
+            self.consume();
+        }
+    }
+
+    /// Checks whether the input starting at the current input character
+    /// starts with `expected`, optionally ignoring ASCII case.
+    fn next_chars_are(&self, expected: &str, ignore_case: bool) -> bool {
+        let mut chars = self.input[self.char_start..].chars();
+
+        expected.chars().all(|expected_char| match chars.next() {
+            Some(ch) if ignore_case => ch.eq_ignore_ascii_case(&expected_char),
+            Some(ch) => ch == expected_char,
+            None => false,
+        })
+    }
+
+    /// Whether enough input is buffered past the current input character for
+    /// the current state to look ahead.
+    fn has_enough_lookahead(&self) -> bool {
+        if self.input_ended {
+            return true;
+        }
+
+        let mut buffered = self.input[self.char_start..].chars();
+
+        match self.current_state {
+            // "DOCTYPE" and "[CDATA[" are the longest keywords looked for.
+            State::MarkupDeclarationOpen => buffered.nth(6).is_some(),
+            // "PUBLIC" or "SYSTEM".
+            State::AfterDoctypeName => buffered.nth(5).is_some(),
+            // The longest possible name plus the character following it.
+            State::NamedCharacterReference => buffered
+                .take(entities::LONGEST_NAME_LENGTH + 1)
+                .enumerate()
+                .any(|(i, c)| !c.is_ascii_alphanumeric() || i == entities::LONGEST_NAME_LENGTH),
+            _ => true,
+        }
+    }
+
+    /// Runs the state machine for one input character. Returns `false` when
+    /// more input has to be fed first.
+    fn step(&mut self) -> bool {
+        if !self.reconsume && self.consume_run() {
+            return true;
+        }
+
+        if !self.reconsume && !self.consume() {
+            return false;
+        }
+
+        if !self.has_enough_lookahead() {
+            self.reconsume = true;
+            return false;
+        }
+
+        self.reconsume = false;
+        let current = self.current_char;
+        let queued = self.tokens.len();
+
+        match self.current_state {
+            State::Data => match current {
+                Some('&') => {
+                    self.return_state = State::Data;
+                    self.current_state = State::CharacterReference;
+                }
+                Some('<') => self.current_state = State::TagOpen,
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.emit_char('\0');
+                }
+                None => self.emit_eof(),
+                Some(ch) => self.emit_char(ch),
+            },
+
+            State::Rcdata => match current {
+                Some('&') => {
+                    self.return_state = State::Rcdata;
+                    self.current_state = State::CharacterReference;
+                }
+                Some('<') => {
+                    self.current_state = State::RcdataLessThanSign;
+                }
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                None => self.emit_eof(),
+                Some(ch) => self.emit_char(ch),
+            },
+
+            State::Rawtext => match current {
+                Some('<') => self.current_state = State::RawtextLessThanSign,
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                None => self.emit_eof(),
+                Some(ch) => self.emit_char(ch),
+            },
+
+            State::ScriptData => match current {
+                Some('<') => self.current_state = State::ScriptDataLessThanSign,
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                None => self.emit_eof(),
+                Some(ch) => self.emit_char(ch),
+            },
+
+            State::Plaintext => match current {
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                None => self.emit_eof(),
+                Some(ch) => self.emit_char(ch),
+            },
+
+            State::TagOpen => match current {
+                Some('!') => self.current_state = State::MarkupDeclarationOpen,
+                Some('/') => self.current_state = State::EndTagOpen,
+                Some(ch) if ch.is_ascii_alphabetic() => {
+                    self.start_new_tag(TagKind::Start);
+                    self.reconsume = true;
+                    self.current_state = State::TagName;
+                }
+                Some('?') => {
+                    self.parse_error(ParseError::UnexpectedQuestionMarkInsteadOfTagName);
+                    self.current_comment = Cow::Borrowed("");
+                    self.reconsume = true;
+                    self.current_state = State::BogusComment;
+                }
+                None => {
+                    self.parse_error(ParseError::EofBeforeTagName);
+                    self.emit_char('<');
+                    self.emit_eof();
+                }
+                _ => {
+                    self.parse_error(ParseError::InvalidFirstCharacterOfTagName);
+                    self.emit_char('<');
+                    self.reconsume = true;
+                    self.current_state = State::Data;
+                }
+            },
+
+            State::EndTagOpen => match current {
+                Some(ch) if ch.is_ascii_alphabetic() => {
+                    self.start_new_tag(TagKind::End);
+                    self.reconsume = true;
+                    self.current_state = State::TagName;
+                }
+                Some('>') => {
+                    self.parse_error(ParseError::MissingEndTagName);
+                    self.skip_token_span();
+                    self.current_state = State::Data;
+                }
+                None => {
+                    self.parse_error(ParseError::EofBeforeTagName);
+                    self.emit_char('<');
+                    self.emit_char('/');
+                    self.emit_eof();
+                }
+                _ => {
+                    self.parse_error(ParseError::InvalidFirstCharacterOfTagName);
+                    self.current_comment = Cow::Borrowed("");
+                    self.reconsume = true;
+                    self.current_state = State::BogusComment;
+                }
+            },
+
+            State::TagName => match current {
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {
+                    self.current_state = State::BeforeAttributeName
+                }
+                Some('/') => self.current_state = State::SelfClosingStartTag,
+                Some('>') => {
+                    self.current_state = State::Data;
+                    self.emit_current_tag();
+                }
+                Some(ch) if ch.is_ascii_uppercase() => {
+                    self.push_to_tag_name(ch.to_ascii_lowercase())
+                }
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.push_to_tag_name('\u{FFFD}')
+                }
+                None => {
+                    self.parse_error(ParseError::EofInTag);
+                    self.emit_eof();
+                }
+                Some(ch) => self.push_to_tag_name(ch),
+            },
+
+            State::RcdataLessThanSign => match current {
+                Some('/') => {
+                    self.temporary_buffer = String::new();
+                    self.current_state = State::RcdataEndTagOpen;
+                }
+                _ => {
+                    self.emit_char('<');
+                    self.reconsume = true;
+                    self.current_state = State::Rcdata;
+                }
+            },
+
+            State::RcdataEndTagOpen => match current {
+                Some(ch) if ch.is_ascii_alphabetic() => {
+                    self.start_new_tag(TagKind::End);
+                    self.reconsume = true;
+                    self.current_state = State::RcdataEndTagName;
+                }
+                _ => {
+                    self.emit_char('<');
+                    self.emit_char('/');
+                    self.reconsume = true;
+                    self.current_state = State::Rcdata;
+                }
+            },
+
+            State::RcdataEndTagName => {
+                let is_appropriate = self.is_appropriate_end_tag();
+
+                match current {
+                    // Tab | Line feed (LF) | Form feed (FF) | Space
+                    Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') if is_appropriate => {
+                        self.current_state = State::BeforeAttributeName;
+                    }
+                    Some('/') if is_appropriate => self.current_state = State::SelfClosingStartTag,
+                    Some('>') if is_appropriate => {
+                        self.current_state = State::Data;
+                        self.emit_current_tag();
+                    }
+                    Some(ch) if ch.is_ascii_uppercase() => {
+                        self.push_to_tag_name(ch.to_ascii_lowercase());
+
+                        self.temporary_buffer.push(ch);
+                    }
+                    Some(ch) if ch.is_ascii_lowercase() => {
+                        self.push_to_tag_name(ch);
+
+                        self.temporary_buffer.push(ch);
+                    }
+                    _ => {
+                        self.emit_char('<');
+                        self.emit_char('/');
+
+                        self.emit_temporary_buffer();
+
+                        self.reconsume = true;
+                        self.current_state = State::Rcdata;
+                    }
+                }
+            }
+
+            State::RawtextLessThanSign => match current {
+                Some('/') => {
+                    self.temporary_buffer.clear();
+                    self.current_state = State::RawtextEndTagOpen;
+                }
+                _ => {
+                    self.emit_char('<');
+                    self.reconsume = true;
+                    self.current_state = State::Rawtext;
+                }
+            },
+
+            State::RawtextEndTagOpen => match current {
+                Some(ch) if ch.is_ascii_alphabetic() => {
+                    self.start_new_tag(TagKind::End);
+                    self.reconsume = true;
+                    self.current_state = State::RawtextEndTagName;
+                }
+                _ => {
+                    self.emit_char('<');
+                    self.emit_char('/');
+                    self.reconsume = true;
+                    self.current_state = State::Rawtext;
+                }
+            },
+
+            State::RawtextEndTagName => {
+                let is_appropriate = self.is_appropriate_end_tag();
+
+                match current {
+                    // Tab | Line feed (LF) | Form feed (FF) | Space
+                    Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') if is_appropriate => {
+                        self.current_state = State::BeforeAttributeName;
+                    }
+                    Some('/') if is_appropriate => self.current_state = State::SelfClosingStartTag,
+                    Some('>') if is_appropriate => {
+                        self.current_state = State::Data;
+                        self.emit_current_tag();
+                    }
+                    Some(ch) if ch.is_ascii_uppercase() => {
+                        self.push_to_tag_name(ch.to_ascii_lowercase());
+
+                        self.temporary_buffer.push(ch);
+                    }
+                    Some(ch) if ch.is_ascii_lowercase() => {
+                        self.push_to_tag_name(ch);
+
+                        self.temporary_buffer.push(ch);
+                    }
+                    _ => {
+                        self.emit_char('<');
+                        self.emit_char('/');
+
+                        self.emit_temporary_buffer();
+
+                        self.reconsume = true;
+                        self.current_state = State::Rawtext;
+                    }
+                }
+            }
+
+            State::ScriptDataLessThanSign => match current {
+                Some('/') => {
+                    self.temporary_buffer.clear();
+                    self.current_state = State::ScriptDataEndTagOpen;
+                }
+                Some('!') => {
+                    self.current_state = State::ScriptDataEscapeStart;
+                    self.emit_char('<');
+                    self.emit_char('!');
+                }
+                _ => {
+                    self.emit_char('<');
+                    self.reconsume = true;
+                    self.current_state = State::ScriptData;
+                }
+            },
+
+            State::ScriptDataEndTagOpen => match current {
+                Some(ch) if ch.is_ascii_alphabetic() => {
+                    self.start_new_tag(TagKind::End);
+
+                    self.reconsume = true;
+                    self.current_state = State::ScriptDataEndTagName;
+                }
+                _ => {
+                    self.emit_char('<');
+                    self.emit_char('/');
+                    self.reconsume = true;
+                    self.current_state = State::ScriptData;
+                }
+            },
+
+            State::ScriptDataEndTagName => {
+                let is_appropriate = self.is_appropriate_end_tag();
+
+                match current {
+                    // Tab | Line feed (LF) | Form feed (FF) | Space
+                    Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') if is_appropriate => {
+                        self.current_state = State::BeforeAttributeName;
+                    }
+                    Some('/') if is_appropriate => self.current_state = State::SelfClosingStartTag,
+                    Some('>') if is_appropriate => {
+                        self.current_state = State::Data;
+                        self.emit_current_tag();
+                    }
+                    Some(ch) if ch.is_ascii_uppercase() => {
+                        self.push_to_tag_name(ch.to_ascii_lowercase());
+
+                        self.temporary_buffer.push(ch);
+                    }
+                    Some(ch) if ch.is_ascii_lowercase() => {
+                        self.push_to_tag_name(ch);
+
+                        self.temporary_buffer.push(ch);
+                    }
+                    _ => {
+                        self.emit_char('<');
+                        self.emit_char('/');
+
+                        self.emit_temporary_buffer();
+
+                        self.reconsume = true;
+                        self.current_state = State::ScriptData;
+                    }
+                }
+            }
+
+            State::ScriptDataEscapeStart => match current {
+                Some('-') => {
+                    self.current_state = State::ScriptDataEscapeStartDash;
+                    self.emit_char('-');
+                }
+                _ => {
+                    self.reconsume = true;
+                    self.current_state = State::ScriptData;
+                }
+            },
+
+            State::ScriptDataEscapeStartDash => match current {
+                Some('-') => {
+                    self.current_state = State::ScriptDataEscapedDashDash;
+                    self.emit_char('-');
+                }
+                _ => {
+                    self.reconsume = true;
+                    self.current_state = State::ScriptData;
+                }
+            },
+
+            State::ScriptDataEscaped => match current {
+                Some('-') => {
+                    self.current_state = State::ScriptDataEscapedDash;
+                    self.emit_char('-');
+                }
+                Some('<') => self.current_state = State::ScriptDataEscapedLessThanSign,
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                None => {
+                    self.parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+                Some(ch) => self.emit_char(ch),
+            },
+
+            State::ScriptDataEscapedDash => match current {
+                Some('-') => {
+                    self.current_state = State::ScriptDataEscapedDashDash;
+                    self.emit_char('-');
+                }
+                Some('<') => self.current_state = State::ScriptDataEscapedLessThanSign,
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.current_state = State::ScriptDataEscaped;
+                    self.emit_char('\u{FFFD}');
+                }
+                None => {
+                    self.parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+                Some(ch) => {
+                    self.current_state = State::ScriptDataEscaped;
+                    self.emit_char(ch);
+                }
+            },
+
+            State::ScriptDataEscapedDashDash => match current {
+                Some('-') => self.emit_char('-'),
+                Some('<') => self.current_state = State::ScriptDataEscapedLessThanSign,
+                Some('>') => {
+                    self.current_state = State::ScriptData;
+                    self.emit_char('>');
+                }
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.current_state = State::ScriptDataEscaped;
+                    self.emit_char('\u{FFFD}');
+                }
+                None => {
+                    self.parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+                Some(ch) => {
+                    self.current_state = State::ScriptDataEscaped;
+                    self.emit_char(ch);
+                }
+            },
+
+            State::ScriptDataEscapedLessThanSign => match current {
+                Some('/') => {
+                    self.temporary_buffer.clear();
+                    self.current_state = State::ScriptDataEscapedEndTagOpen;
+                }
+                Some(ch) if ch.is_ascii_alphabetic() => {
+                    self.temporary_buffer.clear();
+                    self.emit_char('<');
+                    self.reconsume = true;
+                    self.current_state = State::ScriptDataDoubleEscapeStart;
+                }
+                _ => {
+                    self.emit_char('<');
+                    self.reconsume = true;
+                    self.current_state = State::ScriptDataEscaped;
+                }
+            },
+
+            State::ScriptDataEscapedEndTagOpen => match current {
+                Some(ch) if ch.is_ascii_alphabetic() => {
+                    self.start_new_tag(TagKind::End);
+
+                    self.reconsume = true;
+                    self.current_state = State::ScriptDataEscapedEndTagName;
+                }
+                _ => {
+                    self.emit_char('<');
+                    self.emit_char('/');
+                    self.reconsume = true;
+                    self.current_state = State::ScriptDataEscaped;
+                }
+            },
+
+            State::ScriptDataEscapedEndTagName => {
+                let is_appropriate = self.is_appropriate_end_tag();
+
+                match current {
+                    // Tab | Line feed (LF) | Form feed (FF) | Space
+                    Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') if is_appropriate => {
+                        self.current_state = State::BeforeAttributeName;
+                    }
+                    Some('/') if is_appropriate => self.current_state = State::SelfClosingStartTag,
+                    Some('>') if is_appropriate => {
+                        self.current_state = State::Data;
+                        self.emit_current_tag();
+                    }
+                    Some(ch) if ch.is_ascii_uppercase() => {
+                        self.push_to_tag_name(ch.to_ascii_lowercase());
+
+                        self.temporary_buffer.push(ch);
+                    }
+
+                    Some(ch) if ch.is_ascii_lowercase() => {
+                        self.push_to_tag_name(ch);
+
+                        self.temporary_buffer.push(ch);
+                    }
+
+                    _ => {
+                        self.emit_char('<');
+                        self.emit_char('/');
+
+                        self.emit_temporary_buffer();
+
+                        self.reconsume = true;
+                        self.current_state = State::ScriptData;
+                    }
+                }
+            }
+
+            State::ScriptDataDoubleEscapeStart => match current {
+                Some(ch @ ('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' | '/' | '>')) => {
+                    if self.temporary_buffer == "script" {
+                        self.current_state = State::ScriptDataDoubleEscaped;
+                    } else {
+                        self.current_state = State::ScriptDataEscaped;
+                    }
+
+                    self.emit_char(ch);
+                }
+                Some(ch) if ch.is_ascii_uppercase() => {
+                    self.temporary_buffer.push(ch.to_ascii_lowercase());
+                    self.emit_char(ch);
+                }
+
+                Some(ch) if ch.is_ascii_lowercase() => {
+                    self.temporary_buffer.push(ch);
+                    self.emit_char(ch);
+                }
+
+                _ => {
+                    self.reconsume = true;
+                    self.current_state = State::ScriptDataEscaped;
+                }
+            },
+
+            State::ScriptDataDoubleEscaped => match current {
+                Some('-') => {
+                    self.current_state = State::ScriptDataDoubleEscapedDash;
+                    self.emit_char('-');
+                }
+                Some('<') => {
+                    self.current_state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit_char('<');
+                }
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.emit_char('\u{FFFD}');
+                }
+                None => {
+                    self.parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+                Some(ch) => self.emit_char(ch),
+            },
+
+            State::ScriptDataDoubleEscapedDash => match current {
+                Some('-') => {
+                    self.current_state = State::ScriptDataDoubleEscapedDashDash;
+                    self.emit_char('-');
+                }
+                Some('<') => {
+                    self.current_state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit_char('<');
+                }
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.current_state = State::ScriptDataDoubleEscaped;
+                    self.emit_char('\u{FFFD}');
+                }
+                None => {
+                    self.parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+                Some(ch) => {
+                    self.current_state = State::ScriptDataDoubleEscaped;
+                    self.emit_char(ch);
+                }
+            },
+
+            State::ScriptDataDoubleEscapedDashDash => match current {
+                Some('-') => self.emit_char('-'),
+                Some('<') => {
+                    self.current_state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit_char('<');
+                }
+                Some('>') => {
+                    self.current_state = State::ScriptData;
+                    self.emit_char('>');
+                }
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.current_state = State::ScriptDataDoubleEscaped;
+                    self.emit_char('\u{FFFD}');
+                }
+                None => {
+                    self.parse_error(ParseError::EofInScriptHtmlCommentLikeText);
+                    self.emit_eof();
+                }
+                Some(ch) => {
+                    self.current_state = State::ScriptDataDoubleEscaped;
+                    self.emit_char(ch);
+                }
+            },
+
+            State::ScriptDataDoubleEscapedLessThanSign => match current {
+                Some('/') => {
+                    self.temporary_buffer.clear();
+                    self.current_state = State::ScriptDataDoubleEscapeEnd;
+                    self.emit_char('/');
+                }
+                _ => {
+                    self.reconsume = true;
+                    self.current_state = State::ScriptDataDoubleEscaped;
+                }
+            },
+
+            State::ScriptDataDoubleEscapeEnd => match current {
+                Some(ch @ ('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' | '/' | '>')) => {
+                    if self.temporary_buffer == "script" {
+                        self.current_state = State::ScriptDataEscaped;
+                    } else {
+                        self.current_state = State::ScriptDataDoubleEscaped;
+                    }
+
+                    self.emit_char(ch);
+                }
+
+                Some(ch) if ch.is_ascii_uppercase() => {
+                    self.temporary_buffer.push(ch.to_ascii_lowercase());
+                    self.emit_char(ch);
+                }
+
+                Some(ch) if ch.is_ascii_lowercase() => {
+                    self.temporary_buffer.push(ch);
+                    self.emit_char(ch);
+                }
+
+                _ => {
+                    self.reconsume = true;
+                    self.current_state = State::ScriptDataDoubleEscaped;
+                }
+            },
+
+            State::BeforeAttributeName => match current {
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {}
+                None => {
+                    self.reconsume = true;
+                    self.current_state = State::AfterAttributeName;
+                }
+                Some('/' | '>') => {
+                    self.reconsume = true;
+                    self.current_state = State::AfterAttributeName;
+                }
+                Some('=') => {
+                    self.parse_error(ParseError::UnexpectedEqualsSignBeforeAttributeName);
+                    self.start_new_attribute("=");
+                    self.current_state = State::AttributeName;
+                }
+                _ => {
+                    self.start_new_attribute("");
+                    self.reconsume = true;
+                    self.current_state = State::AttributeName;
+                }
+            },
+
+            State::AttributeName => match current {
+                None => {
+                    self.finish_attribute_name();
+                    self.reconsume = true;
+                    self.current_state = State::AfterAttributeName;
+                }
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' | '/' | '>') => {
+                    self.finish_attribute_name();
+                    self.reconsume = true;
+                    self.current_state = State::AfterAttributeName;
+                }
+                Some('=') => {
+                    self.finish_attribute_name();
+                    self.current_state = State::BeforeAttributeValue;
+                }
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.push_to_attribute_name('\u{FFFD}');
+                }
+                Some(ch @ ('"' | '\'' | '<')) => {
+                    self.parse_error(ParseError::UnexpectedCharacterInAttributeName);
+                    self.push_to_attribute_name(ch);
+                }
+                Some(ch) => self.push_to_attribute_name(ch.to_ascii_lowercase()),
+            },
+
+            State::AfterAttributeName => match current {
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {}
+                None => {
+                    self.parse_error(ParseError::EofInTag);
+                    self.emit_eof();
+                }
+                Some('/') => self.current_state = State::SelfClosingStartTag,
+                Some('=') => self.current_state = State::BeforeAttributeValue,
+                Some('>') => {
+                    self.current_state = State::Data;
+                    self.emit_current_tag();
+                }
+                _ => {
+                    self.start_new_attribute("");
+                    self.reconsume = true;
+                    self.current_state = State::AttributeName;
+                }
+            },
+
+            State::BeforeAttributeValue => match current {
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {}
+                Some('"') => {
+                    self.start_attribute_value(self.next_position);
+                    self.current_state = State::AttributeValueDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.start_attribute_value(self.next_position);
+                    self.current_state = State::AttributeValueSingleQuoted;
                 }
-                '<' => current_state = State::TagOpen,
-                _ if eof => tokens.push(Token::EndOfFile),
-                _ => tokens.push(Token::Character(ch)),
-            },
-
-            State::Rcdata => match ch {
-                '&' => {
-                    return_state = State::Rcdata;
-                    current_state = State::CharacterReference;
+                Some('>') => {
+                    self.parse_error(ParseError::MissingAttributeValue);
+                    self.current_state = State::Data;
+                    self.emit_current_tag();
                 }
-                '<' => {
-                    current_state = State::RcdataLessThanSign;
+                _ => {
+                    self.start_attribute_value(self.current_position);
+                    self.reconsume = true;
+                    self.current_state = State::AttributeValueUnquoted;
                 }
-                _ if eof => tokens.push(Token::EndOfFile),
-                _ => tokens.push(Token::Character(ch)),
-            },
-
-            State::Rawtext => match ch {
-                '<' => current_state = State::RawtextLessThanSign,
-                _ if eof => tokens.push(Token::EndOfFile),
-                _ => tokens.push(Token::Character(ch)),
             },
 
-            State::ScriptData => match ch {
-                '<' => current_state = State::ScriptDataLessThanSign,
-                _ if eof => tokens.push(Token::EndOfFile),
-                _ => tokens.push(Token::Character(ch)),
+            State::AttributeValueDoubleQuoted => match current {
+                None => {
+                    self.parse_error(ParseError::EofInTag);
+                    self.emit_eof();
+                }
+                Some('"') => {
+                    self.end_attribute_value();
+                    self.current_state = State::AfterAttributeValueQuoted;
+                }
+                Some('&') => {
+                    self.return_state = State::AttributeValueDoubleQuoted;
+                    self.current_state = State::CharacterReference;
+                }
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.push_to_attribute_value('\u{FFFD}');
+                }
+                Some(ch) => self.push_to_attribute_value(ch),
             },
 
-            State::Plaintext => match ch {
-                _ if eof => tokens.push(Token::EndOfFile),
-                _ => tokens.push(Token::Character(ch)),
+            State::AttributeValueSingleQuoted => match current {
+                None => {
+                    self.parse_error(ParseError::EofInTag);
+                    self.emit_eof();
+                }
+                Some('\'') => {
+                    self.end_attribute_value();
+                    self.current_state = State::AfterAttributeValueQuoted;
+                }
+                Some('&') => {
+                    self.return_state = State::AttributeValueSingleQuoted;
+                    self.current_state = State::CharacterReference;
+                }
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.push_to_attribute_value('\u{FFFD}');
+                }
+                Some(ch) => self.push_to_attribute_value(ch),
             },
 
-            State::TagOpen => match ch {
-                '!' => current_state = State::MarkupDeclarationOpen,
-                '/' => current_state = State::EndTagOpen,
-                _ if ch.is_ascii_alphabetic() => {
-                    current_tag = Some(Tag::StartTag(StartTag {
-                        tag_name: String::new(),
-                        self_closing: false,
-                        attributes: Vec::new(),
-                    }));
-                    i -= 1;
-                    current_state = State::TagName;
+            State::AttributeValueUnquoted => match current {
+                None => {
+                    self.parse_error(ParseError::EofInTag);
+                    self.emit_eof();
+                }
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {
+                    self.end_attribute_value();
+                    self.current_state = State::BeforeAttributeName;
                 }
-                '?' => {
-                    current_state = State::BogusComment;
-                    i -= 1;
+                Some('&') => {
+                    self.return_state = State::AttributeValueUnquoted;
+                    self.current_state = State::CharacterReference;
                 }
-                _ if eof => {
-                    tokens.push(Token::Character('<'));
-                    tokens.push(Token::EndOfFile);
+                Some('>') => {
+                    self.end_attribute_value();
+                    self.current_state = State::Data;
+                    self.emit_current_tag();
                 }
-                _ => {
-                    tokens.push(Token::Character('<'));
-                    i -= 1;
-                    current_state = State::Data;
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.push_to_attribute_value('\u{FFFD}');
                 }
+                Some(ch @ ('"' | '\'' | '<' | '=' | '`')) => {
+                    self.parse_error(ParseError::UnexpectedCharacterInUnquotedAttributeValue);
+                    self.push_to_attribute_value(ch);
+                }
+                Some(ch) => self.push_to_attribute_value(ch),
             },
 
-            State::EndTagOpen => match ch {
-                _ if ch.is_ascii_alphabetic() => {
-                    current_tag = Some(Tag::EndTag(EndTag {
-                        tag_name: String::new(),
-                    }));
-                    i -= 1;
-                    current_state = State::TagName;
+            State::AfterAttributeValueQuoted => match current {
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {
+                    self.current_state = State::BeforeAttributeName
                 }
-                '>' => current_state = State::Data,
-                _ if eof => {
-                    tokens.push(Token::Character('<'));
-                    tokens.push(Token::Character('/'));
-                    tokens.push(Token::EndOfFile);
+                None => {
+                    self.parse_error(ParseError::EofInTag);
+                    self.emit_eof();
+                }
+                Some('/') => self.current_state = State::SelfClosingStartTag,
+                Some('>') => {
+                    self.current_state = State::Data;
+                    self.emit_current_tag();
                 }
                 _ => {
-                    tokens.push(Token::Comment(String::new()));
-                    i -= 1;
-                    current_state = State::BogusComment;
+                    self.parse_error(ParseError::MissingWhitespaceBetweenAttributes);
+                    self.reconsume = true;
+                    self.current_state = State::BeforeAttributeName;
                 }
             },
 
-            State::TagName => match ch {
-                // Tab | Line feed (LF) | Form feed (FF) | Space
-                '\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' => {
-                    current_state = State::BeforeAttributeName
+            State::SelfClosingStartTag => match current {
+                None => {
+                    self.parse_error(ParseError::EofInTag);
+                    self.emit_eof();
                 }
-                '/' => current_state = State::SelfClosingStartTag,
-                '>' => current_state = State::Data,
-                _ if ch.is_ascii_uppercase() => match &mut current_tag {
-                    Some(Tag::StartTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                    Some(Tag::EndTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                    None => unreachable!(),
-                },
-                _ if eof => tokens.push(Token::EndOfFile),
-                _ => match &mut current_tag {
-                    Some(Tag::StartTag(tag)) => tag.tag_name.push(ch),
-                    Some(Tag::EndTag(tag)) => tag.tag_name.push(ch),
-                    None => unreachable!(),
-                },
-            },
+                Some('>') => {
+                    self.current_tag.as_mut().unwrap().self_closing = true;
 
-            State::RcdataLessThanSign => match ch {
-                '/' => {
-                    temporary_buffer = String::new();
-                    current_state = State::RcdataEndTagOpen;
+                    self.current_state = State::Data;
+                    self.emit_current_tag();
                 }
                 _ => {
-                    tokens.push(Token::Character('<'));
-                    i -= 1;
-                    current_state = State::Rcdata;
+                    self.parse_error(ParseError::UnexpectedSolidusInTag);
+                    self.reconsume = true;
+                    self.current_state = State::BeforeAttributeName;
                 }
             },
 
-            State::RcdataEndTagOpen => match ch {
-                _ if ch.is_ascii_alphabetic() => {
-                    current_tag = Some(Tag::EndTag(EndTag {
-                        tag_name: String::new(),
-                    }));
-                    i -= 1;
-                    current_state = State::Rcdata;
+            State::BogusComment => match current {
+                None => {
+                    self.emit_current_comment();
+                    self.emit_eof();
                 }
-                _ => {
-                    tokens.push(Token::Character('<'));
-                    tokens.push(Token::Character('/'));
-                    i -= 1;
-                    current_state = State::Rcdata;
+                Some('>') => {
+                    self.current_state = State::Data;
+                    self.emit_current_comment();
                 }
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.push_to_comment('\u{FFFD}');
+                }
+                Some(ch) => self.push_to_comment(ch),
             },
 
-            State::RcdataEndTagName => {
-                let is_appropriate = is_appropriate_end_tag(&current_tag, &open_start_tags);
-
-                match ch {
-                    // Tab | Line feed (LF) | Form feed (FF) | Space
-                    '\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' if is_appropriate => {
-                        current_state = State::BeforeAttributeName;
-                    }
-                    '/' if is_appropriate => current_state = State::SelfClosingStartTag,
-                    '>' if is_appropriate => {
-                        current_state = State::Data;
-                        tokens.push(Token::Character(ch));
-                    }
-                    _ if ch.is_ascii_uppercase() => {
-                        match &mut current_tag {
-                            Some(Tag::StartTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                            Some(Tag::EndTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                            None => unreachable!(),
-                        }
-
-                        temporary_buffer.push(ch);
-                    }
-                    _ if ch.is_ascii_lowercase() => {
-                        match &mut current_tag {
-                            Some(Tag::StartTag(tag)) => tag.tag_name.push(ch),
-                            Some(Tag::EndTag(tag)) => tag.tag_name.push(ch),
-                            None => unreachable!(),
-                        }
-
-                        temporary_buffer.push(ch);
-                    }
-                    _ => {
-                        tokens.push(Token::Character('<'));
-                        tokens.push(Token::Character('/'));
-
-                        for buffer_char in temporary_buffer.chars() {
-                            tokens.push(Token::Character(buffer_char));
-                        }
-
-                        i -= 1;
-                        current_state = State::Rcdata;
+            State::MarkupDeclarationOpen => {
+                if self.next_chars_are("--", false) {
+                    self.skip(1);
+                    self.current_comment = Cow::Borrowed("");
+                    self.current_state = State::CommentStart;
+                } else if self.next_chars_are("doctype", true) {
+                    self.skip(6);
+                    self.current_state = State::Doctype;
+                } else if self.next_chars_are("[CDATA[", false)
+                    && matches!(self.tokens.back(), Some(Token::Text(..)))
+                {
+                    // Whether this is a CDATA section depends on where the
+                    // text before it is inserted, so let it be processed
+                    // first.
+                    self.text_run_ended = true;
+                    self.reconsume = true;
+                } else if self.next_chars_are("[CDATA[", false) {
+                    self.skip(6);
+
+                    if self.in_foreign_content {
+                        self.current_state = State::CDataSection;
+                    } else {
+                        self.parse_error(ParseError::CdataInHtmlContent);
+                        self.current_comment = Cow::Borrowed("[CDATA[");
+                        self.current_state = State::BogusComment;
                     }
+                } else {
+                    self.parse_error(ParseError::IncorrectlyOpenedComment);
+                    self.current_comment = Cow::Borrowed("");
+                    self.reconsume = true;
+                    self.current_state = State::BogusComment;
                 }
             }
 
-            State::RawtextLessThanSign => match ch {
-                '/' => {
-                    temporary_buffer.clear();
-                    current_state = State::RawtextEndTagOpen;
+            State::CommentStart => match current {
+                Some('-') => self.current_state = State::CommentStartDash,
+                Some('>') => {
+                    self.parse_error(ParseError::AbruptClosingOfEmptyComment);
+                    self.current_state = State::Data;
+                    self.emit_current_comment();
                 }
                 _ => {
-                    tokens.push(Token::Character('<'));
-                    i -= 1;
-                    current_state = State::Rawtext;
+                    self.reconsume = true;
+                    self.current_state = State::Comment;
                 }
             },
 
-            State::RawtextEndTagOpen => match ch {
-                '/' => {
-                    current_tag = Some(Tag::EndTag(EndTag {
-                        tag_name: String::new(),
-                    }));
-                    i -= 1;
-                    current_state = State::RawtextEndTagName;
+            State::CommentStartDash => match current {
+                None => {
+                    self.parse_error(ParseError::EofInComment);
+                    self.emit_current_comment();
+                    self.emit_eof();
+                }
+                Some('-') => self.current_state = State::CommentEnd,
+                Some('>') => {
+                    self.parse_error(ParseError::AbruptClosingOfEmptyComment);
+                    self.current_state = State::Data;
+                    self.emit_current_comment();
                 }
                 _ => {
-                    tokens.push(Token::Character('<'));
-                    tokens.push(Token::Character('/'));
-                    i -= 1;
-                    current_state = State::Rawtext;
+                    self.push_to_comment('-');
+                    self.reconsume = true;
+                    self.current_state = State::Comment;
                 }
             },
 
-            State::RawtextEndTagName => {
-                let is_appropriate = is_appropriate_end_tag(&current_tag, &open_start_tags);
-
-                match ch {
-                    // Tab | Line feed (LF) | Form feed (FF) | Space
-                    '\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' if is_appropriate => {
-                        current_state = State::BeforeAttributeName;
-                    }
-                    '/' if is_appropriate => current_state = State::SelfClosingStartTag,
-                    '>' if is_appropriate => {
-                        current_state = State::Data;
-                        tokens.push(Token::Character(ch));
-                    }
-                    _ if ch.is_ascii_uppercase() => {
-                        match &mut current_tag {
-                            Some(Tag::StartTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                            Some(Tag::EndTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                            None => unreachable!(),
-                        }
+            State::Comment => match current {
+                None => {
+                    self.parse_error(ParseError::EofInComment);
+                    self.emit_current_comment();
+                    self.emit_eof();
+                }
+                Some('<') => {
+                    self.push_to_comment('<');
+                    self.current_state = State::CommentLessThanSign;
+                }
+                Some('-') => self.current_state = State::CommentEndDash,
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.push_to_comment('\u{FFFD}');
+                }
+                Some(ch) => self.push_to_comment(ch),
+            },
 
-                        temporary_buffer.push(ch);
-                    }
-                    _ if ch.is_ascii_lowercase() => {
-                        match &mut current_tag {
-                            Some(Tag::StartTag(tag)) => tag.tag_name.push(ch),
-                            Some(Tag::EndTag(tag)) => tag.tag_name.push(ch),
-                            None => unreachable!(),
-                        }
+            State::CommentLessThanSign => match current {
+                Some('!') => {
+                    self.push_to_comment('!');
+                    self.current_state = State::CommentLessThanSignBang;
+                }
+                Some('<') => self.push_to_comment('<'),
+                _ => {
+                    self.reconsume = true;
+                    self.current_state = State::Comment;
+                }
+            },
 
-                        temporary_buffer.push(ch);
-                    }
-                    _ => {
-                        tokens.push(Token::Character('<'));
-                        tokens.push(Token::Character('/'));
+            State::CommentLessThanSignBang => match current {
+                Some('-') => self.current_state = State::CommentLessThanSignBangDash,
+                _ => {
+                    self.reconsume = true;
+                    self.current_state = State::Comment;
+                }
+            },
 
-                        for c in temporary_buffer.chars() {
-                            tokens.push(Token::Character(c));
-                        }
+            State::CommentLessThanSignBangDash => match current {
+                Some('-') => self.current_state = State::CommentLessThanSignBangDashDash,
+                _ => {
+                    self.reconsume = true;
+                    self.current_state = State::CommentEndDash;
+                }
+            },
 
-                        i -= 1;
-                        current_state = State::Rawtext;
-                    }
+            State::CommentLessThanSignBangDashDash => {
+                // Both '>' and EOF are reconsumed in the comment end state;
+                // anything else is a nested comment.
+                if current.is_some_and(|ch| ch != '>') {
+                    self.parse_error(ParseError::NestedComment);
                 }
+
+                self.reconsume = true;
+                self.current_state = State::CommentEnd;
             }
 
-            State::ScriptDataLessThanSign => match ch {
-                '/' => {
-                    temporary_buffer.clear();
-                    current_state = State::ScriptDataEndTagOpen;
-                }
-                '!' => {
-                    current_state = State::ScriptDataEscapeStart;
-                    tokens.push(Token::Character('<'));
-                    tokens.push(Token::Character('!'));
+            State::CommentEndDash => match current {
+                None => {
+                    self.parse_error(ParseError::EofInComment);
+                    self.emit_current_comment();
+                    self.emit_eof();
                 }
+                Some('-') => self.current_state = State::CommentEnd,
                 _ => {
-                    tokens.push(Token::Character('<'));
-                    i -= 1;
-                    current_state = State::ScriptData;
+                    self.push_to_comment('-');
+                    self.reconsume = true;
+                    self.current_state = State::Comment;
                 }
             },
 
-            State::ScriptDataEndTagOpen => match ch {
-                _ if ch.is_ascii_alphabetic() => {
-                    current_tag = Some(Tag::EndTag(EndTag {
-                        tag_name: String::new(),
-                    }));
-
-                    i -= 1;
-                    current_state = State::ScriptDataEndTagName;
+            State::CommentEnd => match current {
+                None => {
+                    self.parse_error(ParseError::EofInComment);
+                    self.emit_current_comment();
+                    self.emit_eof();
+                }
+                Some('>') => {
+                    self.current_state = State::Data;
+                    self.emit_current_comment();
                 }
+                Some('!') => self.current_state = State::CommentEndBang,
+                Some('-') => self.push_to_comment('-'),
                 _ => {
-                    tokens.push(Token::Character('<'));
-                    tokens.push(Token::Character('/'));
-                    i -= 1;
-                    current_state = State::ScriptData;
+                    self.push_str_to_comment("--");
+                    self.reconsume = true;
+                    self.current_state = State::Comment;
                 }
             },
 
-            State::ScriptDataEndTagName => {
-                let is_appropriate = is_appropriate_end_tag(&current_tag, &open_start_tags);
-
-                match ch {
-                    // Tab | Line feed (LF) | Form feed (FF) | Space
-                    '\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' if is_appropriate => {
-                        current_state = State::BeforeAttributeName;
-                    }
-                    '/' if is_appropriate => current_state = State::SelfClosingStartTag,
-                    '>' if is_appropriate => {
-                        current_state = State::Data;
-                        tokens.push(Token::Character(ch));
-                    }
-                    _ if ch.is_ascii_uppercase() => {
-                        match &mut current_tag {
-                            Some(Tag::StartTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                            Some(Tag::EndTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                            None => unreachable!(),
-                        }
-
-                        temporary_buffer.push(ch);
-                    }
-                    _ if ch.is_ascii_lowercase() => {
-                        match &mut current_tag {
-                            Some(Tag::StartTag(tag)) => tag.tag_name.push(ch),
-                            Some(Tag::EndTag(tag)) => tag.tag_name.push(ch),
-                            None => unreachable!(),
-                        }
-
-                        temporary_buffer.push(ch);
-                    }
-                    _ => {
-                        tokens.push(Token::Character('<'));
-                        tokens.push(Token::Character('/'));
-
-                        for c in temporary_buffer.chars() {
-                            tokens.push(Token::Character(c));
-                        }
-
-                        i -= 1;
-                        current_state = State::Rawtext;
-                    }
+            State::CommentEndBang => match current {
+                None => {
+                    self.parse_error(ParseError::EofInComment);
+                    self.emit_current_comment();
+                    self.emit_eof();
                 }
-            }
-
-            State::ScriptDataEscapeStart => match ch {
-                '-' => {
-                    current_state = State::ScriptDataEscapeStartDash;
-                    tokens.push(Token::Character('-'));
+                Some('-') => {
+                    self.push_str_to_comment("--!");
+                    self.current_state = State::CommentEndDash;
+                }
+                Some('>') => {
+                    self.parse_error(ParseError::IncorrectlyClosedComment);
+                    self.current_state = State::Data;
+                    self.emit_current_comment();
                 }
                 _ => {
-                    i -= 1;
-                    current_state = State::ScriptData;
+                    self.push_str_to_comment("--!");
+                    self.reconsume = true;
+                    self.current_state = State::Comment;
                 }
             },
 
-            State::ScriptDataEscapeStartDash => match ch {
-                '-' => {
-                    current_state = State::ScriptDataEscapedDashDash;
-                    tokens.push(Token::Character('-'));
+            State::Doctype => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype = Doctype::default();
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {
+                    self.current_state = State::BeforeDoctypeName
+                }
+                Some('>') => {
+                    self.reconsume = true;
+                    self.current_state = State::BeforeDoctypeName;
                 }
                 _ => {
-                    i -= 1;
-                    current_state = State::ScriptData;
+                    self.parse_error(ParseError::MissingWhitespaceBeforeDoctypeName);
+                    self.reconsume = true;
+                    self.current_state = State::BeforeDoctypeName;
                 }
             },
 
-            State::ScriptDataEscaped => match ch {
-                '-' => {
-                    current_state = State::ScriptDataEscapedDash;
-                    tokens.push(Token::Character('-'));
+            State::BeforeDoctypeName => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype = Doctype::default();
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {}
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    self.current_doctype = Doctype::default();
+                    self.current_doctype.name = Some(String::from('\u{FFFD}'));
+                    self.current_state = State::DoctypeName;
+                }
+                Some('>') => {
+                    self.parse_error(ParseError::MissingDoctypeName);
+                    self.current_doctype = Doctype::default();
+                    self.current_doctype.force_quirks = true;
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some(ch) => {
+                    self.current_doctype = Doctype::default();
+                    self.current_doctype.name = Some(String::from(ch.to_ascii_lowercase()));
+                    self.current_state = State::DoctypeName;
                 }
-                '<' => current_state = State::ScriptDataEscapedLessThanSign,
-                _ if eof => tokens.push(Token::EndOfFile),
-                _ => tokens.push(Token::Character(ch)),
             },
 
-            State::ScriptDataEscapedDash => match ch {
-                '-' => {
-                    current_state = State::ScriptDataEscapedDashDash;
-                    tokens.push(Token::Character('-'));
+            State::DoctypeName => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
                 }
-                '<' => current_state = State::ScriptDataEscapedLessThanSign,
-                _ if eof => tokens.push(Token::EndOfFile),
-                _ => {
-                    current_state = State::ScriptDataEscaped;
-                    tokens.push(Token::Character(ch));
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {
+                    self.current_state = State::AfterDoctypeName
+                }
+                Some('>') => {
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    push_to_identifier(&mut self.current_doctype.name, '\u{FFFD}');
+                }
+                Some(ch) => {
+                    push_to_identifier(&mut self.current_doctype.name, ch.to_ascii_lowercase())
                 }
             },
 
-            State::ScriptDataEscapedDashDash => match ch {
-                '-' => tokens.push(Token::Character('-')),
-                '<' => current_state = State::ScriptDataEscapedLessThanSign,
-                '>' => {
-                    current_state = State::ScriptData;
-                    tokens.push(Token::Character('>'));
+            State::AfterDoctypeName => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {}
+                Some('>') => {
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
+                }
+                _ if self.next_chars_are("public", true) => {
+                    self.skip(5);
+                    self.current_state = State::AfterDoctypePublicKeyword;
+                }
+                _ if self.next_chars_are("system", true) => {
+                    self.skip(5);
+                    self.current_state = State::AfterDoctypeSystemKeyword;
                 }
-                _ if eof => tokens.push(Token::EndOfFile),
                 _ => {
-                    current_state = State::ScriptDataEscaped;
-                    tokens.push(Token::Character(ch));
+                    self.parse_error(ParseError::InvalidCharacterSequenceAfterDoctypeName);
+                    self.current_doctype.force_quirks = true;
+                    self.reconsume = true;
+                    self.current_state = State::BogusDoctype;
                 }
             },
 
-            State::ScriptDataEscapedLessThanSign => match ch {
-                '/' => {
-                    temporary_buffer.clear();
-                    current_state = State::ScriptDataEscapedEndTagOpen;
+            State::AfterDoctypePublicKeyword => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {
+                    self.current_state = State::BeforeDoctypePublicIdentifier
+                }
+                Some('"') => {
+                    self.parse_error(ParseError::MissingWhitespaceAfterDoctypePublicKeyword);
+                    self.current_doctype.public_id = Some(String::new());
+                    self.current_state = State::DoctypePublicIdentifierDoubleQuoted;
                 }
-                _ if ch.is_ascii_alphabetic() => {
-                    temporary_buffer.clear();
-                    tokens.push(Token::Character('<'));
-                    i -= 1;
-                    current_state = State::ScriptDataDoubleEscapeStart;
+                Some('\'') => {
+                    self.parse_error(ParseError::MissingWhitespaceAfterDoctypePublicKeyword);
+                    self.current_doctype.public_id = Some(String::new());
+                    self.current_state = State::DoctypePublicIdentifierSingleQuoted;
+                }
+                Some('>') => {
+                    self.parse_error(ParseError::MissingDoctypePublicIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
                 }
                 _ => {
-                    tokens.push(Token::Character('<'));
-                    i -= 1;
-                    current_state = State::ScriptDataEscaped;
+                    self.parse_error(ParseError::MissingQuoteBeforeDoctypePublicIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.reconsume = true;
+                    self.current_state = State::BogusDoctype;
                 }
             },
 
-            State::ScriptDataEscapedEndTagOpen => match ch {
-                _ if ch.is_ascii_alphabetic() => {
-                    current_tag = Some(Tag::EndTag(EndTag {
-                        tag_name: String::new(),
-                    }));
-
-                    i -= 1;
-                    current_state = State::ScriptDataEscapedEndTagName;
+            State::BeforeDoctypePublicIdentifier => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {}
+                Some('"') => {
+                    self.current_doctype.public_id = Some(String::new());
+                    self.current_state = State::DoctypePublicIdentifierDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.current_doctype.public_id = Some(String::new());
+                    self.current_state = State::DoctypePublicIdentifierSingleQuoted;
+                }
+                Some('>') => {
+                    self.parse_error(ParseError::MissingDoctypePublicIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
                 }
                 _ => {
-                    tokens.push(Token::Character('<'));
-                    tokens.push(Token::Character('/'));
-                    i -= 1;
-                    current_state = State::ScriptDataEscaped;
+                    self.parse_error(ParseError::MissingQuoteBeforeDoctypePublicIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.reconsume = true;
+                    self.current_state = State::BogusDoctype;
                 }
             },
 
-            State::ScriptDataEscapedEndTagName => {
-                let is_appropriate = is_appropriate_end_tag(&current_tag, &open_start_tags);
-
-                match ch {
-                    // Tab | Line feed (LF) | Form feed (FF) | Space
-                    '\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' if is_appropriate => {
-                        current_state = State::BeforeAttributeName;
-                    }
-                    '/' if is_appropriate => current_state = State::SelfClosingStartTag,
-                    '>' if is_appropriate => {
-                        current_state = State::Data;
-                        tokens.push(Token::Character(ch));
-                    }
-                    _ if ch.is_ascii_uppercase() => {
-                        match &mut current_tag {
-                            Some(Tag::StartTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                            Some(Tag::EndTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                            None => unreachable!(),
-                        };
-
-                        temporary_buffer.push(ch);
-                    }
-
-                    _ if ch.is_ascii_lowercase() => {
-                        match &mut current_tag {
-                            Some(Tag::StartTag(tag)) => tag.tag_name.push(ch),
-                            Some(Tag::EndTag(tag)) => tag.tag_name.push(ch),
-                            None => unreachable!(),
-                        };
-
-                        temporary_buffer.push(ch);
-                    }
-
-                    _ => {
-                        tokens.push(Token::Character('<'));
-                        tokens.push(Token::Character('/'));
+            State::DoctypePublicIdentifierDoubleQuoted => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+                Some('"') => self.current_state = State::AfterDoctypePublicIdentifier,
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    push_to_identifier(&mut self.current_doctype.public_id, '\u{FFFD}');
+                }
+                Some('>') => {
+                    self.parse_error(ParseError::AbruptDoctypePublicIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some(ch) => push_to_identifier(&mut self.current_doctype.public_id, ch),
+            },
 
-                        for buff_char in temporary_buffer.chars() {
-                            tokens.push(Token::Character(buff_char));
-                        }
+            State::DoctypePublicIdentifierSingleQuoted => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+                Some('\'') => self.current_state = State::AfterDoctypePublicIdentifier,
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    push_to_identifier(&mut self.current_doctype.public_id, '\u{FFFD}');
+                }
+                Some('>') => {
+                    self.parse_error(ParseError::AbruptDoctypePublicIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some(ch) => push_to_identifier(&mut self.current_doctype.public_id, ch),
+            },
 
-                        i -= 1;
-                        current_state = State::ScriptData;
-                    }
+            State::AfterDoctypePublicIdentifier => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
                 }
-            }
-
-            State::ScriptDataEscapeStart => match ch {
-                '-' => {
-                    current_state = State::ScriptDataEscapeStartDash;
-                    tokens.push(Token::Character('-'));
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {
+                    self.current_state = State::BetweenDoctypePublicAndSystemIdentifiers
+                }
+                Some('>') => {
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some('"') => {
+                    self.parse_error(
+                        ParseError::MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers,
+                    );
+                    self.current_doctype.system_id = Some(String::new());
+                    self.current_state = State::DoctypeSystemIdentifierDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.parse_error(
+                        ParseError::MissingWhitespaceBetweenDoctypePublicAndSystemIdentifiers,
+                    );
+                    self.current_doctype.system_id = Some(String::new());
+                    self.current_state = State::DoctypeSystemIdentifierSingleQuoted;
                 }
                 _ => {
-                    i -= 1;
-                    current_state = State::ScriptData;
+                    self.parse_error(ParseError::MissingQuoteBeforeDoctypeSystemIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.reconsume = true;
+                    self.current_state = State::BogusDoctype;
                 }
             },
 
-            State::ScriptDataEscapeStartDash => match ch {
-                '-' => {
-                    current_state = State::ScriptDataEscapedDashDash;
-                    tokens.push(Token::Character('-'));
+            State::BetweenDoctypePublicAndSystemIdentifiers => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {}
+                Some('>') => {
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some('"') => {
+                    self.current_doctype.system_id = Some(String::new());
+                    self.current_state = State::DoctypeSystemIdentifierDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.current_doctype.system_id = Some(String::new());
+                    self.current_state = State::DoctypeSystemIdentifierSingleQuoted;
                 }
                 _ => {
-                    i -= 1;
-                    current_state = State::ScriptData;
+                    self.parse_error(ParseError::MissingQuoteBeforeDoctypeSystemIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.reconsume = true;
+                    self.current_state = State::BogusDoctype;
                 }
             },
 
-            State::ScriptDataEscaped => match ch {
-                '-' => {
-                    current_state = State::ScriptDataEscapedDash;
-                    tokens.push(Token::Character('-'));
+            State::AfterDoctypeSystemKeyword => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
                 }
-                '<' => current_state = State::ScriptDataEscapedLessThanSign,
-                _ if eof => tokens.push(Token::EndOfFile),
-                _ => tokens.push(Token::Character(ch)),
-            },
-
-            State::ScriptDataEscapedDash => match ch {
-                '-' => {
-                    current_state = State::ScriptDataEscapedDashDash;
-                    tokens.push(Token::Character('-'));
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {
+                    self.current_state = State::BeforeDoctypeSystemIdentifier
+                }
+                Some('"') => {
+                    self.parse_error(ParseError::MissingWhitespaceAfterDoctypeSystemKeyword);
+                    self.current_doctype.system_id = Some(String::new());
+                    self.current_state = State::DoctypeSystemIdentifierDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.parse_error(ParseError::MissingWhitespaceAfterDoctypeSystemKeyword);
+                    self.current_doctype.system_id = Some(String::new());
+                    self.current_state = State::DoctypeSystemIdentifierSingleQuoted;
+                }
+                Some('>') => {
+                    self.parse_error(ParseError::MissingDoctypeSystemIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
                 }
-                '<' => current_state = State::ScriptDataEscapedLessThanSign,
-                _ if eof => tokens.push(Token::EndOfFile),
                 _ => {
-                    current_state = State::ScriptDataEscaped;
-                    tokens.push(Token::Character(ch));
+                    self.parse_error(ParseError::MissingQuoteBeforeDoctypeSystemIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.reconsume = true;
+                    self.current_state = State::BogusDoctype;
                 }
             },
 
-            State::ScriptDataEscapedDashDash => match ch {
-                '-' => tokens.push(Token::Character('-')),
-                '<' => current_state = State::ScriptDataEscapedLessThanSign,
-                '>' => {
-                    current_state = State::ScriptData;
-                    tokens.push(Token::Character('>'));
+            State::BeforeDoctypeSystemIdentifier => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {}
+                Some('"') => {
+                    self.current_doctype.system_id = Some(String::new());
+                    self.current_state = State::DoctypeSystemIdentifierDoubleQuoted;
+                }
+                Some('\'') => {
+                    self.current_doctype.system_id = Some(String::new());
+                    self.current_state = State::DoctypeSystemIdentifierSingleQuoted;
+                }
+                Some('>') => {
+                    self.parse_error(ParseError::MissingDoctypeSystemIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
                 }
-                _ if eof => tokens.push(Token::EndOfFile),
                 _ => {
-                    current_state = State::ScriptDataEscaped;
-                    tokens.push(Token::Character(ch));
+                    self.parse_error(ParseError::MissingQuoteBeforeDoctypeSystemIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.reconsume = true;
+                    self.current_state = State::BogusDoctype;
                 }
             },
 
-            State::ScriptDataEscapedLessThanSign => match ch {
-                '/' => {
-                    temporary_buffer.clear();
-                    current_state = State::ScriptDataEscapedEndTagOpen;
+            State::DoctypeSystemIdentifierDoubleQuoted => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+                Some('"') => self.current_state = State::AfterDoctypeSystemIdentifier,
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    push_to_identifier(&mut self.current_doctype.system_id, '\u{FFFD}');
+                }
+                Some('>') => {
+                    self.parse_error(ParseError::AbruptDoctypeSystemIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some(ch) => push_to_identifier(&mut self.current_doctype.system_id, ch),
+            },
+
+            State::DoctypeSystemIdentifierSingleQuoted => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+                Some('\'') => self.current_state = State::AfterDoctypeSystemIdentifier,
+                Some('\0') => {
+                    self.parse_error(ParseError::UnexpectedNullCharacter);
+                    push_to_identifier(&mut self.current_doctype.system_id, '\u{FFFD}');
+                }
+                Some('>') => {
+                    self.parse_error(ParseError::AbruptDoctypeSystemIdentifier);
+                    self.current_doctype.force_quirks = true;
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some(ch) => push_to_identifier(&mut self.current_doctype.system_id, ch),
+            },
+
+            State::AfterDoctypeSystemIdentifier => match current {
+                None => {
+                    self.parse_error(ParseError::EofInDoctype);
+                    self.current_doctype.force_quirks = true;
+                    self.emit_current_doctype();
+                    self.emit_eof();
                 }
-                _ if ch.is_ascii_alphabetic() => {
-                    temporary_buffer.clear();
-                    tokens.push(Token::Character('<'));
-                    i -= 1;
-                    current_state = State::ScriptDataDoubleEscapeStart;
+                // Tab | Line feed (LF) | Form feed (FF) | Space
+                Some('\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}') => {}
+                Some('>') => {
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
                 }
                 _ => {
-                    tokens.push(Token::Character('<'));
-                    i -= 1;
-                    current_state = State::ScriptDataEscaped;
+                    self.parse_error(ParseError::UnexpectedCharacterAfterDoctypeSystemIdentifier);
+                    self.reconsume = true;
+                    self.current_state = State::BogusDoctype;
                 }
             },
 
-            State::ScriptDataEscapedEndTagOpen => match ch {
-                _ if ch.is_ascii_alphabetic() => {
-                    current_tag = Some(Tag::EndTag(EndTag {
-                        tag_name: String::new(),
-                    }));
-
-                    i -= 1;
-                    current_state = State::ScriptDataEscapedEndTagName;
+            State::BogusDoctype => match current {
+                Some('\0') => self.parse_error(ParseError::UnexpectedNullCharacter),
+                None => {
+                    self.emit_current_doctype();
+                    self.emit_eof();
                 }
-                _ => {
-                    tokens.push(Token::Character('<'));
-                    tokens.push(Token::Character('/'));
-                    i -= 1;
-                    current_state = State::ScriptDataEscaped;
+                Some('>') => {
+                    self.current_state = State::Data;
+                    self.emit_current_doctype();
                 }
+                _ => {}
             },
 
-            State::ScriptDataEscapedEndTagName => {
-                let is_appropriate = is_appropriate_end_tag(&current_tag, &open_start_tags);
+            State::CharacterReference => {
+                self.temporary_buffer.clear();
+                self.temporary_buffer.push('&');
 
-                match ch {
-                    // Tab | Line feed (LF) | Form feed (FF) | Space
-                    '\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' if is_appropriate => {
-                        current_state = State::BeforeAttributeName;
+                match current {
+                    Some(ch) if ch.is_ascii_alphanumeric() => {
+                        self.reconsume = true;
+                        self.current_state = State::NamedCharacterReference;
                     }
-                    '/' if is_appropriate => current_state = State::SelfClosingStartTag,
-                    '>' if is_appropriate => {
-                        current_state = State::Data;
-                        tokens.push(Token::Character(ch));
+                    Some('#') => {
+                        self.temporary_buffer.push('#');
+                        self.current_state = State::NumericCharacterReference;
                     }
-                    _ if ch.is_ascii_uppercase() => {
-                        match &mut current_tag {
-                            Some(Tag::StartTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                            Some(Tag::EndTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                            None => unreachable!(),
-                        };
-
-                        temporary_buffer.push(ch);
-                    }
-
-                    _ if ch.is_ascii_lowercase() => {
-                        match &mut current_tag {
-                            Some(Tag::StartTag(tag)) => tag.tag_name.push(ch),
-                            Some(Tag::EndTag(tag)) => tag.tag_name.push(ch),
-                            None => unreachable!(),
-                        };
-
-                        temporary_buffer.push(ch);
+                    _ => {
+                        self.flush_character_reference();
+                        self.reconsume = true;
+                        self.current_state = self.return_state;
                     }
+                }
+            }
 
-                    _ => {
-                        tokens.push(Token::Character('<'));
-                        tokens.push(Token::Character('/'));
+            State::NamedCharacterReference => {
+                match longest_named_character_reference(&self.input[self.char_start..]) {
+                    Some((name_length, characters)) => {
+                        let name = &self.input[self.char_start..self.char_start + name_length];
+                        let last_char = name.chars().last().unwrap();
+                        let next_char = self.input[self.char_start + name_length..].chars().next();
+
+                        self.temporary_buffer.push_str(name);
+
+                        // For historical reasons, an unterminated reference
+                        // in an attribute value followed by '=' or an
+                        // alphanumeric is not decoded, e.g. "?a=1&copy=2".
+                        let is_historical_attribute_case =
+                            is_consumed_in_attribute(&self.return_state)
+                                && last_char != ';'
+                                && next_char.is_some_and(|c| c == '=' || c.is_ascii_alphanumeric());
+
+                        if !is_historical_attribute_case {
+                            self.temporary_buffer.clear();
+                            self.temporary_buffer.push_str(characters);
+                        }
 
-                        for buff_char in temporary_buffer.chars() {
-                            tokens.push(Token::Character(buff_char));
+                        // Consume the rest of the name first, so that the
+                        // characters' spans cover the whole reference.
+                        self.skip(name_length - 1);
+                        if !is_historical_attribute_case && last_char != ';' {
+                            self.parse_error(ParseError::MissingSemicolonAfterCharacterReference);
                         }
 
-                        i -= 1;
-                        current_state = State::ScriptData;
+                        self.flush_character_reference();
+                        self.current_state = self.return_state;
+                    }
+                    None => {
+                        self.flush_character_reference();
+
+                        self.reconsume = true;
+                        self.current_state = State::AmbiguousAmpersand;
                     }
                 }
             }
 
-            State::ScriptDataDoubleEscapeStart => match ch {
-                '\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' | '/' | '>' => {
-                    if temporary_buffer == "script".to_string() {
-                        current_state = State::ScriptDataDoubleEscaped;
+            State::AmbiguousAmpersand => match current {
+                Some(ch) if ch.is_ascii_alphanumeric() => {
+                    if is_consumed_in_attribute(&self.return_state) {
+                        self.push_to_attribute_value(ch);
                     } else {
-                        current_state = State::ScriptDataEscaped;
+                        self.emit_char(ch);
                     }
-
-                    tokens.push(Token::Character(ch));
                 }
-                _ if ch.is_ascii_uppercase() => {
-                    match &mut current_tag {
-                        Some(Tag::StartTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                        Some(Tag::EndTag(tag)) => tag.tag_name.push(ch.to_ascii_lowercase()),
-                        None => unreachable!(),
-                    };
-
-                    tokens.push(Token::Character(ch));
-                }
-
-                _ if ch.is_ascii_lowercase() => {
-                    match &mut current_tag {
-                        Some(Tag::StartTag(tag)) => tag.tag_name.push(ch),
-                        Some(Tag::EndTag(tag)) => tag.tag_name.push(ch),
-                        None => unreachable!(),
-                    };
-
-                    tokens.push(Token::Character(ch));
+                Some(';') => {
+                    self.parse_error(ParseError::UnknownNamedCharacterReference);
+                    self.reconsume = true;
+                    self.current_state = self.return_state;
                 }
-
                 _ => {
-                    i -= 1;
-                    current_state = State::ScriptDataEscaped;
+                    self.reconsume = true;
+                    self.current_state = self.return_state;
                 }
             },
 
-            State::ScriptDataDoubleEscaped => match ch {
-                '-' => {
-                    current_state = State::ScriptDataDoubleEscapedDash;
-                    tokens.push(Token::Character('-'));
+            State::NumericCharacterReference => {
+                self.character_reference_code = 0;
+
+                match current {
+                    Some(ch @ ('x' | 'X')) => {
+                        self.temporary_buffer.push(ch);
+                        self.current_state = State::HexadecimalCharacterReferenceStart;
+                    }
+                    _ => {
+                        self.reconsume = true;
+                        self.current_state = State::DecimalCharacterReferenceStart;
+                    }
+                }
+            }
+
+            State::HexadecimalCharacterReferenceStart => match current {
+                Some(ch) if ch.is_ascii_hexdigit() => {
+                    self.reconsume = true;
+                    self.current_state = State::HexadecimalCharacterReference;
                 }
-                '<' => {
-                    current_state = State::ScriptDataDoubleEscapedLessThanSign;
-                    tokens.push(Token::Character('<'));
+                _ => {
+                    self.parse_error(ParseError::AbsenceOfDigitsInNumericCharacterReference);
+                    self.flush_character_reference();
+                    self.reconsume = true;
+                    self.current_state = self.return_state;
                 }
-                _ if eof => tokens.push(Token::EndOfFile),
-                _ => tokens.push(Token::Character(ch)),
             },
 
-            State::ScriptDataDoubleEscapedDash => match ch {
-                '-' => {
-                    current_state = State::ScriptDataDoubleEscapedDashDash;
-                    tokens.push(Token::Character('-'));
-                }
-                '<' => {
-                    current_state = State::ScriptDataDoubleEscapedLessThanSign;
-                    tokens.push(Token::Character('<'));
+            State::DecimalCharacterReferenceStart => match current {
+                Some(ch) if ch.is_ascii_digit() => {
+                    self.reconsume = true;
+                    self.current_state = State::DecimalCharacterReference;
                 }
-                _ if eof => tokens.push(Token::EndOfFile),
                 _ => {
-                    current_state = State::ScriptDataDoubleEscaped;
-                    tokens.push(Token::Character(ch));
+                    self.parse_error(ParseError::AbsenceOfDigitsInNumericCharacterReference);
+                    self.flush_character_reference();
+                    self.reconsume = true;
+                    self.current_state = self.return_state;
                 }
             },
 
-            State::ScriptDataDoubleEscapedDashDash => match ch {
-                '-' => tokens.push(Token::Character('-')),
-                '<' => {
-                    current_state = State::ScriptDataDoubleEscapedLessThanSign;
-                    tokens.push(Token::Character('<'));
+            State::HexadecimalCharacterReference => match current {
+                Some(ch) if ch.is_ascii_hexdigit() => {
+                    // Clamping keeps the code out of range without overflowing.
+                    self.character_reference_code = (self.character_reference_code * 16
+                        + ch.to_digit(16).unwrap())
+                    .min(0x110000);
                 }
-                '>' => {
-                    current_state = State::ScriptData;
-                    tokens.push(Token::Character('>'));
-                }
-                _ if eof => tokens.push(Token::EndOfFile),
+                Some(';') => self.current_state = State::NumericCharacterReferenceEnd,
                 _ => {
-                    current_state = State::ScriptDataDoubleEscaped;
-                    tokens.push(Token::Character(ch));
+                    self.parse_error(ParseError::MissingSemicolonAfterCharacterReference);
+                    self.reconsume = true;
+                    self.current_state = State::NumericCharacterReferenceEnd;
                 }
             },
 
-            State::ScriptDataDoubleEscapedLessThanSign => match ch {
-                '/' => {
-                    temporary_buffer.clear();
-                    current_state = State::ScriptDataDoubleEscapeEnd;
-                    tokens.push(Token::Character('/'));
+            State::DecimalCharacterReference => match current {
+                Some(ch) if ch.is_ascii_digit() => {
+                    self.character_reference_code = (self.character_reference_code * 10
+                        + ch.to_digit(10).unwrap())
+                    .min(0x110000);
                 }
+                Some(';') => self.current_state = State::NumericCharacterReferenceEnd,
                 _ => {
-                    i -= 1;
-                    current_state = State::ScriptDataDoubleEscaped;
+                    self.parse_error(ParseError::MissingSemicolonAfterCharacterReference);
+                    self.reconsume = true;
+                    self.current_state = State::NumericCharacterReferenceEnd;
                 }
             },
 
-            State::ScriptDataDoubleEscapeEnd => match ch {
-                '\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' | '/' | '>' => {
-                    if temporary_buffer == "script".to_string() {
-                        current_state = State::ScriptDataEscaped;
-                    } else {
-                        current_state = State::ScriptDataDoubleEscaped;
-                    }
+            State::NumericCharacterReferenceEnd => {
+                let (character, error) = numeric_character_reference(self.character_reference_code);
 
-                    tokens.push(Token::Character(ch));
+                if let Some(error) = error {
+                    self.parse_error(error);
                 }
 
-                _ if ch.is_ascii_uppercase() => {
-                    temporary_buffer.push(ch.to_ascii_lowercase());
-                    tokens.push(Token::Character(ch));
-                }
+                self.temporary_buffer.clear();
+                self.temporary_buffer.push(character);
+
+                self.flush_character_reference();
+
+                self.reconsume = true;
+                self.current_state = self.return_state;
+            }
 
-                _ if ch.is_ascii_lowercase() => {
-                    temporary_buffer.push(ch);
-                    tokens.push(Token::Character(ch));
+            State::CDataSection => match current {
+                None => {
+                    self.parse_error(ParseError::EofInCdata);
+                    self.emit_eof();
                 }
+                Some(']') => self.current_state = State::CDataSectionBracket,
+                Some(ch) => self.emit_char(ch),
+            },
 
+            State::CDataSectionBracket => match current {
+                Some(']') => self.current_state = State::CDataSectionEnd,
                 _ => {
-                    i -= 1;
-                    current_state = State::ScriptDataDoubleEscaped;
+                    self.emit_char(']');
+                    self.reconsume = true;
+                    self.current_state = State::CDataSection;
                 }
             },
 
-            State::BeforeAttributeName => match ch {
-                // Tab | Line feed (LF) | Form feed (FF) | Space
-                '\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' => {}
-                '/' | '>' | _ if eof => {
-                    i -= 1;
-                    current_state = State::AfterAttributeName;
+            State::CDataSectionEnd => match current {
+                Some(']') => self.emit_char(']'),
+                Some('>') => {
+                    self.skip_token_span();
+                    self.current_state = State::Data;
+                }
+                _ => {
+                    self.emit_char(']');
+                    self.emit_char(']');
+                    self.reconsume = true;
+                    self.current_state = State::CDataSection;
                 }
-                '=' => match &mut current_tag {
-                    Some(Tag::StartTag(tag)) => {
-                        tag.attributes.push(Attribute {
-                            name: String::from(ch),
-                            value: String::new(),
-                        });
+            },
+        }
 
-                        current_state = State::AttributeName;
-                    }
+        if self.reconsume {
+            self.end_spans_before_reconsumed_char(queued);
+        }
 
-                    _ => unreachable!(),
-                },
+        true
+    }
+}
 
-                _ => match &mut current_tag {
-                    Some(Tag::StartTag(tag)) => {
-                        tag.attributes.push(Attribute {
-                            name: String::new(),
-                            value: String::new(),
-                        });
-                        i -= 1;
-                        current_state = State::AttributeName;
-                    }
-                    _ => unreachable!(),
-                },
-            },
+impl Default for Tokenizer<'static> {
+    fn default() -> Tokenizer<'static> {
+        Tokenizer::new()
+    }
+}
 
-            State::AttributeName => match ch {
-                '\u{0009}' | '\u{000A}' | '\u{000C}' | '\u{0020}' | '/' | '>' | _ if eof => {
-                    i -= 1;
-                    current_state = State::AfterAttributeName;
-                }
-                '=' => current_state = State::BeforeAttributeValue,
-                _ if ch.is_ascii_uppercase() => match current_tag {
-                    Some(Tag::)
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        loop {
+            // A text run at the end of the queue may still grow, so it is
+            // held back until another token follows it.
+            let growing_text_run = !self.emitted_eof
+                && !self.text_run_ended
+                && self.tokens.len() == 1
+                && matches!(self.tokens.front(), Some(Token::Text(..)));
+
+            if !growing_text_run {
+                if let Some(token) = self.tokens.pop_front() {
+                    self.text_run_ended = false;
+                    return Some(token);
                 }
-            },
+            }
+
+            if self.emitted_eof || !self.step() {
+                return None;
+            }
         }
+    }
+}
 
-        i += 1;
+/// The state `StatePolicy::ByTagName` switches to after a start tag called
+/// `tag_name`.
+fn state_after_start_tag(tag_name: &LocalName) -> Option<State> {
+    match *tag_name {
+        local_name::title | local_name::textarea => Some(State::Rcdata),
+        local_name::style
+        | local_name::xmp
+        | local_name::iframe
+        | local_name::noembed
+        | local_name::noframes => Some(State::Rawtext),
+        local_name::script => Some(State::ScriptData),
+        local_name::plaintext => Some(State::Plaintext),
+        _ => None,
+    }
+}
+
+/// Appends `addition` to `text`. Text that is empty or borrowed from
+/// `input` stays borrowed for as long as `addition` is what follows it in
+/// `input`, `at` being where an empty text would start.
+fn append<'a>(text: &mut Cow<'a, str>, addition: &str, input: Option<&'a str>, at: usize) {
+    if let Some(input) = input {
+        let start = match text {
+            Cow::Borrowed(borrowed) if !borrowed.is_empty() => offset_in(input, borrowed),
+            _ if text.is_empty() => Some(at),
+            _ => None,
+        };
+
+        if let Some(start) = start {
+            let end = start + text.len();
+            if input[end..].starts_with(addition) {
+                *text = Cow::Borrowed(&input[start..end + addition.len()]);
+                return;
+            }
+        }
     }
 
-    tokens
+    text.to_mut().push_str(addition);
 }
 
-fn is_appropriate_end_tag(current_tag: &Option<Tag>, open_start_tags: &Vec<&StartTag>) -> bool {
-    return match current_tag {
-        Some(Tag::EndTag(end_tag)) => {
-            if let Some(last_start_tag) = open_start_tags.last() {
-                return end_tag.tag_name == last_start_tag.tag_name;
+/// Appends `text`, found in the input at byte `at` and at `position`, to the
+/// text runs at the end of `tokens`, starting new runs where `Token::Text`
+/// requires. `text` contains no U+0000. The span of the first new run starts
+/// at `span_start`.
+fn push_str_to_text_runs<'a>(
+    tokens: &mut VecDeque<Token<'a>>,
+    mut text: &str,
+    input: Option<&'a str>,
+    mut at: usize,
+    mut span_start: Position,
+    mut position: Position,
+) {
+    let leading_whitespace = |text: &str| {
+        text.bytes()
+            .take_while(|byte| byte.is_ascii_whitespace())
+            .count()
+    };
+
+    if let Some(Token::Text(run, span)) = tokens.back_mut() {
+        if run != "\0" {
+            let whitespace_run = run.starts_with(|c: char| c.is_ascii_whitespace());
+            let length = if whitespace_run {
+                leading_whitespace(text)
             } else {
-                return false;
+                text.len()
+            };
+
+            append(run, &text[..length], input, at);
+            position.advance_str(&text[..length]);
+            span.end = position;
+            span_start = position;
+            text = &text[length..];
+            at += length;
+        }
+    }
+
+    while !text.is_empty() {
+        let length = match leading_whitespace(text) {
+            0 => text.len(),
+            whitespace => whitespace,
+        };
+
+        let mut run = Cow::Borrowed("");
+        append(&mut run, &text[..length], input, at);
+        position.advance_str(&text[..length]);
+        let span = Span {
+            start: span_start,
+            end: position,
+        };
+        tokens.push_back(Token::Text(run, span));
+        span_start = position;
+        text = &text[length..];
+        at += length;
+    }
+}
+
+/// How many bytes `Tokenizer::consume_run` searches for a delimiter at a
+/// time.
+const RUN_SEARCH_BLOCK_SIZE: usize = 1024;
+
+/// Scans `text` from byte `from` to byte `to` for a character that input
+/// preprocessing does not leave as it is: a CR, or a control or
+/// noncharacter that is a parse error. Returns its offset if there is one,
+/// and otherwise `to`, or the end of a character that straddles it.
+fn unprocessed_prefix_end(text: &str, from: usize, to: usize) -> usize {
+    let bytes = text.as_bytes();
+    let mut index = from;
+
+    while index < to {
+        match bytes[index] {
+            b'\t' | b'\n' | b'\x0C' | b' '..=b'~' => index += 1,
+            // Lead bytes of C1 controls and noncharacters.
+            0xC2 | 0xEF..=0xF4 => {
+                let ch = text[index..].chars().next().unwrap();
+                if is_input_stream_error(ch) {
+                    break;
+                }
+                index += ch.len_utf8();
             }
+            0x80..=0xFF => index += 1,
+            _ => break,
         }
-        _ => false,
-    };
+    }
+
+    index
+}
+
+/// Whether `ch` is a control or noncharacter that is a parse error in the
+/// input stream.
+fn is_input_stream_error(ch: char) -> bool {
+    is_noncharacter(ch as u32) || (ch.is_control() && !ch.is_ascii_whitespace() && ch != '\0')
+}
+
+/// The byte offset of `slice` in `input`, if it is a slice of `input`.
+fn offset_in(input: &str, slice: &str) -> Option<usize> {
+    let offset = (slice.as_ptr() as usize).checked_sub(input.as_ptr() as usize)?;
+    (offset + slice.len() <= input.len()).then_some(offset)
+}
+
+fn get_attr<'t>(attributes: &'t [Attribute<'_>], name: &str) -> Option<&'t str> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.name == *name)
+        .map(|attribute| &*attribute.value)
+}
+
+/// Writes `text` escaped as the HTML Standard serializes text and, with
+/// `in_attribute`, double-quoted attribute values.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#escapingString
+fn write_escaped(f: &mut fmt::Formatter, text: &str, in_attribute: bool) -> fmt::Result {
+    let mut rest = text;
+    while let Some(index) = rest.find(['&', '\u{A0}', '"', '<', '>']) {
+        let (plain, special) = rest.split_at(index);
+        f.write_str(plain)?;
+
+        let ch = special.chars().next().unwrap();
+        match ch {
+            '&' => f.write_str("&amp;")?,
+            '\u{A0}' => f.write_str("&nbsp;")?,
+            '"' if in_attribute => f.write_str("&quot;")?,
+            '<' => f.write_str("&lt;")?,
+            '>' => f.write_str("&gt;")?,
+            _ => write!(f, "{}", ch)?,
+        }
+        rest = &special[ch.len_utf8()..];
+    }
+    f.write_str(rest)
+}
+
+/// A DOCTYPE identifier in whichever quotes it does not contain.
+fn quoted_identifier(identifier: &str) -> String {
+    if identifier.contains('"') {
+        format!("'{}'", identifier)
+    } else {
+        format!("\"{}\"", identifier)
+    }
+}
+
+fn push_to_identifier(identifier: &mut Option<String>, ch: char) {
+    identifier.get_or_insert_with(String::new).push(ch);
+}
+
+fn tag_attributes<'t, 'a>(current_tag: &'t mut Option<Tag<'a>>) -> &'t mut Vec<Attribute<'a>> {
+    &mut current_tag.as_mut().unwrap().attributes
+}
+
+fn current_attribute<'t, 'a>(current_tag: &'t mut Option<Tag<'a>>) -> &'t mut Attribute<'a> {
+    tag_attributes(current_tag).last_mut().unwrap()
+}
+
+/// Finds the longest named character reference at the start of `input`,
+/// returning the length of its name and the characters it stands for.
+fn longest_named_character_reference(input: &str) -> Option<(usize, &'static str)> {
+    let mut name = String::new();
+
+    for c in input.chars().take(entities::LONGEST_NAME_LENGTH) {
+        if !c.is_ascii_alphanumeric() && c != ';' {
+            break;
+        }
+
+        name.push(c);
+
+        if c == ';' {
+            break;
+        }
+    }
+
+    (1..=name.len())
+        .rev()
+        .find_map(|length| entities::lookup(&name[..length]).map(|characters| (length, characters)))
+}
+
+/// Maps a numeric character reference code to the character it stands for,
+/// replacing the code points the HTML Standard does not allow and reporting
+/// the parse error they cause.
+fn numeric_character_reference(code: u32) -> (char, Option<ParseError>) {
+    match code {
+        0x00 => ('\u{FFFD}', Some(ParseError::NullCharacterReference)),
+        0x110000.. => (
+            '\u{FFFD}',
+            Some(ParseError::CharacterReferenceOutsideUnicodeRange),
+        ),
+        0xD800..=0xDFFF => ('\u{FFFD}', Some(ParseError::SurrogateCharacterReference)),
+        _ if is_noncharacter(code) => (
+            char::from_u32(code).unwrap(),
+            Some(ParseError::NoncharacterCharacterReference),
+        ),
+        0x80..=0x9F => {
+            let character = WINDOWS_1252_REPLACEMENTS[(code - 0x80) as usize]
+                .unwrap_or_else(|| char::from_u32(code).unwrap());
+
+            (character, Some(ParseError::ControlCharacterReference))
+        }
+        // Controls other than ASCII whitespace, plus carriage return.
+        0x01..=0x08 | 0x0B | 0x0D..=0x1F | 0x7F => (
+            char::from_u32(code).unwrap(),
+            Some(ParseError::ControlCharacterReference),
+        ),
+        _ => (char::from_u32(code).unwrap(), None),
+    }
+}
+
+fn is_noncharacter(code: u32) -> bool {
+    matches!(code, 0xFDD0..=0xFDEF) || (code & 0xFFFE == 0xFFFE && code <= 0x10FFFF)
+}
+
+/// The characters that numeric references to the C1 control range are
+/// replaced with, following windows-1252.
+const WINDOWS_1252_REPLACEMENTS: [Option<char>; 32] = [
+    Some('\u{20AC}'), // 0x80 EURO SIGN
+    None,
+    Some('\u{201A}'), // 0x82 SINGLE LOW-9 QUOTATION MARK
+    Some('\u{0192}'), // 0x83 LATIN SMALL LETTER F WITH HOOK
+    Some('\u{201E}'), // 0x84 DOUBLE LOW-9 QUOTATION MARK
+    Some('\u{2026}'), // 0x85 HORIZONTAL ELLIPSIS
+    Some('\u{2020}'), // 0x86 DAGGER
+    Some('\u{2021}'), // 0x87 DOUBLE DAGGER
+    Some('\u{02C6}'), // 0x88 MODIFIER LETTER CIRCUMFLEX ACCENT
+    Some('\u{2030}'), // 0x89 PER MILLE SIGN
+    Some('\u{0160}'), // 0x8A LATIN CAPITAL LETTER S WITH CARON
+    Some('\u{2039}'), // 0x8B SINGLE LEFT-POINTING ANGLE QUOTATION MARK
+    Some('\u{0152}'), // 0x8C LATIN CAPITAL LIGATURE OE
+    None,
+    Some('\u{017D}'), // 0x8E LATIN CAPITAL LETTER Z WITH CARON
+    None,
+    None,
+    Some('\u{2018}'), // 0x91 LEFT SINGLE QUOTATION MARK
+    Some('\u{2019}'), // 0x92 RIGHT SINGLE QUOTATION MARK
+    Some('\u{201C}'), // 0x93 LEFT DOUBLE QUOTATION MARK
+    Some('\u{201D}'), // 0x94 RIGHT DOUBLE QUOTATION MARK
+    Some('\u{2022}'), // 0x95 BULLET
+    Some('\u{2013}'), // 0x96 EN DASH
+    Some('\u{2014}'), // 0x97 EM DASH
+    Some('\u{02DC}'), // 0x98 SMALL TILDE
+    Some('\u{2122}'), // 0x99 TRADE MARK SIGN
+    Some('\u{0161}'), // 0x9A LATIN SMALL LETTER S WITH CARON
+    Some('\u{203A}'), // 0x9B SINGLE RIGHT-POINTING ANGLE QUOTATION MARK
+    Some('\u{0153}'), // 0x9C LATIN SMALL LIGATURE OE
+    None,
+    Some('\u{017E}'), // 0x9E LATIN SMALL LETTER Z WITH CARON
+    Some('\u{0178}'), // 0x9F LATIN CAPITAL LETTER Y WITH DIAERESIS
+];
+
+fn is_consumed_in_attribute(return_state: &State) -> bool {
+    matches!(
+        return_state,
+        State::AttributeValueDoubleQuoted
+            | State::AttributeValueSingleQuoted
+            | State::AttributeValueUnquoted
+    )
 }
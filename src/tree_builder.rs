@@ -0,0 +1,3245 @@
+//! Tree construction: turning tokens into a `Document`.
+//!
+//! https://html.spec.whatwg.org/multipage/parsing.html#tree-construction
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use crate::dom::{
+    self, Document, Element, Namespace, NodeData, NodeId, QuirksMode, ShadowRootMode,
+};
+use crate::local_name::{self, LocalName};
+use crate::parse_error::{ParseError, PositionedError, Span};
+use crate::tokenizer::{
+    self, Doctype, EndTag, StartTag, State, StatePolicy, Token, Tokenizer, TokenizerOptions,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InsertionMode {
+    Initial,
+    BeforeHtml,
+    BeforeHead,
+    InHead,
+    InHeadNoscript,
+    AfterHead,
+    InBody,
+    Text,
+    InTable,
+    InTableText,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
+    InSelect,
+    InSelectInTable,
+    InTemplate,
+    AfterBody,
+    InFrameset,
+    AfterFrameset,
+    AfterAfterBody,
+    AfterAfterFrameset,
+}
+
+/// Parses `html` into a document.
+pub fn parse_document(html: &str) -> Document {
+    parse_document_with_errors(html).0
+}
+
+/// Parses `html` into a document, also returning the parse errors found
+/// along the way.
+///
+/// Only the errors the HTML Standard gives a code are reported: those of
+/// the tokenizer, and a trailing solidus on a non-void HTML element. The
+/// errors tree construction otherwise recovers from, like misnested or
+/// unexpected end tags, have no code and are not reported.
+pub fn parse_document_with_errors(html: &str) -> (Document, Vec<PositionedError>) {
+    let tokenizer = Tokenizer::borrowing(
+        html,
+        &TokenizerOptions {
+            state_policy: StatePolicy::Manual,
+            ..TokenizerOptions::default()
+        },
+    );
+
+    let mut tree_builder = TreeBuilder::new(tokenizer);
+    tree_builder.run();
+    tree_builder.finish()
+}
+
+/// The kinds of "has an element in scope" checks, which differ in the
+/// elements that end the search.
+#[derive(Clone, Copy)]
+enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+    Select,
+}
+
+impl Scope {
+    fn is_boundary(self, element: &Element) -> bool {
+        let name = element.local_name();
+
+        // The MathML and SVG elements that bound the other scopes are the
+        // special ones.
+        if element.namespace() != Namespace::Html {
+            return match self {
+                Scope::Table => false,
+                Scope::Select => true,
+                _ => is_special(element),
+            };
+        }
+
+        let default = matches!(
+            *name,
+            local_name::applet
+                | local_name::caption
+                | local_name::html
+                | local_name::table
+                | local_name::td
+                | local_name::th
+                | local_name::marquee
+                | local_name::object
+                | local_name::template
+        );
+
+        match self {
+            Scope::Default => default,
+            Scope::ListItem => default || matches!(*name, local_name::ol | local_name::ul),
+            Scope::Button => default || *name == local_name::button,
+            Scope::Table => matches!(
+                *name,
+                local_name::html | local_name::table | local_name::template
+            ),
+            Scope::Select => !matches!(*name, local_name::optgroup | local_name::option),
+        }
+    }
+}
+
+/// An entry in the list of active formatting elements.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#list-of-active-formatting-elements
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum FormattingEntry {
+    /// Set at `applet`, `marquee`, `object`, `template`, table cells and
+    /// captions, so formatting elements don't leak into or out of them.
+    Marker,
+    Element(NodeId),
+}
+
+/// The stack of open elements.
+///
+/// It also counts the open HTML `p` and `button` elements, so that the
+/// checks for them in scope, which most start tags in the body make, don't
+/// walk the whole stack when there are none, and keeps a set of its nodes
+/// for the checks whether active formatting elements are still open.
+/// Otherwise deeply nested elements would take quadratic time to parse.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#stack-of-open-elements
+#[derive(Default)]
+struct OpenElements {
+    nodes: Vec<NodeId>,
+    open: HashSet<NodeId>,
+    /// For each node, the index of its name in `COUNTED_NAMES`, if any.
+    counted: Vec<Option<usize>>,
+    counts: [usize; COUNTED_NAMES.len()],
+}
+
+const COUNTED_NAMES: [LocalName; 2] = [local_name::p, local_name::button];
+
+impl OpenElements {
+    fn push(&mut self, node: NodeId, document: &Document) {
+        let index = self.nodes.len();
+        self.insert(index, node, document);
+    }
+
+    fn insert(&mut self, index: usize, node: NodeId, document: &Document) {
+        let counted = counted_name(node, document);
+        if let Some(name) = counted {
+            self.counts[name] += 1;
+        }
+        self.nodes.insert(index, node);
+        self.open.insert(node);
+        self.counted.insert(index, counted);
+    }
+
+    /// Replaces the node at `index` with `node`, which has the same name.
+    fn replace(&mut self, index: usize, node: NodeId) {
+        self.open.remove(&self.nodes[index]);
+        self.open.insert(node);
+        self.nodes[index] = node;
+    }
+
+    fn pop(&mut self) -> Option<NodeId> {
+        let node = self.nodes.pop()?;
+        self.open.remove(&node);
+        let counted = self.counted.pop().flatten();
+        self.uncount(counted);
+        Some(node)
+    }
+
+    fn remove(&mut self, index: usize) -> NodeId {
+        let counted = self.counted.remove(index);
+        self.uncount(counted);
+        let node = self.nodes.remove(index);
+        self.open.remove(&node);
+        node
+    }
+
+    fn truncate(&mut self, len: usize) {
+        while self.nodes.len() > len {
+            self.pop();
+        }
+    }
+
+    fn is_open(&self, node: NodeId) -> bool {
+        self.open.contains(&node)
+    }
+
+    fn remove_node(&mut self, node: NodeId) {
+        if let Some(index) = self.index(node) {
+            self.remove(index);
+        }
+    }
+
+    fn index(&self, node: NodeId) -> Option<usize> {
+        if !self.is_open(node) {
+            return None;
+        }
+        self.nodes.iter().rposition(|&open| open == node)
+    }
+
+    /// The number of open HTML elements called `name`, if it is one of the
+    /// names counted.
+    fn count(&self, name: &LocalName) -> Option<usize> {
+        let index = COUNTED_NAMES.iter().position(|counted| counted == name)?;
+        Some(self.counts[index])
+    }
+
+    fn uncount(&mut self, counted: Option<usize>) {
+        if let Some(name) = counted {
+            self.counts[name] -= 1;
+        }
+    }
+}
+
+impl std::ops::Deref for OpenElements {
+    type Target = [NodeId];
+
+    fn deref(&self) -> &[NodeId] {
+        &self.nodes
+    }
+}
+
+fn counted_name(node: NodeId, document: &Document) -> Option<usize> {
+    let element = document.node(node).as_element()?;
+    if element.namespace() != Namespace::Html {
+        return None;
+    }
+    COUNTED_NAMES
+        .iter()
+        .position(|name| name == element.local_name())
+}
+
+/// Where to insert a node: as the last child of a parent, or just before
+/// a sibling when foster parenting.
+#[derive(Clone, Copy)]
+enum InsertionPoint {
+    LastChild(NodeId),
+    Before(NodeId),
+}
+
+/// Builds a `Document` from the tokens of a `Tokenizer`, switching the
+/// tokenizer's state where the tree construction rules say so.
+struct TreeBuilder<'a> {
+    tokenizer: Tokenizer<'a>,
+    document: Document,
+    mode: InsertionMode,
+    /// The insertion mode to return to after the `Text` mode.
+    original_mode: InsertionMode,
+    open_elements: OpenElements,
+    active_formatting_elements: Vec<FormattingEntry>,
+    head_element: Option<NodeId>,
+    form_element: Option<NodeId>,
+    frameset_ok: bool,
+    /// Set while content misplaced in a table is processed, so that it is
+    /// inserted before the table instead.
+    foster_parenting: bool,
+    /// Text seen in the `InTableText` mode, inserted or foster parented
+    /// together once it ends.
+    pending_table_text: Vec<(String, Span)>,
+    /// The stack of template insertion modes, with one entry for each open
+    /// `template` element.
+    template_modes: Vec<InsertionMode>,
+    /// Set after a `pre`, `listing` or `textarea` start tag, whose first
+    /// newline is dropped.
+    ignore_next_line_feed: bool,
+    /// Whether the self-closing flag of the start tag being processed has
+    /// been acknowledged.
+    self_closing_acknowledged: bool,
+}
+
+impl<'a> TreeBuilder<'a> {
+    fn new(tokenizer: Tokenizer<'a>) -> TreeBuilder<'a> {
+        TreeBuilder {
+            tokenizer,
+            document: Document::new(),
+            mode: InsertionMode::Initial,
+            original_mode: InsertionMode::Initial,
+            open_elements: OpenElements::default(),
+            active_formatting_elements: Vec::new(),
+            head_element: None,
+            form_element: None,
+            frameset_ok: true,
+            foster_parenting: false,
+            pending_table_text: Vec::new(),
+            template_modes: Vec::new(),
+            ignore_next_line_feed: false,
+            self_closing_acknowledged: false,
+        }
+    }
+
+    fn run(&mut self) {
+        while let Some(mut token) = self.tokenizer.next() {
+            if self.ignore_next_line_feed {
+                self.ignore_next_line_feed = false;
+                if let Token::Text(text, _) = &mut token {
+                    if text.starts_with('\n') {
+                        match text {
+                            Cow::Borrowed(borrowed) => *borrowed = &borrowed[1..],
+                            Cow::Owned(owned) => {
+                                owned.remove(0);
+                            }
+                        }
+                        if text.is_empty() {
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            let self_closing = matches!(&token, Token::StartTag(tag) if tag.self_closing);
+            self.self_closing_acknowledged = false;
+
+            self.process_token(token);
+
+            if self_closing && !self.self_closing_acknowledged {
+                self.tokenizer
+                    .parse_error(ParseError::NonVoidHtmlElementStartTagWithTrailingSolidus);
+            }
+
+            // CDATA sections are only recognized in foreign content.
+            let in_foreign_content = self
+                .open_elements
+                .last()
+                .is_some_and(|&node| self.html_name(node).is_none());
+            self.tokenizer.set_in_foreign_content(in_foreign_content);
+        }
+    }
+
+    fn finish(mut self) -> (Document, Vec<PositionedError>) {
+        (self.document, self.tokenizer.take_errors())
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#tree-construction-dispatcher
+    fn process_token(&mut self, token: Token<'_>) {
+        if self.is_foreign(&token) {
+            self.in_foreign_content(token);
+        } else {
+            self.process_using(self.mode, token);
+        }
+    }
+
+    /// Whether `token` is processed with the rules for foreign content, as
+    /// it is inside SVG or MathML and not at an integration point that
+    /// takes it as HTML.
+    fn is_foreign(&self, token: &Token<'_>) -> bool {
+        let Some(&node) = self.open_elements.last() else {
+            return false;
+        };
+        let element = self.element(node);
+
+        match (element.namespace(), element.local_name(), token) {
+            (Namespace::Html, ..) => false,
+            (_, _, Token::EndOfFile(_)) => false,
+            (_, _, Token::Text(..)) => {
+                !is_mathml_text_integration_point(element) && !is_html_integration_point(element)
+            }
+            (Namespace::MathMl, &local_name::annotation_xml, Token::StartTag(tag))
+                if tag.tag_name == local_name::svg =>
+            {
+                false
+            }
+            (_, _, Token::StartTag(tag)) => {
+                (!is_mathml_text_integration_point(element)
+                    || matches!(tag.tag_name, local_name::mglyph | local_name::malignmark))
+                    && !is_html_integration_point(element)
+            }
+            _ => true,
+        }
+    }
+
+    /// Processes `token` following the rules for `mode`, which need not be
+    /// the current insertion mode.
+    fn process_using(&mut self, mode: InsertionMode, token: Token<'_>) {
+        match mode {
+            InsertionMode::Initial => self.initial(token),
+            InsertionMode::BeforeHtml => self.before_html(token),
+            InsertionMode::BeforeHead => self.before_head(token),
+            InsertionMode::InHead => self.in_head(token),
+            InsertionMode::InHeadNoscript => self.in_head_noscript(token),
+            InsertionMode::AfterHead => self.after_head(token),
+            InsertionMode::InBody => self.in_body(token),
+            InsertionMode::Text => self.text(token),
+            InsertionMode::InTable => self.in_table(token),
+            InsertionMode::InTableText => self.in_table_text(token),
+            InsertionMode::InCaption => self.in_caption(token),
+            InsertionMode::InColumnGroup => self.in_column_group(token),
+            InsertionMode::InTableBody => self.in_table_body(token),
+            InsertionMode::InRow => self.in_row(token),
+            InsertionMode::InCell => self.in_cell(token),
+            InsertionMode::InSelect => self.in_select(token),
+            InsertionMode::InSelectInTable => self.in_select_in_table(token),
+            InsertionMode::AfterBody => self.after_body(token),
+            InsertionMode::InFrameset => self.in_frameset(token),
+            InsertionMode::AfterFrameset => self.after_frameset(token),
+            InsertionMode::AfterAfterBody => self.after_after_body(token),
+            InsertionMode::AfterAfterFrameset => self.after_after_frameset(token),
+            InsertionMode::InTemplate => self.in_template(token),
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+    fn initial(&mut self, token: Token<'_>) {
+        match token {
+            Token::Text(ref text, _) if is_whitespace_run(text) => {}
+            Token::Comment(text, span) => self.insert_comment_in(self.document.root(), &text, span),
+            Token::Doctype(doctype) => {
+                self.document.set_quirks_mode(quirks_mode(&doctype));
+
+                let node = self.document.create_node(
+                    NodeData::Doctype {
+                        name: doctype.name.unwrap_or_default(),
+                        public_id: doctype.public_id.unwrap_or_default(),
+                        system_id: doctype.system_id.unwrap_or_default(),
+                    },
+                    doctype.span,
+                );
+                self.document.append(self.document.root(), node);
+                self.mode = InsertionMode::BeforeHtml;
+            }
+            _ => {
+                self.document.set_quirks_mode(QuirksMode::Quirks);
+                self.mode = InsertionMode::BeforeHtml;
+                self.process_token(token);
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#the-before-html-insertion-mode
+    fn before_html(&mut self, token: Token<'_>) {
+        match token {
+            Token::Doctype(_) => {}
+            Token::Comment(text, span) => self.insert_comment_in(self.document.root(), &text, span),
+            Token::Text(ref text, _) if is_whitespace_run(text) => {}
+            Token::StartTag(tag) if tag.tag_name == local_name::html => {
+                let html = self.create_element(tag, Namespace::Html);
+                self.document.append(self.document.root(), html);
+                self.open_elements.push(html, &self.document);
+                self.mode = InsertionMode::BeforeHead;
+            }
+            Token::EndTag(ref tag)
+                if !matches!(
+                    tag.tag_name,
+                    local_name::head | local_name::body | local_name::html | local_name::br
+                ) => {}
+            _ => {
+                let tag = synthesized_tag(local_name::html, Span::empty(token.span().start));
+                let html = self.create_element(tag, Namespace::Html);
+                self.document.append(self.document.root(), html);
+                self.open_elements.push(html, &self.document);
+                self.mode = InsertionMode::BeforeHead;
+                self.process_token(token);
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#the-before-head-insertion-mode
+    fn before_head(&mut self, token: Token<'_>) {
+        match token {
+            Token::Text(ref text, _) if is_whitespace_run(text) => {}
+            Token::Comment(text, span) => self.insert_comment(&text, span),
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.tag_name == local_name::html => self.in_body(token),
+            Token::StartTag(tag) if tag.tag_name == local_name::head => {
+                self.head_element = Some(self.insert_element(tag));
+                self.mode = InsertionMode::InHead;
+            }
+            Token::EndTag(ref tag)
+                if !matches!(
+                    tag.tag_name,
+                    local_name::head | local_name::body | local_name::html | local_name::br
+                ) => {}
+            _ => {
+                let tag = synthesized_tag(local_name::head, Span::empty(token.span().start));
+                self.head_element = Some(self.insert_element(tag));
+                self.mode = InsertionMode::InHead;
+                self.process_token(token);
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inhead
+    fn in_head(&mut self, token: Token<'_>) {
+        match token {
+            Token::Text(text, span) if is_whitespace_run(&text) => self.insert_text(&text, span),
+            Token::Comment(text, span) => self.insert_comment(&text, span),
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.tag_name == local_name::html => self.in_body(token),
+            Token::StartTag(tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::base
+                        | local_name::basefont
+                        | local_name::bgsound
+                        | local_name::link
+                        | local_name::meta
+                ) =>
+            {
+                self.insert_element(tag);
+                self.open_elements.pop();
+                self.self_closing_acknowledged = true;
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::title => {
+                self.parse_text_element(tag, State::Rcdata)
+            }
+            Token::StartTag(tag)
+                if matches!(tag.tag_name, local_name::noframes | local_name::style) =>
+            {
+                self.parse_text_element(tag, State::Rawtext)
+            }
+            // Scripting is disabled, so the contents of `noscript` are
+            // parsed as markup.
+            Token::StartTag(tag) if tag.tag_name == local_name::noscript => {
+                self.insert_element(tag);
+                self.mode = InsertionMode::InHeadNoscript;
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::script => {
+                self.parse_text_element(tag, State::ScriptData)
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::head => {
+                self.open_elements.pop();
+                self.mode = InsertionMode::AfterHead;
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::template => {
+                self.active_formatting_elements
+                    .push(FormattingEntry::Marker);
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InTemplate;
+                self.template_modes.push(InsertionMode::InTemplate);
+                self.insert_template(tag);
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::template => {
+                if !self.has_open_element(local_name::template) {
+                    return;
+                }
+                self.generate_all_implied_end_tags_thoroughly();
+                self.pop_until(&[local_name::template]);
+                self.clear_active_formatting_elements_to_last_marker();
+                self.template_modes.pop();
+                self.reset_insertion_mode();
+            }
+            Token::StartTag(ref tag) if tag.tag_name == local_name::head => {}
+            Token::EndTag(ref tag)
+                if !matches!(
+                    tag.tag_name,
+                    local_name::body | local_name::html | local_name::br
+                ) => {}
+            _ => {
+                self.open_elements.pop();
+                self.mode = InsertionMode::AfterHead;
+                self.process_token(token);
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inheadnoscript
+    fn in_head_noscript(&mut self, token: Token<'_>) {
+        match token {
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.tag_name == local_name::html => self.in_body(token),
+            Token::EndTag(tag) if tag.tag_name == local_name::noscript => {
+                self.open_elements.pop();
+                self.mode = InsertionMode::InHead;
+            }
+            Token::Text(ref text, _) if is_whitespace_run(text) => self.in_head(token),
+            Token::Comment(..) => self.in_head(token),
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::basefont
+                        | local_name::bgsound
+                        | local_name::link
+                        | local_name::meta
+                        | local_name::noframes
+                        | local_name::style
+                ) =>
+            {
+                self.in_head(token)
+            }
+            Token::StartTag(ref tag)
+                if matches!(tag.tag_name, local_name::head | local_name::noscript) => {}
+            Token::EndTag(ref tag) if tag.tag_name != local_name::br => {}
+            _ => {
+                self.open_elements.pop();
+                self.mode = InsertionMode::InHead;
+                self.process_token(token);
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#the-after-head-insertion-mode
+    fn after_head(&mut self, token: Token<'_>) {
+        match token {
+            Token::Text(text, span) if is_whitespace_run(&text) => self.insert_text(&text, span),
+            Token::Comment(text, span) => self.insert_comment(&text, span),
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.tag_name == local_name::html => self.in_body(token),
+            Token::StartTag(tag) if tag.tag_name == local_name::body => {
+                self.insert_element(tag);
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InBody;
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::frameset => {
+                self.insert_element(tag);
+                self.mode = InsertionMode::InFrameset;
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::base
+                        | local_name::basefont
+                        | local_name::bgsound
+                        | local_name::link
+                        | local_name::meta
+                        | local_name::noframes
+                        | local_name::script
+                        | local_name::style
+                        | local_name::template
+                        | local_name::title
+                ) =>
+            {
+                let head = self
+                    .head_element
+                    .expect("the head element has been inserted");
+                self.open_elements.push(head, &self.document);
+                self.in_head(token);
+                self.open_elements.remove_node(head);
+            }
+            Token::EndTag(ref tag) if tag.tag_name == local_name::template => self.in_head(token),
+            Token::StartTag(ref tag) if tag.tag_name == local_name::head => {}
+            Token::EndTag(ref tag)
+                if !matches!(
+                    tag.tag_name,
+                    local_name::body | local_name::html | local_name::br
+                ) => {}
+            _ => {
+                let tag = synthesized_tag(local_name::body, Span::empty(token.span().start));
+                self.insert_element(tag);
+                self.mode = InsertionMode::InBody;
+                self.process_token(token);
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inbody
+    fn in_body(&mut self, token: Token<'_>) {
+        match token {
+            Token::Character(..) => unreachable!("the tokenizer coalesces text into runs"),
+            Token::Text(text, _) if text == "\0" => {}
+            Token::Text(text, span) if is_whitespace_run(&text) => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_text(&text, span);
+            }
+            Token::Text(text, span) => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_text(&text, span);
+                self.frameset_ok = false;
+            }
+            Token::Comment(text, span) => self.insert_comment(&text, span),
+            Token::Doctype(_) => {}
+            Token::StartTag(tag) if tag.tag_name == local_name::html => {
+                if self.has_open_element(local_name::template) {
+                    return;
+                }
+                let html = self.open_elements[0];
+                self.add_missing_attributes(html, tag);
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::base
+                        | local_name::basefont
+                        | local_name::bgsound
+                        | local_name::link
+                        | local_name::meta
+                        | local_name::noframes
+                        | local_name::script
+                        | local_name::style
+                        | local_name::template
+                        | local_name::title
+                ) =>
+            {
+                self.in_head(token)
+            }
+            Token::EndTag(ref tag) if tag.tag_name == local_name::template => self.in_head(token),
+            Token::StartTag(tag) if tag.tag_name == local_name::body => {
+                if self.open_elements.len() == 1
+                    || !self.is_element_named(self.open_elements[1], &local_name::body)
+                    || self.has_open_element(local_name::template)
+                {
+                    return;
+                }
+                self.frameset_ok = false;
+                let body = self.open_elements[1];
+                self.add_missing_attributes(body, tag);
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::frameset => {
+                if self.open_elements.len() == 1
+                    || !self.is_element_named(self.open_elements[1], &local_name::body)
+                    || !self.frameset_ok
+                {
+                    return;
+                }
+                self.document.detach(self.open_elements[1]);
+                self.open_elements.truncate(1);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InFrameset;
+            }
+            Token::EndOfFile(_) => {
+                if !self.template_modes.is_empty() {
+                    self.in_template(token);
+                }
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::body => {
+                if self.has_element_in_scope(&[local_name::body], Scope::Default) {
+                    self.mode = InsertionMode::AfterBody;
+                }
+            }
+            Token::EndTag(ref tag) if tag.tag_name == local_name::html => {
+                if self.has_element_in_scope(&[local_name::body], Scope::Default) {
+                    self.mode = InsertionMode::AfterBody;
+                    self.process_token(token);
+                }
+            }
+            Token::StartTag(tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::address
+                        | local_name::article
+                        | local_name::aside
+                        | local_name::blockquote
+                        | local_name::center
+                        | local_name::details
+                        | local_name::dialog
+                        | local_name::dir
+                        | local_name::div
+                        | local_name::dl
+                        | local_name::fieldset
+                        | local_name::figcaption
+                        | local_name::figure
+                        | local_name::footer
+                        | local_name::header
+                        | local_name::hgroup
+                        | local_name::main
+                        | local_name::menu
+                        | local_name::nav
+                        | local_name::ol
+                        | local_name::p
+                        | local_name::search
+                        | local_name::section
+                        | local_name::summary
+                        | local_name::ul
+                ) =>
+            {
+                self.close_p_element_in_button_scope();
+                self.insert_element(tag);
+            }
+            Token::StartTag(tag) if is_heading(&tag.tag_name) => {
+                self.close_p_element_in_button_scope();
+                if self.current_node_name().is_some_and(is_heading) {
+                    self.open_elements.pop();
+                }
+                self.insert_element(tag);
+            }
+            Token::StartTag(tag)
+                if matches!(tag.tag_name, local_name::pre | local_name::listing) =>
+            {
+                self.close_p_element_in_button_scope();
+                self.insert_element(tag);
+                self.ignore_next_line_feed = true;
+                self.frameset_ok = false;
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::form => {
+                let has_template = self.has_open_element(local_name::template);
+                if self.form_element.is_some() && !has_template {
+                    return;
+                }
+                self.close_p_element_in_button_scope();
+                let form = self.insert_element(tag);
+                if !has_template {
+                    self.form_element = Some(form);
+                }
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::li => {
+                self.frameset_ok = false;
+                self.close_list_item(&[local_name::li]);
+                self.close_p_element_in_button_scope();
+                self.insert_element(tag);
+            }
+            Token::StartTag(tag) if matches!(tag.tag_name, local_name::dd | local_name::dt) => {
+                self.frameset_ok = false;
+                self.close_list_item(&[local_name::dd, local_name::dt]);
+                self.close_p_element_in_button_scope();
+                self.insert_element(tag);
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::plaintext => {
+                self.close_p_element_in_button_scope();
+                self.insert_element(tag);
+                self.tokenizer.set_state(State::Plaintext);
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::button => {
+                if self.has_element_in_scope(&[local_name::button], Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&[local_name::button]);
+                }
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.frameset_ok = false;
+            }
+            Token::EndTag(tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::address
+                        | local_name::article
+                        | local_name::aside
+                        | local_name::blockquote
+                        | local_name::button
+                        | local_name::center
+                        | local_name::details
+                        | local_name::dialog
+                        | local_name::dir
+                        | local_name::div
+                        | local_name::dl
+                        | local_name::fieldset
+                        | local_name::figcaption
+                        | local_name::figure
+                        | local_name::footer
+                        | local_name::header
+                        | local_name::hgroup
+                        | local_name::listing
+                        | local_name::main
+                        | local_name::menu
+                        | local_name::nav
+                        | local_name::ol
+                        | local_name::pre
+                        | local_name::search
+                        | local_name::section
+                        | local_name::summary
+                        | local_name::ul
+                ) =>
+            {
+                let names = [tag.tag_name];
+                if self.has_element_in_scope(&names, Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&names);
+                }
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::form => {
+                if self.has_open_element(local_name::template) {
+                    if self.has_element_in_scope(&[local_name::form], Scope::Default) {
+                        self.generate_implied_end_tags(None);
+                        self.pop_until(&[local_name::form]);
+                    }
+                    return;
+                }
+
+                let Some(form) = self.form_element.take() else {
+                    return;
+                };
+                if !self.has_node_in_scope(form) {
+                    return;
+                }
+                self.generate_implied_end_tags(None);
+                self.open_elements.remove_node(form);
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::p => {
+                if !self.has_element_in_scope(&[local_name::p], Scope::Button) {
+                    self.insert_element(synthesized_tag(
+                        local_name::p,
+                        Span::empty(tag.span.start),
+                    ));
+                }
+                self.close_p_element();
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::li => {
+                if self.has_element_in_scope(&[local_name::li], Scope::ListItem) {
+                    self.generate_implied_end_tags(Some(&local_name::li));
+                    self.pop_until(&[local_name::li]);
+                }
+            }
+            Token::EndTag(tag) if matches!(tag.tag_name, local_name::dd | local_name::dt) => {
+                let names = [tag.tag_name];
+                if self.has_element_in_scope(&names, Scope::Default) {
+                    self.generate_implied_end_tags(Some(&names[0]));
+                    self.pop_until(&names);
+                }
+            }
+            Token::EndTag(tag) if is_heading(&tag.tag_name) => {
+                const HEADINGS: [LocalName; 6] = [
+                    local_name::h1,
+                    local_name::h2,
+                    local_name::h3,
+                    local_name::h4,
+                    local_name::h5,
+                    local_name::h6,
+                ];
+                if self.has_element_in_scope(&HEADINGS, Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&HEADINGS);
+                }
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::a => {
+                let open_a = self
+                    .active_formatting_elements_after_last_marker()
+                    .find(|&element| self.is_element_named(element, &local_name::a));
+                if let Some(open_a) = open_a {
+                    self.adoption_agency(&local_name::a);
+                    self.remove_from_active_formatting_elements(open_a);
+                    self.open_elements.remove_node(open_a);
+                }
+                self.reconstruct_active_formatting_elements();
+                let element = self.insert_element(tag);
+                self.push_active_formatting_element(element);
+            }
+            Token::StartTag(tag) if is_formatting(&tag.tag_name) => {
+                self.reconstruct_active_formatting_elements();
+                let element = self.insert_element(tag);
+                self.push_active_formatting_element(element);
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::nobr => {
+                self.reconstruct_active_formatting_elements();
+                if self.has_element_in_scope(&[local_name::nobr], Scope::Default) {
+                    self.adoption_agency(&local_name::nobr);
+                    self.reconstruct_active_formatting_elements();
+                }
+                let element = self.insert_element(tag);
+                self.push_active_formatting_element(element);
+            }
+            Token::EndTag(tag)
+                if matches!(tag.tag_name, local_name::a | local_name::nobr)
+                    || is_formatting(&tag.tag_name) =>
+            {
+                if !self.adoption_agency(&tag.tag_name) {
+                    self.any_other_end_tag(tag.tag_name);
+                }
+            }
+            Token::StartTag(tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::applet | local_name::marquee | local_name::object
+                ) =>
+            {
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.active_formatting_elements
+                    .push(FormattingEntry::Marker);
+                self.frameset_ok = false;
+            }
+            Token::EndTag(tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::applet | local_name::marquee | local_name::object
+                ) =>
+            {
+                let names = [tag.tag_name];
+                if self.has_element_in_scope(&names, Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&names);
+                    self.clear_active_formatting_elements_to_last_marker();
+                }
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::table => {
+                if self.document.quirks_mode() != QuirksMode::Quirks {
+                    self.close_p_element_in_button_scope();
+                }
+                self.insert_element(tag);
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InTable;
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::br => {
+                self.in_body(Token::StartTag(synthesized_tag(local_name::br, tag.span)))
+            }
+            Token::StartTag(tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::area
+                        | local_name::br
+                        | local_name::embed
+                        | local_name::img
+                        | local_name::keygen
+                        | local_name::wbr
+                ) =>
+            {
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.open_elements.pop();
+                self.self_closing_acknowledged = true;
+                self.frameset_ok = false;
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::input => {
+                let hidden = tag.attributes.iter().any(|attribute| {
+                    attribute.name == local_name::r#type
+                        && attribute.value.eq_ignore_ascii_case("hidden")
+                });
+
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.open_elements.pop();
+                self.self_closing_acknowledged = true;
+                if !hidden {
+                    self.frameset_ok = false;
+                }
+            }
+            Token::StartTag(tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::param | local_name::source | local_name::track
+                ) =>
+            {
+                self.insert_element(tag);
+                self.open_elements.pop();
+                self.self_closing_acknowledged = true;
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::hr => {
+                self.close_p_element_in_button_scope();
+                self.insert_element(tag);
+                self.open_elements.pop();
+                self.self_closing_acknowledged = true;
+                self.frameset_ok = false;
+            }
+            Token::StartTag(mut tag) if tag.tag_name == local_name::image => {
+                tag.tag_name = local_name::img;
+                self.process_token(Token::StartTag(tag));
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::textarea => {
+                self.ignore_next_line_feed = true;
+                self.frameset_ok = false;
+                self.parse_text_element(tag, State::Rcdata);
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::xmp => {
+                self.close_p_element_in_button_scope();
+                self.reconstruct_active_formatting_elements();
+                self.frameset_ok = false;
+                self.parse_text_element(tag, State::Rawtext);
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::iframe => {
+                self.frameset_ok = false;
+                self.parse_text_element(tag, State::Rawtext);
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::noembed => {
+                self.parse_text_element(tag, State::Rawtext)
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::select => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.frameset_ok = false;
+                self.mode = match self.mode {
+                    InsertionMode::InTable
+                    | InsertionMode::InCaption
+                    | InsertionMode::InTableBody
+                    | InsertionMode::InRow
+                    | InsertionMode::InCell => InsertionMode::InSelectInTable,
+                    _ => InsertionMode::InSelect,
+                };
+            }
+            Token::StartTag(tag)
+                if matches!(tag.tag_name, local_name::optgroup | local_name::option) =>
+            {
+                if self.current_node_is(local_name::option) {
+                    self.open_elements.pop();
+                }
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+            }
+            Token::StartTag(tag) if matches!(tag.tag_name, local_name::rb | local_name::rtc) => {
+                if self.has_element_in_scope(&[local_name::ruby], Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                }
+                self.insert_element(tag);
+            }
+            Token::StartTag(tag) if matches!(tag.tag_name, local_name::rp | local_name::rt) => {
+                if self.has_element_in_scope(&[local_name::ruby], Scope::Default) {
+                    self.generate_implied_end_tags(Some(&local_name::rtc));
+                }
+                self.insert_element(tag);
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::caption
+                        | local_name::col
+                        | local_name::colgroup
+                        | local_name::frame
+                        | local_name::head
+                        | local_name::tbody
+                        | local_name::td
+                        | local_name::tfoot
+                        | local_name::th
+                        | local_name::thead
+                        | local_name::tr
+                ) => {}
+            Token::StartTag(tag) if tag.tag_name == local_name::math => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_foreign_element(tag, Namespace::MathMl);
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::svg => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_foreign_element(tag, Namespace::Svg);
+            }
+            Token::StartTag(tag) => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+            }
+            Token::EndTag(tag) => self.any_other_end_tag(tag.tag_name),
+        }
+    }
+
+    /// The steps for "any other end tag" in the body.
+    fn any_other_end_tag(&mut self, name: LocalName) {
+        for index in (0..self.open_elements.len()).rev() {
+            let node = self.open_elements[index];
+
+            if self.is_element_named(node, &name) {
+                self.generate_implied_end_tags(Some(&name));
+                self.open_elements.truncate(index);
+                return;
+            }
+
+            if is_special(self.element(node)) {
+                return;
+            }
+        }
+    }
+
+    /// Closes the formatting element called `subject` for its end tag,
+    /// moving content that was misnested inside it so the tree is what
+    /// browsers build. Returns false if the end tag should be handled as
+    /// any other end tag instead.
+    ///
+    /// https://html.spec.whatwg.org/multipage/parsing.html#adoption-agency-algorithm
+    fn adoption_agency(&mut self, subject: &LocalName) -> bool {
+        let current_node = self.current_node();
+        if self.is_element_named(current_node, subject)
+            && self.formatting_index(current_node).is_none()
+        {
+            self.open_elements.pop();
+            return true;
+        }
+
+        for _ in 0..8 {
+            let Some(formatting_element) = self
+                .active_formatting_elements_after_last_marker()
+                .find(|&element| self.is_element_named(element, subject))
+            else {
+                return false;
+            };
+
+            let Some(formatting_index) = self.open_element_index(formatting_element) else {
+                self.remove_from_active_formatting_elements(formatting_element);
+                return true;
+            };
+            if !self.has_node_in_scope(formatting_element) {
+                return true;
+            }
+
+            let furthest_block = (formatting_index + 1..self.open_elements.len())
+                .find(|&index| is_special(self.element(self.open_elements[index])));
+            let Some(furthest_block_index) = furthest_block else {
+                self.open_elements.truncate(formatting_index);
+                self.remove_from_active_formatting_elements(formatting_element);
+                return true;
+            };
+            let furthest_block = self.open_elements[furthest_block_index];
+
+            let common_ancestor = self.open_elements[formatting_index - 1];
+            let mut bookmark = self
+                .formatting_index(formatting_element)
+                .expect("the formatting element is in the list");
+
+            // Walk up from the furthest block to the formatting element,
+            // replacing formatting elements on the way with new ones that
+            // wrap what is below them, and dropping the rest from the stack.
+            let mut node_index = furthest_block_index;
+            let mut last_node = furthest_block;
+            let mut inner_loop_counter = 0;
+            loop {
+                inner_loop_counter += 1;
+                node_index -= 1;
+                let node = self.open_elements[node_index];
+                if node == formatting_element {
+                    break;
+                }
+
+                if inner_loop_counter > 3 {
+                    if let Some(index) = self.formatting_index(node) {
+                        self.active_formatting_elements.remove(index);
+                        if index < bookmark {
+                            bookmark -= 1;
+                        }
+                    }
+                }
+
+                let Some(index) = self.formatting_index(node) else {
+                    self.open_elements.remove(node_index);
+                    continue;
+                };
+
+                let node = self.clone_element(node);
+                self.active_formatting_elements[index] = FormattingEntry::Element(node);
+                self.open_elements.replace(node_index, node);
+
+                if last_node == furthest_block {
+                    bookmark = index + 1;
+                }
+                self.document.append(node, last_node);
+                last_node = node;
+            }
+
+            self.insert_node(self.appropriate_place(Some(common_ancestor)), last_node);
+
+            let element = self.clone_element(formatting_element);
+            while let Some(child) = self.document.node(furthest_block).first_child() {
+                self.document.append(element, child);
+            }
+            self.document.append(furthest_block, element);
+
+            let index = self
+                .formatting_index(formatting_element)
+                .expect("the formatting element is in the list");
+            self.active_formatting_elements.remove(index);
+            if index < bookmark {
+                bookmark -= 1;
+            }
+            self.active_formatting_elements
+                .insert(bookmark, FormattingEntry::Element(element));
+
+            self.open_elements.remove_node(formatting_element);
+            let furthest_block_index = self
+                .open_element_index(furthest_block)
+                .expect("the furthest block is open");
+            self.open_elements
+                .insert(furthest_block_index + 1, element, &self.document);
+        }
+
+        true
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#reconstruct-the-active-formatting-elements
+    fn reconstruct_active_formatting_elements(&mut self) {
+        // Reopen every element after the last marker or open element.
+        let start = self
+            .active_formatting_elements
+            .iter()
+            .rposition(|&entry| match entry {
+                FormattingEntry::Marker => true,
+                FormattingEntry::Element(element) => self.open_elements.is_open(element),
+            })
+            .map_or(0, |index| index + 1);
+
+        for index in start..self.active_formatting_elements.len() {
+            let FormattingEntry::Element(element) = self.active_formatting_elements[index] else {
+                unreachable!("there are no markers after the last one");
+            };
+
+            let element = self.clone_element(element);
+            self.insert_node(self.appropriate_place(None), element);
+            self.open_elements.push(element, &self.document);
+            self.active_formatting_elements[index] = FormattingEntry::Element(element);
+        }
+    }
+
+    /// Adds `element` to the list of active formatting elements. If there
+    /// are already three with the same name and attributes after the last
+    /// marker, the earliest of them is removed ("Noah's Ark").
+    ///
+    /// https://html.spec.whatwg.org/multipage/parsing.html#push-onto-the-list-of-active-formatting-elements
+    fn push_active_formatting_element(&mut self, element: NodeId) {
+        let same: Vec<NodeId> = self
+            .active_formatting_elements_after_last_marker()
+            .filter(|&other| self.is_same_formatting_element(element, other))
+            .collect();
+
+        if let [_, _, .., earliest] = same[..] {
+            self.remove_from_active_formatting_elements(earliest);
+        }
+
+        self.active_formatting_elements
+            .push(FormattingEntry::Element(element));
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#clear-the-list-of-active-formatting-elements-up-to-the-last-marker
+    fn clear_active_formatting_elements_to_last_marker(&mut self) {
+        while let Some(entry) = self.active_formatting_elements.pop() {
+            if entry == FormattingEntry::Marker {
+                break;
+            }
+        }
+    }
+
+    /// The elements in the list of active formatting elements after the
+    /// last marker, the last one first.
+    fn active_formatting_elements_after_last_marker(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.active_formatting_elements
+            .iter()
+            .rev()
+            .map_while(|&entry| match entry {
+                FormattingEntry::Marker => None,
+                FormattingEntry::Element(element) => Some(element),
+            })
+    }
+
+    fn formatting_index(&self, element: NodeId) -> Option<usize> {
+        self.active_formatting_elements
+            .iter()
+            .position(|&entry| entry == FormattingEntry::Element(element))
+    }
+
+    fn remove_from_active_formatting_elements(&mut self, element: NodeId) {
+        if let Some(index) = self.formatting_index(element) {
+            self.active_formatting_elements.remove(index);
+        }
+    }
+
+    /// Whether `a` and `b` have the same name, namespace and attributes,
+    /// in any order.
+    fn is_same_formatting_element(&self, a: NodeId, b: NodeId) -> bool {
+        let a = self
+            .document
+            .element(a)
+            .expect("formatting elements are elements");
+        let b = self
+            .document
+            .element(b)
+            .expect("formatting elements are elements");
+
+        a.local_name() == b.local_name()
+            && a.namespace() == b.namespace()
+            && a.attributes().len() == b.attributes().len()
+            && a.attributes().iter().all(|attribute| {
+                b.attributes().get_ns(attribute.namespace, &attribute.name)
+                    == Some(attribute.value.as_str())
+            })
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incdata
+    fn text(&mut self, token: Token<'_>) {
+        match token {
+            Token::Text(text, span) => self.insert_text(&text, span),
+            Token::EndOfFile(_) => {
+                self.open_elements.pop();
+                self.mode = self.original_mode;
+                self.process_token(token);
+            }
+            Token::EndTag(_) => {
+                self.open_elements.pop();
+                self.mode = self.original_mode;
+            }
+            _ => {}
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intable
+    fn in_table(&mut self, token: Token<'_>) {
+        match token {
+            Token::Text(..)
+                if matches!(
+                    self.current_node_name(),
+                    Some(
+                        &(local_name::table
+                            | local_name::tbody
+                            | local_name::template
+                            | local_name::tfoot
+                            | local_name::thead
+                            | local_name::tr)
+                    )
+                ) =>
+            {
+                self.pending_table_text.clear();
+                self.original_mode = self.mode;
+                self.mode = InsertionMode::InTableText;
+                self.process_token(token);
+            }
+            Token::Comment(text, span) => self.insert_comment(&text, span),
+            Token::Doctype(_) => {}
+            Token::StartTag(tag) if tag.tag_name == local_name::caption => {
+                self.clear_stack_back_to(&TABLE_CONTEXT);
+                self.active_formatting_elements
+                    .push(FormattingEntry::Marker);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InCaption;
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::colgroup => {
+                self.clear_stack_back_to(&TABLE_CONTEXT);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InColumnGroup;
+            }
+            Token::StartTag(ref tag) if tag.tag_name == local_name::col => {
+                self.clear_stack_back_to(&TABLE_CONTEXT);
+                let colgroup = synthesized_tag(local_name::colgroup, Span::empty(tag.span.start));
+                self.insert_element(colgroup);
+                self.mode = InsertionMode::InColumnGroup;
+                self.process_token(token);
+            }
+            Token::StartTag(tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::tbody | local_name::tfoot | local_name::thead
+                ) =>
+            {
+                self.clear_stack_back_to(&TABLE_CONTEXT);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InTableBody;
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::td | local_name::th | local_name::tr
+                ) =>
+            {
+                self.clear_stack_back_to(&TABLE_CONTEXT);
+                let tbody = synthesized_tag(local_name::tbody, Span::empty(tag.span.start));
+                self.insert_element(tbody);
+                self.mode = InsertionMode::InTableBody;
+                self.process_token(token);
+            }
+            Token::StartTag(ref tag) if tag.tag_name == local_name::table => {
+                if self.has_element_in_scope(&[local_name::table], Scope::Table) {
+                    self.pop_until(&[local_name::table]);
+                    self.reset_insertion_mode();
+                    self.process_token(token);
+                }
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::table => {
+                if self.has_element_in_scope(&[local_name::table], Scope::Table) {
+                    self.pop_until(&[local_name::table]);
+                    self.reset_insertion_mode();
+                }
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::body
+                        | local_name::caption
+                        | local_name::col
+                        | local_name::colgroup
+                        | local_name::html
+                        | local_name::tbody
+                        | local_name::td
+                        | local_name::tfoot
+                        | local_name::th
+                        | local_name::thead
+                        | local_name::tr
+                ) => {}
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::script | local_name::style | local_name::template
+                ) =>
+            {
+                self.in_head(token)
+            }
+            Token::EndTag(ref tag) if tag.tag_name == local_name::template => self.in_head(token),
+            Token::StartTag(tag)
+                if tag.tag_name == local_name::input
+                    && tag
+                        .get_attr("type")
+                        .is_some_and(|value| value.eq_ignore_ascii_case("hidden")) =>
+            {
+                self.insert_element(tag);
+                self.open_elements.pop();
+                self.self_closing_acknowledged = true;
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::form => {
+                if self.form_element.is_some() || self.has_open_element(local_name::template) {
+                    return;
+                }
+                self.form_element = Some(self.insert_element(tag));
+                self.open_elements.pop();
+            }
+            Token::EndOfFile(_) => self.in_body(token),
+            _ => {
+                self.foster_parenting = true;
+                self.in_body(token);
+                self.foster_parenting = false;
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intabletext
+    fn in_table_text(&mut self, token: Token<'_>) {
+        match token {
+            Token::Text(text, _) if text == "\0" => {}
+            Token::Text(text, span) => self.pending_table_text.push((text.into_owned(), span)),
+            _ => {
+                let pending_table_text = std::mem::take(&mut self.pending_table_text);
+                let whitespace_only = pending_table_text
+                    .iter()
+                    .all(|(text, _)| text.chars().all(is_whitespace));
+
+                for (text, span) in pending_table_text {
+                    if whitespace_only {
+                        self.insert_text(&text, span);
+                    } else {
+                        // Foster parented, as anything else in a table.
+                        self.foster_parenting = true;
+                        self.in_body(Token::Text(Cow::Owned(text), span));
+                        self.foster_parenting = false;
+                    }
+                }
+
+                self.mode = self.original_mode;
+                self.process_token(token);
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incaption
+    fn in_caption(&mut self, token: Token<'_>) {
+        match token {
+            Token::EndTag(tag) if tag.tag_name == local_name::caption => {
+                self.close_caption();
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::caption
+                        | local_name::col
+                        | local_name::colgroup
+                        | local_name::tbody
+                        | local_name::td
+                        | local_name::tfoot
+                        | local_name::th
+                        | local_name::thead
+                        | local_name::tr
+                ) =>
+            {
+                if self.close_caption() {
+                    self.process_token(token);
+                }
+            }
+            Token::EndTag(ref tag) if tag.tag_name == local_name::table => {
+                if self.close_caption() {
+                    self.process_token(token);
+                }
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::body
+                        | local_name::col
+                        | local_name::colgroup
+                        | local_name::html
+                        | local_name::tbody
+                        | local_name::td
+                        | local_name::tfoot
+                        | local_name::th
+                        | local_name::thead
+                        | local_name::tr
+                ) => {}
+            _ => self.in_body(token),
+        }
+    }
+
+    /// Closes the open caption, if there is one in table scope, and
+    /// returns whether there was.
+    fn close_caption(&mut self) -> bool {
+        if !self.has_element_in_scope(&[local_name::caption], Scope::Table) {
+            return false;
+        }
+
+        self.generate_implied_end_tags(None);
+        self.pop_until(&[local_name::caption]);
+        self.clear_active_formatting_elements_to_last_marker();
+        self.mode = InsertionMode::InTable;
+        true
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incolgroup
+    fn in_column_group(&mut self, token: Token<'_>) {
+        match token {
+            Token::Text(text, span) if is_whitespace_run(&text) => self.insert_text(&text, span),
+            Token::Comment(text, span) => self.insert_comment(&text, span),
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.tag_name == local_name::html => self.in_body(token),
+            Token::StartTag(tag) if tag.tag_name == local_name::col => {
+                self.insert_element(tag);
+                self.open_elements.pop();
+                self.self_closing_acknowledged = true;
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::colgroup => {
+                if self.current_node_is(local_name::colgroup) {
+                    self.open_elements.pop();
+                    self.mode = InsertionMode::InTable;
+                }
+            }
+            Token::EndTag(ref tag) if tag.tag_name == local_name::col => {}
+            Token::StartTag(ref tag) if tag.tag_name == local_name::template => self.in_head(token),
+            Token::EndTag(ref tag) if tag.tag_name == local_name::template => self.in_head(token),
+            Token::EndOfFile(_) => self.in_body(token),
+            _ => {
+                if self.current_node_is(local_name::colgroup) {
+                    self.open_elements.pop();
+                    self.mode = InsertionMode::InTable;
+                    self.process_token(token);
+                }
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intbody
+    fn in_table_body(&mut self, token: Token<'_>) {
+        match token {
+            Token::StartTag(tag) if tag.tag_name == local_name::tr => {
+                self.clear_stack_back_to(&TABLE_BODY_CONTEXT);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InRow;
+            }
+            Token::StartTag(ref tag) if matches!(tag.tag_name, local_name::td | local_name::th) => {
+                self.clear_stack_back_to(&TABLE_BODY_CONTEXT);
+                let tr = synthesized_tag(local_name::tr, Span::empty(tag.span.start));
+                self.insert_element(tr);
+                self.mode = InsertionMode::InRow;
+                self.process_token(token);
+            }
+            Token::EndTag(tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::tbody | local_name::tfoot | local_name::thead
+                ) =>
+            {
+                if self.has_element_in_scope(std::slice::from_ref(&tag.tag_name), Scope::Table) {
+                    self.clear_stack_back_to(&TABLE_BODY_CONTEXT);
+                    self.open_elements.pop();
+                    self.mode = InsertionMode::InTable;
+                }
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::caption
+                        | local_name::col
+                        | local_name::colgroup
+                        | local_name::tbody
+                        | local_name::tfoot
+                        | local_name::thead
+                ) =>
+            {
+                if self.close_table_body() {
+                    self.process_token(token);
+                }
+            }
+            Token::EndTag(ref tag) if tag.tag_name == local_name::table => {
+                if self.close_table_body() {
+                    self.process_token(token);
+                }
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::body
+                        | local_name::caption
+                        | local_name::col
+                        | local_name::colgroup
+                        | local_name::html
+                        | local_name::td
+                        | local_name::th
+                        | local_name::tr
+                ) => {}
+            _ => self.in_table(token),
+        }
+    }
+
+    /// Closes the open `tbody`, `thead` or `tfoot`, if there is one in table
+    /// scope, and returns whether there was.
+    fn close_table_body(&mut self) -> bool {
+        const TABLE_BODIES: [LocalName; 3] =
+            [local_name::tbody, local_name::tfoot, local_name::thead];
+        if !self.has_element_in_scope(&TABLE_BODIES, Scope::Table) {
+            return false;
+        }
+
+        self.clear_stack_back_to(&TABLE_BODY_CONTEXT);
+        self.open_elements.pop();
+        self.mode = InsertionMode::InTable;
+        true
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intr
+    fn in_row(&mut self, token: Token<'_>) {
+        match token {
+            Token::StartTag(tag) if matches!(tag.tag_name, local_name::td | local_name::th) => {
+                self.clear_stack_back_to(&TABLE_ROW_CONTEXT);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InCell;
+                self.active_formatting_elements
+                    .push(FormattingEntry::Marker);
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::tr => {
+                self.close_row();
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::caption
+                        | local_name::col
+                        | local_name::colgroup
+                        | local_name::tbody
+                        | local_name::tfoot
+                        | local_name::thead
+                        | local_name::tr
+                ) =>
+            {
+                if self.close_row() {
+                    self.process_token(token);
+                }
+            }
+            Token::EndTag(ref tag) if tag.tag_name == local_name::table => {
+                if self.close_row() {
+                    self.process_token(token);
+                }
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::tbody | local_name::tfoot | local_name::thead
+                ) =>
+            {
+                if self.has_element_in_scope(std::slice::from_ref(&tag.tag_name), Scope::Table)
+                    && self.close_row()
+                {
+                    self.process_token(token);
+                }
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::body
+                        | local_name::caption
+                        | local_name::col
+                        | local_name::colgroup
+                        | local_name::html
+                        | local_name::td
+                        | local_name::th
+                ) => {}
+            _ => self.in_table(token),
+        }
+    }
+
+    /// Closes the open `tr`, if there is one in table scope, and returns
+    /// whether there was.
+    fn close_row(&mut self) -> bool {
+        if !self.has_element_in_scope(&[local_name::tr], Scope::Table) {
+            return false;
+        }
+
+        self.clear_stack_back_to(&TABLE_ROW_CONTEXT);
+        self.open_elements.pop();
+        self.mode = InsertionMode::InTableBody;
+        true
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intd
+    fn in_cell(&mut self, token: Token<'_>) {
+        match token {
+            Token::EndTag(tag) if matches!(tag.tag_name, local_name::td | local_name::th) => {
+                if self.has_element_in_scope(std::slice::from_ref(&tag.tag_name), Scope::Table) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&[tag.tag_name]);
+                    self.clear_active_formatting_elements_to_last_marker();
+                    self.mode = InsertionMode::InRow;
+                }
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::caption
+                        | local_name::col
+                        | local_name::colgroup
+                        | local_name::tbody
+                        | local_name::td
+                        | local_name::tfoot
+                        | local_name::th
+                        | local_name::thead
+                        | local_name::tr
+                ) =>
+            {
+                if self.has_element_in_scope(&[local_name::td, local_name::th], Scope::Table) {
+                    self.close_cell();
+                    self.process_token(token);
+                }
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::body
+                        | local_name::caption
+                        | local_name::col
+                        | local_name::colgroup
+                        | local_name::html
+                ) => {}
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::table
+                        | local_name::tbody
+                        | local_name::tfoot
+                        | local_name::thead
+                        | local_name::tr
+                ) =>
+            {
+                if self.has_element_in_scope(std::slice::from_ref(&tag.tag_name), Scope::Table) {
+                    self.close_cell();
+                    self.process_token(token);
+                }
+            }
+            _ => self.in_body(token),
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#close-the-cell
+    fn close_cell(&mut self) {
+        self.generate_implied_end_tags(None);
+        self.pop_until(&[local_name::td, local_name::th]);
+        self.clear_active_formatting_elements_to_last_marker();
+        self.mode = InsertionMode::InRow;
+    }
+
+    /// Pops elements until the current node is named like any of `names`,
+    /// for "clear the stack back to a table context" and its siblings.
+    fn clear_stack_back_to(&mut self, names: &[LocalName]) {
+        while !self
+            .current_node_name()
+            .is_some_and(|name| names.contains(name))
+        {
+            self.open_elements.pop();
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inselect
+    fn in_select(&mut self, token: Token<'_>) {
+        match token {
+            Token::Text(text, _) if text == "\0" => {}
+            Token::Text(text, span) => self.insert_text(&text, span),
+            Token::Comment(text, span) => self.insert_comment(&text, span),
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.tag_name == local_name::html => self.in_body(token),
+            Token::StartTag(tag) if tag.tag_name == local_name::option => {
+                if self.current_node_is(local_name::option) {
+                    self.open_elements.pop();
+                }
+                self.insert_element(tag);
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::optgroup => {
+                if self.current_node_is(local_name::option) {
+                    self.open_elements.pop();
+                }
+                if self.current_node_is(local_name::optgroup) {
+                    self.open_elements.pop();
+                }
+                self.insert_element(tag);
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::hr => {
+                if self.current_node_is(local_name::option) {
+                    self.open_elements.pop();
+                }
+                if self.current_node_is(local_name::optgroup) {
+                    self.open_elements.pop();
+                }
+                self.insert_element(tag);
+                self.open_elements.pop();
+                self.self_closing_acknowledged = true;
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::optgroup => {
+                let length = self.open_elements.len();
+                if self.current_node_is(local_name::option)
+                    && length >= 2
+                    && self.is_element_named(self.open_elements[length - 2], &local_name::optgroup)
+                {
+                    self.open_elements.pop();
+                }
+                if self.current_node_is(local_name::optgroup) {
+                    self.open_elements.pop();
+                }
+            }
+            Token::EndTag(tag)
+                if tag.tag_name == local_name::option
+                    && self.current_node_is(local_name::option) =>
+            {
+                self.open_elements.pop();
+            }
+            Token::StartTag(StartTag { tag_name, .. }) | Token::EndTag(EndTag { tag_name, .. })
+                if tag_name == local_name::select
+                    && self.has_element_in_scope(&[local_name::select], Scope::Select) =>
+            {
+                self.pop_until(&[local_name::select]);
+                self.reset_insertion_mode();
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::input | local_name::keygen | local_name::textarea
+                ) && self.has_element_in_scope(&[local_name::select], Scope::Select) =>
+            {
+                self.pop_until(&[local_name::select]);
+                self.reset_insertion_mode();
+                self.process_token(token);
+            }
+            Token::StartTag(ref tag)
+                if matches!(tag.tag_name, local_name::script | local_name::template) =>
+            {
+                self.in_head(token)
+            }
+            Token::EndTag(ref tag) if tag.tag_name == local_name::template => self.in_head(token),
+            Token::EndOfFile(_) => self.in_body(token),
+            _ => {}
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inselectintable
+    fn in_select_in_table(&mut self, token: Token<'_>) {
+        match token {
+            Token::StartTag(ref tag) if closes_select_in_table(&tag.tag_name) => {
+                self.pop_until(&[local_name::select]);
+                self.reset_insertion_mode();
+                self.process_token(token);
+            }
+            Token::EndTag(ref tag) if closes_select_in_table(&tag.tag_name) => {
+                if self.has_element_in_scope(std::slice::from_ref(&tag.tag_name), Scope::Table) {
+                    self.pop_until(&[local_name::select]);
+                    self.reset_insertion_mode();
+                    self.process_token(token);
+                }
+            }
+            _ => self.in_select(token),
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intemplate
+    fn in_template(&mut self, token: Token<'_>) {
+        match token {
+            Token::Character(..) | Token::Text(..) | Token::Comment(..) | Token::Doctype(_) => {
+                self.in_body(token)
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.tag_name,
+                    local_name::base
+                        | local_name::basefont
+                        | local_name::bgsound
+                        | local_name::link
+                        | local_name::meta
+                        | local_name::noframes
+                        | local_name::script
+                        | local_name::style
+                        | local_name::template
+                        | local_name::title
+                ) =>
+            {
+                self.in_head(token)
+            }
+            Token::EndTag(ref tag) if tag.tag_name == local_name::template => self.in_head(token),
+            Token::StartTag(ref tag) => {
+                let mode = match tag.tag_name {
+                    local_name::caption
+                    | local_name::colgroup
+                    | local_name::tbody
+                    | local_name::tfoot
+                    | local_name::thead => InsertionMode::InTable,
+                    local_name::col => InsertionMode::InColumnGroup,
+                    local_name::tr => InsertionMode::InTableBody,
+                    local_name::td | local_name::th => InsertionMode::InRow,
+                    _ => InsertionMode::InBody,
+                };
+
+                self.template_modes.pop();
+                self.template_modes.push(mode);
+                self.mode = mode;
+                self.process_token(token);
+            }
+            Token::EndTag(_) => {}
+            Token::EndOfFile(_) => {
+                if !self.has_open_element(local_name::template) {
+                    return;
+                }
+                // Reprocessing the token with a template still open comes
+                // back here, so close them all first rather than recursing
+                // once per nested template.
+                while !self.template_modes.is_empty() {
+                    self.pop_until(&[local_name::template]);
+                    self.clear_active_formatting_elements_to_last_marker();
+                    self.template_modes.pop();
+                    self.reset_insertion_mode();
+                }
+                self.process_token(token);
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-afterbody
+    fn after_body(&mut self, token: Token<'_>) {
+        match token {
+            Token::Text(ref text, _) if is_whitespace_run(text) => self.in_body(token),
+            Token::Comment(text, span) => {
+                self.insert_comment_in(self.open_elements[0], &text, span)
+            }
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.tag_name == local_name::html => self.in_body(token),
+            Token::EndTag(tag) if tag.tag_name == local_name::html => {
+                self.mode = InsertionMode::AfterAfterBody
+            }
+            Token::EndOfFile(_) => {}
+            _ => {
+                self.mode = InsertionMode::InBody;
+                self.process_token(token);
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inframeset
+    fn in_frameset(&mut self, token: Token<'_>) {
+        match token {
+            // Characters other than whitespace are ignored.
+            Token::Text(text, span) => {
+                let whitespace = whitespace_of(&text);
+                if !whitespace.is_empty() {
+                    self.insert_text(&whitespace, span);
+                }
+            }
+            Token::Comment(text, span) => self.insert_comment(&text, span),
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.tag_name == local_name::html => self.in_body(token),
+            Token::StartTag(tag) if tag.tag_name == local_name::frameset => {
+                self.insert_element(tag);
+            }
+            Token::EndTag(tag) if tag.tag_name == local_name::frameset => {
+                if self.open_elements.len() == 1 {
+                    return;
+                }
+                self.open_elements.pop();
+                if !self.current_node_is(local_name::frameset) {
+                    self.mode = InsertionMode::AfterFrameset;
+                }
+            }
+            Token::StartTag(tag) if tag.tag_name == local_name::frame => {
+                self.insert_element(tag);
+                self.open_elements.pop();
+                self.self_closing_acknowledged = true;
+            }
+            Token::StartTag(ref tag) if tag.tag_name == local_name::noframes => self.in_head(token),
+            _ => {}
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-afterframeset
+    fn after_frameset(&mut self, token: Token<'_>) {
+        match token {
+            // Characters other than whitespace are ignored.
+            Token::Text(text, span) => {
+                let whitespace = whitespace_of(&text);
+                if !whitespace.is_empty() {
+                    self.insert_text(&whitespace, span);
+                }
+            }
+            Token::Comment(text, span) => self.insert_comment(&text, span),
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if tag.tag_name == local_name::html => self.in_body(token),
+            Token::EndTag(tag) if tag.tag_name == local_name::html => {
+                self.mode = InsertionMode::AfterAfterFrameset
+            }
+            Token::StartTag(ref tag) if tag.tag_name == local_name::noframes => self.in_head(token),
+            _ => {}
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#the-after-after-body-insertion-mode
+    fn after_after_body(&mut self, token: Token<'_>) {
+        match token {
+            Token::Comment(text, span) => self.insert_comment_in(self.document.root(), &text, span),
+            Token::Doctype(_) => self.in_body(token),
+            Token::Text(ref text, _) if is_whitespace_run(text) => self.in_body(token),
+            Token::StartTag(ref tag) if tag.tag_name == local_name::html => self.in_body(token),
+            Token::EndOfFile(_) => {}
+            _ => {
+                self.mode = InsertionMode::InBody;
+                self.process_token(token);
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#the-after-after-frameset-insertion-mode
+    fn after_after_frameset(&mut self, token: Token<'_>) {
+        match token {
+            Token::Comment(text, span) => self.insert_comment_in(self.document.root(), &text, span),
+            Token::Doctype(_) => self.in_body(token),
+            Token::Text(text, span) => {
+                let whitespace = whitespace_of(&text);
+                if !whitespace.is_empty() {
+                    self.in_body(Token::Text(Cow::Owned(whitespace), span));
+                }
+            }
+            Token::StartTag(ref tag) if tag.tag_name == local_name::html => self.in_body(token),
+            Token::StartTag(ref tag) if tag.tag_name == local_name::noframes => self.in_head(token),
+            _ => {}
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inforeign
+    fn in_foreign_content(&mut self, token: Token<'_>) {
+        match token {
+            Token::Text(text, span) if text == "\0" => self.insert_text("\u{FFFD}", span),
+            Token::Text(text, span) if is_whitespace_run(&text) => self.insert_text(&text, span),
+            Token::Text(text, span) => {
+                self.insert_text(&text, span);
+                self.frameset_ok = false;
+            }
+            Token::Comment(text, span) => self.insert_comment(&text, span),
+            Token::Doctype(_) => {}
+            Token::StartTag(ref tag) if breaks_out_of_foreign_content(tag) => {
+                while !self.current_node_is_html_or_integration_point() {
+                    self.open_elements.pop();
+                }
+                self.process_using(self.mode, token);
+            }
+            Token::StartTag(mut tag) => {
+                let namespace = self.element(self.current_node()).namespace();
+                if namespace == Namespace::Svg {
+                    tag.tag_name = adjust_svg_tag_name(tag.tag_name);
+                }
+                self.insert_foreign_element(tag, namespace);
+            }
+            Token::EndTag(tag) => {
+                // Pop up to the closest element with the tag's name, unless
+                // an HTML element comes first, whose rules then apply.
+                for index in (0..self.open_elements.len()).rev() {
+                    let node = self.open_elements[index];
+                    let element = self.element(node);
+
+                    if index < self.open_elements.len() - 1
+                        && element.namespace() == Namespace::Html
+                    {
+                        self.process_using(self.mode, Token::EndTag(tag));
+                        return;
+                    }
+
+                    if element.name().eq_ignore_ascii_case(&tag.tag_name) {
+                        self.open_elements.truncate(index);
+                        return;
+                    }
+                }
+            }
+            Token::Character(..) | Token::EndOfFile(_) => {
+                unreachable!("the dispatcher doesn't take these to foreign content")
+            }
+        }
+    }
+
+    fn current_node_is_html_or_integration_point(&self) -> bool {
+        let element = self.element(self.current_node());
+
+        element.namespace() == Namespace::Html
+            || is_mathml_text_integration_point(element)
+            || is_html_integration_point(element)
+    }
+
+    fn current_node(&self) -> NodeId {
+        *self
+            .open_elements
+            .last()
+            .expect("the stack of open elements is not empty")
+    }
+
+    /// The local name of the current node, if it is an HTML element.
+    fn current_node_name(&self) -> Option<&LocalName> {
+        self.html_name(self.current_node())
+    }
+
+    fn current_node_is(&self, name: LocalName) -> bool {
+        self.is_element_named(self.current_node(), &name)
+    }
+
+    fn element(&self, node: NodeId) -> &Element {
+        self.document
+            .element(node)
+            .expect("open elements are elements")
+    }
+
+    /// The local name of `node`, if it is an HTML element.
+    fn html_name(&self, node: NodeId) -> Option<&LocalName> {
+        let element = self.element(node);
+        (element.namespace() == Namespace::Html).then(|| element.local_name())
+    }
+
+    /// Whether `node` is the HTML element called `name`.
+    fn is_element_named(&self, node: NodeId, name: &LocalName) -> bool {
+        self.html_name(node) == Some(name)
+    }
+
+    fn open_element_index(&self, node: NodeId) -> Option<usize> {
+        self.open_elements.index(node)
+    }
+
+    fn has_open_element(&self, name: LocalName) -> bool {
+        self.open_elements
+            .iter()
+            .any(|&node| self.is_element_named(node, &name))
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-the-specific-scope
+    fn has_element_in_scope(&self, names: &[LocalName], scope: Scope) -> bool {
+        if names
+            .iter()
+            .all(|name| self.open_elements.count(name) == Some(0))
+        {
+            return false;
+        }
+
+        for &node in self.open_elements.iter().rev() {
+            if self
+                .html_name(node)
+                .is_some_and(|name| names.contains(name))
+            {
+                return true;
+            }
+            if scope.is_boundary(self.element(node)) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Whether `target` itself is in the default scope.
+    fn has_node_in_scope(&self, target: NodeId) -> bool {
+        for &node in self.open_elements.iter().rev() {
+            if node == target {
+                return true;
+            }
+            if Scope::Default.is_boundary(self.element(node)) {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn create_element(&mut self, tag: StartTag<'_>, namespace: Namespace) -> NodeId {
+        let attributes = tag
+            .attributes
+            .into_iter()
+            .map(|attribute| dom_attribute(attribute, namespace))
+            .collect();
+
+        let element = self.document.create_node(
+            NodeData::Element(Element::new(namespace, tag.tag_name.clone(), attributes)),
+            tag.span,
+        );
+
+        if namespace == Namespace::Html && tag.tag_name == local_name::template {
+            let contents = self
+                .document
+                .create_node(NodeData::DocumentFragment, tag.span);
+            self.document.set_template_contents(element, contents);
+        }
+
+        element
+    }
+
+    /// Where a new node goes: usually at the end of `override_target`, or
+    /// the current node, but before the last table when foster parenting.
+    /// Nodes for a `template` go in its contents.
+    ///
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-place-for-inserting-a-node
+    fn appropriate_place(&self, override_target: Option<NodeId>) -> InsertionPoint {
+        let target = override_target.unwrap_or_else(|| self.current_node());
+
+        if !self.foster_parenting
+            || !matches!(
+                self.html_name(target),
+                Some(
+                    &(local_name::table
+                        | local_name::tbody
+                        | local_name::tfoot
+                        | local_name::thead
+                        | local_name::tr)
+                )
+            )
+        {
+            return self.inside(target);
+        }
+
+        let last = |name| {
+            self.open_elements
+                .iter()
+                .rposition(|&node| self.is_element_named(node, &name))
+        };
+
+        match (last(local_name::template), last(local_name::table)) {
+            (Some(template), table) if table.is_none_or(|table| template > table) => {
+                self.inside(self.open_elements[template])
+            }
+            (_, None) => self.inside(self.open_elements[0]),
+            (_, Some(table)) => {
+                let table_element = self.open_elements[table];
+                match self.document.node(table_element).parent() {
+                    Some(_) => InsertionPoint::Before(table_element),
+                    None => self.inside(self.open_elements[table - 1]),
+                }
+            }
+        }
+    }
+
+    /// The end of `parent`, or of its contents if it is a `template`.
+    fn inside(&self, parent: NodeId) -> InsertionPoint {
+        match self.document.node(parent).template_contents() {
+            Some(contents) => InsertionPoint::LastChild(contents),
+            None => InsertionPoint::LastChild(parent),
+        }
+    }
+
+    fn insert_node(&mut self, place: InsertionPoint, node: NodeId) {
+        match place {
+            InsertionPoint::LastChild(parent) => self.document.append(parent, node),
+            InsertionPoint::Before(sibling) => self.document.insert_before(sibling, node),
+        }
+    }
+
+    /// Creates an element for the same token as `element`, for reopening
+    /// and splitting formatting elements.
+    fn clone_element(&mut self, element: NodeId) -> NodeId {
+        let node = self.document.node(element);
+        let (data, span) = (node.data().clone(), node.span());
+        self.document.create_node(data, span)
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#insert-an-html-element
+    fn insert_element(&mut self, tag: StartTag<'_>) -> NodeId {
+        let element = self.create_element(tag, Namespace::Html);
+        self.insert_node(self.appropriate_place(None), element);
+        self.open_elements.push(element, &self.document);
+        element
+    }
+
+    /// Inserts an SVG or MathML element for `tag`, which is closed right
+    /// away if it is self-closing.
+    ///
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-foreign-element
+    fn insert_foreign_element(&mut self, tag: StartTag<'_>, namespace: Namespace) {
+        let self_closing = tag.self_closing;
+        let element = self.create_element(tag, namespace);
+        self.insert_node(self.appropriate_place(None), element);
+        self.open_elements.push(element, &self.document);
+
+        if self_closing {
+            self.open_elements.pop();
+            self.self_closing_acknowledged = true;
+        }
+    }
+
+    /// Inserts a `template` element for `tag`. With a `shadowrootmode`
+    /// attribute, its contents are instead attached to the current node as
+    /// a shadow root, and the element itself only goes on the stack of open
+    /// elements.
+    ///
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inhead
+    fn insert_template(&mut self, tag: StartTag<'_>) {
+        let attribute = |name| {
+            tag.attributes
+                .iter()
+                .find(|attribute| attribute.name == name)
+        };
+        let shadow_root = attribute(local_name::shadowrootmode)
+            .and_then(
+                |attribute| match attribute.value.to_ascii_lowercase().as_str() {
+                    "open" => Some(ShadowRootMode::Open),
+                    "closed" => Some(ShadowRootMode::Closed),
+                    _ => None,
+                },
+            )
+            .map(|mode| NodeData::ShadowRoot {
+                mode,
+                delegates_focus: attribute(local_name::shadowrootdelegatesfocus).is_some(),
+                clonable: attribute(local_name::shadowrootclonable).is_some(),
+                serializable: attribute(local_name::shadowrootserializable).is_some(),
+            });
+
+        let host = self.current_node();
+        let attaches = shadow_root.is_some()
+            && self.open_elements.len() > 1
+            && self.document.node(host).shadow_root().is_none()
+            && is_valid_shadow_host(self.element(host));
+
+        let place = self.appropriate_place(None);
+        let span = tag.span;
+        let template = self.create_element(tag, Namespace::Html);
+        self.open_elements.push(template, &self.document);
+
+        match shadow_root {
+            Some(shadow_root) if attaches => {
+                let shadow_root = self.document.create_node(shadow_root, span);
+                self.document.attach_shadow_root(host, shadow_root);
+                self.document.set_template_contents(template, shadow_root);
+            }
+            _ => self.insert_node(place, template),
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#insert-a-character
+    fn insert_text(&mut self, text: &str, span: Span) {
+        match self.appropriate_place(None) {
+            InsertionPoint::LastChild(parent) => self.document.append_text(parent, text, span),
+            InsertionPoint::Before(sibling) => {
+                self.document.insert_text_before(sibling, text, span)
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#insert-a-comment
+    fn insert_comment(&mut self, text: &str, span: Span) {
+        let comment = self
+            .document
+            .create_node(NodeData::Comment(String::from(text)), span);
+        self.insert_node(self.appropriate_place(None), comment);
+    }
+
+    fn insert_comment_in(&mut self, parent: NodeId, text: &str, span: Span) {
+        let comment = self
+            .document
+            .create_node(NodeData::Comment(String::from(text)), span);
+        self.document.append(parent, comment);
+    }
+
+    /// Adds the attributes of `tag` to `element` where it does not have
+    /// them already, for stray `html` and `body` start tags.
+    fn add_missing_attributes(&mut self, element: NodeId, tag: StartTag<'_>) {
+        let element = self
+            .document
+            .element_mut(element)
+            .expect("open elements are elements");
+
+        for attribute in tag.attributes {
+            element
+                .attributes_mut()
+                .insert_if_missing(dom_attribute(attribute, Namespace::Html));
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#generic-raw-text-element-parsing-algorithm
+    fn parse_text_element(&mut self, tag: StartTag<'_>, state: State) {
+        self.insert_element(tag);
+        self.tokenizer.set_state(state);
+        self.original_mode = self.mode;
+        self.mode = InsertionMode::Text;
+    }
+
+    /// Pops elements until one named like any of `names` has been popped.
+    fn pop_until(&mut self, names: &[LocalName]) {
+        while let Some(node) = self.open_elements.pop() {
+            if self
+                .html_name(node)
+                .is_some_and(|name| names.contains(name))
+            {
+                break;
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#generate-implied-end-tags
+    fn generate_implied_end_tags(&mut self, except: Option<&LocalName>) {
+        loop {
+            let Some(name) = self.current_node_name() else {
+                return;
+            };
+            if Some(name) == except
+                || !matches!(
+                    *name,
+                    local_name::dd
+                        | local_name::dt
+                        | local_name::li
+                        | local_name::optgroup
+                        | local_name::option
+                        | local_name::p
+                        | local_name::rb
+                        | local_name::rp
+                        | local_name::rt
+                        | local_name::rtc
+                )
+            {
+                return;
+            }
+            self.open_elements.pop();
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#generate-all-implied-end-tags-thoroughly
+    fn generate_all_implied_end_tags_thoroughly(&mut self) {
+        while matches!(
+            self.current_node_name(),
+            Some(
+                &(local_name::caption
+                    | local_name::colgroup
+                    | local_name::dd
+                    | local_name::dt
+                    | local_name::li
+                    | local_name::optgroup
+                    | local_name::option
+                    | local_name::p
+                    | local_name::rb
+                    | local_name::rp
+                    | local_name::rt
+                    | local_name::rtc
+                    | local_name::tbody
+                    | local_name::td
+                    | local_name::tfoot
+                    | local_name::th
+                    | local_name::thead
+                    | local_name::tr)
+            )
+        ) {
+            self.open_elements.pop();
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#close-a-p-element
+    fn close_p_element(&mut self) {
+        self.generate_implied_end_tags(Some(&local_name::p));
+        self.pop_until(&[local_name::p]);
+    }
+
+    fn close_p_element_in_button_scope(&mut self) {
+        if self.has_element_in_scope(&[local_name::p], Scope::Button) {
+            self.close_p_element();
+        }
+    }
+
+    /// Closes an open `li`, or `dd` and `dt`, element before a new one is
+    /// inserted, unless a special element other than `address`, `div` and
+    /// `p` is open below it.
+    fn close_list_item(&mut self, names: &[LocalName]) {
+        for index in (0..self.open_elements.len()).rev() {
+            let node = self.open_elements[index];
+            let name = self.html_name(node);
+
+            if let Some(name) = name.filter(|name| names.contains(name)).cloned() {
+                self.generate_implied_end_tags(Some(&name));
+                self.pop_until(&[name]);
+                return;
+            }
+
+            if is_special(self.element(node))
+                && !matches!(
+                    name,
+                    Some(&(local_name::address | local_name::div | local_name::p))
+                )
+            {
+                return;
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/parsing.html#reset-the-insertion-mode-appropriately
+    fn reset_insertion_mode(&mut self) {
+        for (index, &node) in self.open_elements.iter().enumerate().rev() {
+            let last = index == 0;
+
+            let Some(name) = self.html_name(node) else {
+                if last {
+                    self.mode = InsertionMode::InBody;
+                    return;
+                }
+                continue;
+            };
+
+            self.mode = match *name {
+                local_name::select => {
+                    let in_table = self.open_elements[..index]
+                        .iter()
+                        .rev()
+                        .map(|&ancestor| self.html_name(ancestor))
+                        .take_while(|&name| name != Some(&local_name::template))
+                        .any(|name| name == Some(&local_name::table));
+
+                    if in_table {
+                        InsertionMode::InSelectInTable
+                    } else {
+                        InsertionMode::InSelect
+                    }
+                }
+                local_name::td | local_name::th if !last => InsertionMode::InCell,
+                local_name::tr => InsertionMode::InRow,
+                local_name::tbody | local_name::thead | local_name::tfoot => {
+                    InsertionMode::InTableBody
+                }
+                local_name::caption => InsertionMode::InCaption,
+                local_name::colgroup => InsertionMode::InColumnGroup,
+                local_name::table => InsertionMode::InTable,
+                local_name::template => *self
+                    .template_modes
+                    .last()
+                    .expect("each open template has a template insertion mode"),
+                local_name::head if !last => InsertionMode::InHead,
+                local_name::body => InsertionMode::InBody,
+                local_name::frameset => InsertionMode::InFrameset,
+                local_name::html if self.head_element.is_none() => InsertionMode::BeforeHead,
+                local_name::html => InsertionMode::AfterHead,
+                _ if last => InsertionMode::InBody,
+                _ => continue,
+            };
+            return;
+        }
+    }
+}
+
+/// A start tag for an element the tree builder inserts without one in the
+/// input, like an implied `html`, `head` or `body`. Its span is usually the
+/// empty one where the token that implied it starts.
+fn synthesized_tag(name: LocalName, span: Span) -> StartTag<'static> {
+    StartTag {
+        tag_name: name,
+        self_closing: false,
+        attributes: Vec::new(),
+        span,
+    }
+}
+
+/// Converts an attribute of a start tag for an element in `namespace`,
+/// adjusting the names of MathML and SVG attributes.
+fn dom_attribute(attribute: tokenizer::Attribute<'_>, namespace: Namespace) -> dom::Attribute {
+    let (namespace, name) = match namespace {
+        Namespace::Html => (None, attribute.name),
+        _ => adjust_foreign_attribute(namespace, attribute.name),
+    };
+
+    dom::Attribute {
+        namespace,
+        name,
+        value: attribute.value.into_owned(),
+        name_span: attribute.name_span,
+        value_span: attribute.value_span,
+    }
+}
+
+// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-stack-back-to-a-table-context
+const TABLE_CONTEXT: [LocalName; 3] = [local_name::table, local_name::template, local_name::html];
+
+// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-stack-back-to-a-table-body-context
+const TABLE_BODY_CONTEXT: [LocalName; 5] = [
+    local_name::tbody,
+    local_name::tfoot,
+    local_name::thead,
+    local_name::template,
+    local_name::html,
+];
+
+// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-stack-back-to-a-table-row-context
+const TABLE_ROW_CONTEXT: [LocalName; 3] = [local_name::tr, local_name::template, local_name::html];
+
+fn is_whitespace(ch: char) -> bool {
+    matches!(ch, '\t' | '\n' | '\x0C' | '\r' | ' ')
+}
+
+/// Whether a `Token::Text` run is whitespace only. Runs never start with
+/// whitespace unless they are all whitespace.
+fn is_whitespace_run(text: &str) -> bool {
+    text.starts_with(is_whitespace)
+}
+
+fn whitespace_of(text: &str) -> String {
+    text.chars().filter(|&ch| is_whitespace(ch)).collect()
+}
+
+/// The table elements whose start and end tags close a `select` in a
+/// table.
+fn closes_select_in_table(name: &LocalName) -> bool {
+    matches!(
+        *name,
+        local_name::caption
+            | local_name::table
+            | local_name::tbody
+            | local_name::td
+            | local_name::tfoot
+            | local_name::th
+            | local_name::thead
+            | local_name::tr
+    )
+}
+
+fn is_heading(name: &LocalName) -> bool {
+    matches!(
+        *name,
+        local_name::h1
+            | local_name::h2
+            | local_name::h3
+            | local_name::h4
+            | local_name::h5
+            | local_name::h6
+    )
+}
+
+/// Formatting elements other than `a` and `nobr`, whose start tags have
+/// rules of their own.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#formatting
+fn is_formatting(name: &LocalName) -> bool {
+    matches!(
+        *name,
+        local_name::b
+            | local_name::big
+            | local_name::code
+            | local_name::em
+            | local_name::font
+            | local_name::i
+            | local_name::s
+            | local_name::small
+            | local_name::strike
+            | local_name::strong
+            | local_name::tt
+            | local_name::u
+    )
+}
+
+// https://html.spec.whatwg.org/multipage/parsing.html#special
+fn is_special(element: &Element) -> bool {
+    let name = element.local_name();
+
+    match element.namespace() {
+        Namespace::Html => is_special_html(name),
+        Namespace::MathMl => matches!(
+            *name,
+            local_name::mi
+                | local_name::mo
+                | local_name::mn
+                | local_name::ms
+                | local_name::mtext
+                | local_name::annotation_xml
+        ),
+        Namespace::Svg => matches!(
+            *name,
+            local_name::foreignObject | local_name::desc | local_name::title
+        ),
+        _ => false,
+    }
+}
+
+fn is_special_html(name: &LocalName) -> bool {
+    matches!(
+        *name,
+        local_name::address
+            | local_name::applet
+            | local_name::area
+            | local_name::article
+            | local_name::aside
+            | local_name::base
+            | local_name::basefont
+            | local_name::bgsound
+            | local_name::blockquote
+            | local_name::body
+            | local_name::br
+            | local_name::button
+            | local_name::caption
+            | local_name::center
+            | local_name::col
+            | local_name::colgroup
+            | local_name::dd
+            | local_name::details
+            | local_name::dir
+            | local_name::div
+            | local_name::dl
+            | local_name::dt
+            | local_name::embed
+            | local_name::fieldset
+            | local_name::figcaption
+            | local_name::figure
+            | local_name::footer
+            | local_name::form
+            | local_name::frame
+            | local_name::frameset
+            | local_name::h1
+            | local_name::h2
+            | local_name::h3
+            | local_name::h4
+            | local_name::h5
+            | local_name::h6
+            | local_name::head
+            | local_name::header
+            | local_name::hgroup
+            | local_name::hr
+            | local_name::html
+            | local_name::iframe
+            | local_name::img
+            | local_name::input
+            | local_name::keygen
+            | local_name::li
+            | local_name::link
+            | local_name::listing
+            | local_name::main
+            | local_name::marquee
+            | local_name::menu
+            | local_name::meta
+            | local_name::nav
+            | local_name::noembed
+            | local_name::noframes
+            | local_name::noscript
+            | local_name::object
+            | local_name::ol
+            | local_name::p
+            | local_name::param
+            | local_name::plaintext
+            | local_name::pre
+            | local_name::script
+            | local_name::search
+            | local_name::section
+            | local_name::select
+            | local_name::source
+            | local_name::style
+            | local_name::summary
+            | local_name::table
+            | local_name::tbody
+            | local_name::td
+            | local_name::template
+            | local_name::textarea
+            | local_name::tfoot
+            | local_name::th
+            | local_name::thead
+            | local_name::title
+            | local_name::tr
+            | local_name::track
+            | local_name::ul
+            | local_name::wbr
+            | local_name::xmp
+    )
+}
+
+/// MathML text integration points, where text and most start tags are
+/// parsed as HTML.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#mathml-text-integration-point
+fn is_mathml_text_integration_point(element: &Element) -> bool {
+    element.namespace() == Namespace::MathMl
+        && matches!(
+            *element.local_name(),
+            local_name::mi | local_name::mo | local_name::mn | local_name::ms | local_name::mtext
+        )
+}
+
+// https://html.spec.whatwg.org/multipage/parsing.html#html-integration-point
+fn is_html_integration_point(element: &Element) -> bool {
+    match element.namespace() {
+        Namespace::MathMl => {
+            *element.local_name() == local_name::annotation_xml
+                && element.attribute("encoding").is_some_and(|encoding| {
+                    encoding.eq_ignore_ascii_case("text/html")
+                        || encoding.eq_ignore_ascii_case("application/xhtml+xml")
+                })
+        }
+        Namespace::Svg => matches!(
+            *element.local_name(),
+            local_name::foreignObject | local_name::desc | local_name::title
+        ),
+        _ => false,
+    }
+}
+
+/// Start tags that close foreign elements up to the closest HTML element
+/// or integration point, to be parsed as HTML.
+fn breaks_out_of_foreign_content(tag: &StartTag<'_>) -> bool {
+    match tag.tag_name {
+        local_name::font => tag.attributes.iter().any(|attribute| {
+            matches!(
+                attribute.name,
+                local_name::color | local_name::face | local_name::size
+            )
+        }),
+        _ => {
+            is_heading(&tag.tag_name)
+                || matches!(
+                    tag.tag_name,
+                    local_name::b
+                        | local_name::big
+                        | local_name::blockquote
+                        | local_name::body
+                        | local_name::br
+                        | local_name::center
+                        | local_name::code
+                        | local_name::dd
+                        | local_name::div
+                        | local_name::dl
+                        | local_name::dt
+                        | local_name::em
+                        | local_name::embed
+                        | local_name::head
+                        | local_name::hr
+                        | local_name::i
+                        | local_name::img
+                        | local_name::li
+                        | local_name::listing
+                        | local_name::menu
+                        | local_name::meta
+                        | local_name::nobr
+                        | local_name::ol
+                        | local_name::p
+                        | local_name::pre
+                        | local_name::ruby
+                        | local_name::s
+                        | local_name::small
+                        | local_name::span
+                        | local_name::strong
+                        | local_name::strike
+                        | local_name::sub
+                        | local_name::sup
+                        | local_name::table
+                        | local_name::tt
+                        | local_name::u
+                        | local_name::ul
+                        | local_name::var
+                )
+        }
+    }
+}
+
+fn adjust_svg_tag_name(name: LocalName) -> LocalName {
+    SVG_TAG_NAMES
+        .iter()
+        .find(|(lowercase, _)| *lowercase == name)
+        .map_or(name, |(_, adjusted)| adjusted.clone())
+}
+
+/// The namespace and local name of an attribute on a MathML or SVG
+/// element, whose name the tokenizer lowercased.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#adjust-foreign-attributes
+fn adjust_foreign_attribute(
+    namespace: Namespace,
+    name: LocalName,
+) -> (Option<Namespace>, LocalName) {
+    if let Some((_, namespace, local_name)) = FOREIGN_ATTRIBUTES
+        .iter()
+        .find(|(qualified_name, _, _)| *qualified_name == name)
+    {
+        return (Some(*namespace), local_name.clone());
+    }
+
+    let name = match namespace {
+        Namespace::MathMl if name == local_name::definitionurl => local_name::definitionURL,
+        Namespace::Svg => SVG_ATTRIBUTE_NAMES
+            .iter()
+            .find(|(lowercase, _)| *lowercase == name)
+            .map_or(name, |(_, adjusted)| adjusted.clone()),
+        _ => name,
+    };
+
+    (None, name)
+}
+
+// https://dom.spec.whatwg.org/#valid-shadow-host-name
+fn is_valid_shadow_host(element: &Element) -> bool {
+    element.namespace() == Namespace::Html
+        && (is_valid_custom_element_name(element.name())
+            || matches!(
+                *element.local_name(),
+                local_name::article
+                    | local_name::aside
+                    | local_name::blockquote
+                    | local_name::body
+                    | local_name::div
+                    | local_name::footer
+                    | local_name::header
+                    | local_name::main
+                    | local_name::nav
+                    | local_name::p
+                    | local_name::section
+                    | local_name::span
+            )
+            || is_heading(element.local_name()))
+}
+
+/// Whether `name`, as lowercased by the tokenizer, names an autonomous
+/// custom element.
+///
+/// https://html.spec.whatwg.org/multipage/custom-elements.html#valid-custom-element-name
+fn is_valid_custom_element_name(name: &str) -> bool {
+    name.starts_with(|ch: char| ch.is_ascii_lowercase())
+        && name.contains('-')
+        && !matches!(
+            name,
+            "annotation-xml"
+                | "color-profile"
+                | "font-face"
+                | "font-face-src"
+                | "font-face-uri"
+                | "font-face-format"
+                | "font-face-name"
+                | "missing-glyph"
+        )
+}
+
+/// The quirks mode a document with `doctype` is rendered in.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+fn quirks_mode(doctype: &Doctype) -> QuirksMode {
+    let public_id = doctype.public_id.as_deref().map(str::to_ascii_lowercase);
+    let system_id = doctype.system_id.as_deref().map(str::to_ascii_lowercase);
+    let public_id_starts_with = |prefixes: &[&str]| {
+        public_id
+            .as_deref()
+            .is_some_and(|public_id| prefixes.iter().any(|prefix| public_id.starts_with(prefix)))
+    };
+
+    let html_401_frameset_or_transitional = public_id_starts_with(&[
+        "-//w3c//dtd html 4.01 frameset//",
+        "-//w3c//dtd html 4.01 transitional//",
+    ]);
+
+    if doctype.force_quirks
+        || doctype.name.as_deref() != Some("html")
+        || matches!(
+            public_id.as_deref(),
+            Some(
+                "-//w3o//dtd w3 html strict 3.0//en//"
+                    | "-/w3c/dtd html 4.0 transitional/en"
+                    | "html"
+            )
+        )
+        || system_id.as_deref()
+            == Some("http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd")
+        || public_id_starts_with(&QUIRKY_PUBLIC_ID_PREFIXES)
+        || (system_id.is_none() && html_401_frameset_or_transitional)
+    {
+        QuirksMode::Quirks
+    } else if public_id_starts_with(&[
+        "-//w3c//dtd xhtml 1.0 frameset//",
+        "-//w3c//dtd xhtml 1.0 transitional//",
+    ]) || (system_id.is_some() && html_401_frameset_or_transitional)
+    {
+        QuirksMode::LimitedQuirks
+    } else {
+        QuirksMode::NoQuirks
+    }
+}
+
+/// Public identifiers, in lowercase, that put a document in quirks mode.
+const QUIRKY_PUBLIC_ID_PREFIXES: [&str; 55] = [
+    "+//silmaril//dtd html pro v0r11 19970101//",
+    "-//as//dtd html 3.0 aswedit + extensions//",
+    "-//advasoft ltd//dtd html 3.0 aswedit + extensions//",
+    "-//ietf//dtd html 2.0 level 1//",
+    "-//ietf//dtd html 2.0 level 2//",
+    "-//ietf//dtd html 2.0 strict level 1//",
+    "-//ietf//dtd html 2.0 strict level 2//",
+    "-//ietf//dtd html 2.0 strict//",
+    "-//ietf//dtd html 2.0//",
+    "-//ietf//dtd html 2.1e//",
+    "-//ietf//dtd html 3.0//",
+    "-//ietf//dtd html 3.2 final//",
+    "-//ietf//dtd html 3.2//",
+    "-//ietf//dtd html 3//",
+    "-//ietf//dtd html level 0//",
+    "-//ietf//dtd html level 1//",
+    "-//ietf//dtd html level 2//",
+    "-//ietf//dtd html level 3//",
+    "-//ietf//dtd html strict level 0//",
+    "-//ietf//dtd html strict level 1//",
+    "-//ietf//dtd html strict level 2//",
+    "-//ietf//dtd html strict level 3//",
+    "-//ietf//dtd html strict//",
+    "-//ietf//dtd html//",
+    "-//metrius//dtd metrius presentational//",
+    "-//microsoft//dtd internet explorer 2.0 html strict//",
+    "-//microsoft//dtd internet explorer 2.0 html//",
+    "-//microsoft//dtd internet explorer 2.0 tables//",
+    "-//microsoft//dtd internet explorer 3.0 html strict//",
+    "-//microsoft//dtd internet explorer 3.0 html//",
+    "-//microsoft//dtd internet explorer 3.0 tables//",
+    "-//netscape comm. corp.//dtd html//",
+    "-//netscape comm. corp.//dtd strict html//",
+    "-//o'reilly and associates//dtd html 2.0//",
+    "-//o'reilly and associates//dtd html extended 1.0//",
+    "-//o'reilly and associates//dtd html extended relaxed 1.0//",
+    "-//sq//dtd html 2.0 hotmetal + extensions//",
+    "-//softquad software//dtd hotmetal pro 6.0::19990601::extensions to html 4.0//",
+    "-//softquad//dtd hotmetal pro 4.0::19971010::extensions to html 4.0//",
+    "-//spyglass//dtd html 2.0 extended//",
+    "-//sun microsystems corp.//dtd hotjava html//",
+    "-//sun microsystems corp.//dtd hotjava strict html//",
+    "-//w3c//dtd html 3 1995-03-24//",
+    "-//w3c//dtd html 3.2 draft//",
+    "-//w3c//dtd html 3.2 final//",
+    "-//w3c//dtd html 3.2//",
+    "-//w3c//dtd html 3.2s draft//",
+    "-//w3c//dtd html 4.0 frameset//",
+    "-//w3c//dtd html 4.0 transitional//",
+    "-//w3c//dtd html experimental 19960712//",
+    "-//w3c//dtd html experimental 970421//",
+    "-//w3c//dtd w3 html//",
+    "-//w3o//dtd w3 html 3.0//",
+    "-//webtechs//dtd mozilla html 2.0//",
+    "-//webtechs//dtd mozilla html//",
+];
+
+/// Lowercased SVG element names and how they are written in SVG.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inforeign
+const SVG_TAG_NAMES: [(LocalName, LocalName); 37] = [
+    (local_name::altglyph, local_name::altGlyph),
+    (local_name::altglyphdef, local_name::altGlyphDef),
+    (local_name::altglyphitem, local_name::altGlyphItem),
+    (local_name::animatecolor, local_name::animateColor),
+    (local_name::animatemotion, local_name::animateMotion),
+    (local_name::animatetransform, local_name::animateTransform),
+    (local_name::clippath, local_name::clipPath),
+    (local_name::feblend, local_name::feBlend),
+    (local_name::fecolormatrix, local_name::feColorMatrix),
+    (
+        local_name::fecomponenttransfer,
+        local_name::feComponentTransfer,
+    ),
+    (local_name::fecomposite, local_name::feComposite),
+    (local_name::feconvolvematrix, local_name::feConvolveMatrix),
+    (local_name::fediffuselighting, local_name::feDiffuseLighting),
+    (local_name::fedisplacementmap, local_name::feDisplacementMap),
+    (local_name::fedistantlight, local_name::feDistantLight),
+    (local_name::fedropshadow, local_name::feDropShadow),
+    (local_name::feflood, local_name::feFlood),
+    (local_name::fefunca, local_name::feFuncA),
+    (local_name::fefuncb, local_name::feFuncB),
+    (local_name::fefuncg, local_name::feFuncG),
+    (local_name::fefuncr, local_name::feFuncR),
+    (local_name::fegaussianblur, local_name::feGaussianBlur),
+    (local_name::feimage, local_name::feImage),
+    (local_name::femerge, local_name::feMerge),
+    (local_name::femergenode, local_name::feMergeNode),
+    (local_name::femorphology, local_name::feMorphology),
+    (local_name::feoffset, local_name::feOffset),
+    (local_name::fepointlight, local_name::fePointLight),
+    (
+        local_name::fespecularlighting,
+        local_name::feSpecularLighting,
+    ),
+    (local_name::fespotlight, local_name::feSpotLight),
+    (local_name::fetile, local_name::feTile),
+    (local_name::feturbulence, local_name::feTurbulence),
+    (local_name::foreignobject, local_name::foreignObject),
+    (local_name::glyphref, local_name::glyphRef),
+    (local_name::lineargradient, local_name::linearGradient),
+    (local_name::radialgradient, local_name::radialGradient),
+    (local_name::textpath, local_name::textPath),
+];
+
+/// Lowercased SVG attribute names and how they are written in SVG.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#adjust-svg-attributes
+const SVG_ATTRIBUTE_NAMES: [(LocalName, LocalName); 58] = [
+    (local_name::attributename, local_name::attributeName),
+    (local_name::attributetype, local_name::attributeType),
+    (local_name::basefrequency, local_name::baseFrequency),
+    (local_name::baseprofile, local_name::baseProfile),
+    (local_name::calcmode, local_name::calcMode),
+    (local_name::clippathunits, local_name::clipPathUnits),
+    (local_name::diffuseconstant, local_name::diffuseConstant),
+    (local_name::edgemode, local_name::edgeMode),
+    (local_name::filterunits, local_name::filterUnits),
+    (local_name::glyphref, local_name::glyphRef),
+    (local_name::gradienttransform, local_name::gradientTransform),
+    (local_name::gradientunits, local_name::gradientUnits),
+    (local_name::kernelmatrix, local_name::kernelMatrix),
+    (local_name::kernelunitlength, local_name::kernelUnitLength),
+    (local_name::keypoints, local_name::keyPoints),
+    (local_name::keysplines, local_name::keySplines),
+    (local_name::keytimes, local_name::keyTimes),
+    (local_name::lengthadjust, local_name::lengthAdjust),
+    (local_name::limitingconeangle, local_name::limitingConeAngle),
+    (local_name::markerheight, local_name::markerHeight),
+    (local_name::markerunits, local_name::markerUnits),
+    (local_name::markerwidth, local_name::markerWidth),
+    (local_name::maskcontentunits, local_name::maskContentUnits),
+    (local_name::maskunits, local_name::maskUnits),
+    (local_name::numoctaves, local_name::numOctaves),
+    (local_name::pathlength, local_name::pathLength),
+    (
+        local_name::patterncontentunits,
+        local_name::patternContentUnits,
+    ),
+    (local_name::patterntransform, local_name::patternTransform),
+    (local_name::patternunits, local_name::patternUnits),
+    (local_name::pointsatx, local_name::pointsAtX),
+    (local_name::pointsaty, local_name::pointsAtY),
+    (local_name::pointsatz, local_name::pointsAtZ),
+    (local_name::preservealpha, local_name::preserveAlpha),
+    (
+        local_name::preserveaspectratio,
+        local_name::preserveAspectRatio,
+    ),
+    (local_name::primitiveunits, local_name::primitiveUnits),
+    (local_name::refx, local_name::refX),
+    (local_name::refy, local_name::refY),
+    (local_name::repeatcount, local_name::repeatCount),
+    (local_name::repeatdur, local_name::repeatDur),
+    (
+        local_name::requiredextensions,
+        local_name::requiredExtensions,
+    ),
+    (local_name::requiredfeatures, local_name::requiredFeatures),
+    (local_name::specularconstant, local_name::specularConstant),
+    (local_name::specularexponent, local_name::specularExponent),
+    (local_name::spreadmethod, local_name::spreadMethod),
+    (local_name::startoffset, local_name::startOffset),
+    (local_name::stddeviation, local_name::stdDeviation),
+    (local_name::stitchtiles, local_name::stitchTiles),
+    (local_name::surfacescale, local_name::surfaceScale),
+    (local_name::systemlanguage, local_name::systemLanguage),
+    (local_name::tablevalues, local_name::tableValues),
+    (local_name::targetx, local_name::targetX),
+    (local_name::targety, local_name::targetY),
+    (local_name::textlength, local_name::textLength),
+    (local_name::viewbox, local_name::viewBox),
+    (local_name::viewtarget, local_name::viewTarget),
+    (local_name::xchannelselector, local_name::xChannelSelector),
+    (local_name::ychannelselector, local_name::yChannelSelector),
+    (local_name::zoomandpan, local_name::zoomAndPan),
+];
+
+/// Namespaced attributes of foreign elements, with their namespace and
+/// local name.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#adjust-foreign-attributes
+const FOREIGN_ATTRIBUTES: [(LocalName, Namespace, LocalName); 11] = [
+    (
+        local_name::xlink_actuate,
+        Namespace::XLink,
+        local_name::actuate,
+    ),
+    (
+        local_name::xlink_arcrole,
+        Namespace::XLink,
+        local_name::arcrole,
+    ),
+    (local_name::xlink_href, Namespace::XLink, local_name::href),
+    (local_name::xlink_role, Namespace::XLink, local_name::role),
+    (local_name::xlink_show, Namespace::XLink, local_name::show),
+    (local_name::xlink_title, Namespace::XLink, local_name::title),
+    (local_name::xlink_type, Namespace::XLink, local_name::r#type),
+    (local_name::xml_lang, Namespace::Xml, local_name::lang),
+    (local_name::xml_space, Namespace::Xml, local_name::space),
+    (local_name::xmlns, Namespace::XmlNs, local_name::xmlns),
+    (local_name::xmlns_xlink, Namespace::XmlNs, local_name::xlink),
+];
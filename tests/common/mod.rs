@@ -0,0 +1,81 @@
+//! Helpers shared by the integration tests.
+
+use std::fmt::Write;
+
+use rust_scraper::dom::{Document, Namespace, NodeData, NodeId};
+
+/// Returns the tree of `document` in the format of html5lib-tests'
+/// tree construction fixtures.
+pub fn tree(document: &Document) -> String {
+    children(document, document.root())
+}
+
+/// Like `tree`, for the children of any node.
+pub fn children(document: &Document, parent: NodeId) -> String {
+    let mut out = String::new();
+    serialize(document, parent, 0, &mut out);
+    out
+}
+
+/// Writes the children of `parent` in the fixtures' format, e.g.
+/// `| <svg foreignObject>` followed by its attributes and children, each
+/// indented two more spaces.
+fn serialize(document: &Document, parent: NodeId, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+
+    for child in document.children(parent) {
+        let node = document.node(child);
+        match node.data() {
+            NodeData::Doctype {
+                name,
+                public_id,
+                system_id,
+            } => {
+                if public_id.is_empty() && system_id.is_empty() {
+                    writeln!(out, "| {indent}<!DOCTYPE {name}>").unwrap();
+                } else {
+                    writeln!(
+                        out,
+                        "| {indent}<!DOCTYPE {name} \"{public_id}\" \"{system_id}\">"
+                    )
+                    .unwrap();
+                }
+            }
+            NodeData::Element(element) => {
+                let prefix = match element.namespace() {
+                    Namespace::Svg => "svg ",
+                    Namespace::MathMl => "math ",
+                    _ => "",
+                };
+                writeln!(out, "| {indent}<{prefix}{}>", element.name()).unwrap();
+
+                let mut attributes: Vec<_> = element
+                    .attributes()
+                    .iter()
+                    .map(|attribute| {
+                        let prefix = match attribute.namespace {
+                            Some(Namespace::XLink) => "xlink ",
+                            Some(Namespace::Xml) => "xml ",
+                            Some(Namespace::XmlNs) => "xmlns ",
+                            _ => "",
+                        };
+                        (format!("{prefix}{}", attribute.name), &attribute.value)
+                    })
+                    .collect();
+                attributes.sort();
+                for (name, value) in attributes {
+                    writeln!(out, "| {indent}  {name}=\"{value}\"").unwrap();
+                }
+
+                if let Some(contents) = node.template_contents() {
+                    writeln!(out, "| {indent}  content").unwrap();
+                    serialize(document, contents, depth + 2, out);
+                }
+                serialize(document, child, depth + 1, out);
+            }
+            NodeData::Text(text) => writeln!(out, "| {indent}\"{text}\"").unwrap(),
+            NodeData::Comment(text) => writeln!(out, "| {indent}<!-- {text} -->").unwrap(),
+            data => panic!("unexpected node in a parsed document: {data:?}"),
+        }
+    }
+}
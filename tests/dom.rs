@@ -0,0 +1,71 @@
+//! Building and changing a `Document` directly.
+
+use rust_scraper::dom::{Attributes, Document, Element, Namespace, NodeData, NodeId};
+use rust_scraper::local_name::LocalName;
+use rust_scraper::parse_error::Span;
+
+fn element(document: &mut Document, name: &str) -> NodeId {
+    let element = Element::new(Namespace::Html, LocalName::new(name), Attributes::default());
+    document.create_node(NodeData::Element(element), Span::default())
+}
+
+/// `<div><p><b></b></p></div>` under the root, as `(div, p, b)`.
+fn nested(document: &mut Document) -> (NodeId, NodeId, NodeId) {
+    let (div, p, b) = (
+        element(document, "div"),
+        element(document, "p"),
+        element(document, "b"),
+    );
+    document.append(document.root(), div);
+    document.append(div, p);
+    document.append(p, b);
+    (div, p, b)
+}
+
+#[test]
+fn nodes_move_between_parents() {
+    let mut document = Document::new();
+    let (div, p, b) = nested(&mut document);
+    let i = element(&mut document, "i");
+    document.append(div, i);
+
+    // `p` takes `b` along.
+    document.insert_before(p, i);
+    document.append(i, p);
+    let descendants: Vec<_> = document.descendants(div).collect();
+    assert_eq!(descendants, [i, p, b]);
+    assert_eq!(document.node(p).parent(), Some(i));
+    assert_eq!(document.node(i).next_sibling(), None);
+}
+
+#[test]
+#[should_panic(expected = "cannot insert a node into itself or one of its descendants")]
+fn append_to_itself() {
+    let mut document = Document::new();
+    let (_, p, _) = nested(&mut document);
+    document.append(p, p);
+}
+
+#[test]
+#[should_panic(expected = "cannot insert a node into itself or one of its descendants")]
+fn append_to_descendant() {
+    let mut document = Document::new();
+    let (div, _, b) = nested(&mut document);
+    document.append(b, div);
+}
+
+#[test]
+#[should_panic(expected = "cannot insert a node before itself")]
+fn insert_before_itself() {
+    let mut document = Document::new();
+    let (_, p, _) = nested(&mut document);
+    document.insert_before(p, p);
+}
+
+#[test]
+#[should_panic(expected = "cannot insert a node into itself or one of its descendants")]
+fn insert_before_descendant() {
+    let mut document = Document::new();
+    let (div, _, b) = nested(&mut document);
+    document.insert_before(b, div);
+}
@@ -0,0 +1,198 @@
+//! Encoding sniffing and decoding of byte streams.
+
+use encoding_rs::{Encoding, KOI8_R, UTF_16LE, UTF_8, WINDOWS_1252};
+use rust_scraper::encoding::{
+    decode, extract_charset_from_content, sniff, Confidence, EncodingDecision, EncodingSource,
+};
+use rust_scraper::tokenizer::{tokenize_bytes, Token};
+
+fn decision(
+    encoding: &'static Encoding,
+    confidence: Confidence,
+    source: EncodingSource,
+) -> EncodingDecision {
+    EncodingDecision {
+        encoding,
+        confidence,
+        source,
+    }
+}
+
+#[test]
+fn byte_order_mark_wins() {
+    let bytes = b"\xEF\xBB\xBF<meta charset=koi8-r>\xC3\xA9";
+    let sniffed = sniff(bytes, Some("iso-8859-2"));
+    assert_eq!(
+        sniffed,
+        decision(UTF_8, Confidence::Certain, EncodingSource::ByteOrderMark)
+    );
+    assert_eq!(decode(bytes, &sniffed), "<meta charset=koi8-r>é");
+
+    let bytes = b"\xFF\xFEa\x00";
+    let sniffed = sniff(bytes, None);
+    assert_eq!(
+        sniffed,
+        decision(UTF_16LE, Confidence::Certain, EncodingSource::ByteOrderMark)
+    );
+    assert_eq!(decode(bytes, &sniffed), "a");
+}
+
+#[test]
+fn transport_layer_overrides_meta() {
+    let bytes = b"<meta charset=utf-8>";
+    assert_eq!(
+        sniff(bytes, Some("koi8-r")),
+        decision(KOI8_R, Confidence::Certain, EncodingSource::TransportLayer)
+    );
+    // An unknown label is ignored.
+    assert_eq!(
+        sniff(bytes, Some("bogus")),
+        decision(UTF_8, Confidence::Tentative, EncodingSource::Prescan)
+    );
+}
+
+#[test]
+fn prescan_finds_meta() {
+    let prescan = |html: &str| sniff(html.as_bytes(), None).encoding;
+
+    assert_eq!(prescan("<meta charset=koi8-r>"), KOI8_R);
+    assert_eq!(
+        prescan("<!-- <meta charset=utf-8> --><meta charset='koi8-r'>"),
+        KOI8_R
+    );
+    assert_eq!(
+        prescan("<p title='<meta charset=utf-8>'><meta charset=koi8-r>"),
+        KOI8_R
+    );
+    assert_eq!(
+        prescan(r#"<meta http-equiv=Content-Type content="text/html; charset=koi8-r">"#),
+        KOI8_R
+    );
+    // `content` needs `http-equiv`.
+    assert_eq!(
+        prescan(r#"<meta content="text/html; charset=koi8-r">"#),
+        WINDOWS_1252
+    );
+
+    // The first of `content` and `charset` is used.
+    assert_eq!(
+        prescan(
+            r#"<meta content="text/html; charset=koi8-r" charset=utf-8 http-equiv=content-type>"#
+        ),
+        KOI8_R
+    );
+    assert_eq!(
+        prescan(
+            r#"<meta charset=koi8-r content="text/html; charset=utf-8" http-equiv=content-type>"#
+        ),
+        KOI8_R
+    );
+    // So is the first of duplicate attributes.
+    assert_eq!(prescan("<meta charset=koi8-r charset=utf-8>"), KOI8_R);
+}
+
+#[test]
+fn prescan_stops_after_1024_bytes() {
+    let html = format!("<p>{}<meta charset=koi8-r>", "x".repeat(1024));
+    assert_eq!(
+        sniff(html.as_bytes(), None),
+        decision(WINDOWS_1252, Confidence::Tentative, EncodingSource::Default)
+    );
+}
+
+#[test]
+fn utf_16_declarations_mean_utf_8() {
+    assert_eq!(sniff(b"<meta charset=utf-16le>", None).encoding, UTF_8);
+    assert_eq!(sniff(b"<meta charset=utf-16be>", None).encoding, UTF_8);
+    assert_eq!(
+        sniff(b"<meta charset=x-user-defined>", None).encoding,
+        WINDOWS_1252
+    );
+}
+
+fn extract(content: &str) -> Option<&str> {
+    extract_charset_from_content(content.as_bytes())
+        .map(|label| std::str::from_utf8(label).unwrap())
+}
+
+#[test]
+fn charset_is_extracted_from_content() {
+    assert_eq!(extract("text/html; charset=utf-8"), Some("utf-8"));
+    assert_eq!(extract("text/html; CHARSET = 'koi8-r' x"), Some("koi8-r"));
+    assert_eq!(extract("charset=Big5;x"), Some("Big5"));
+    assert_eq!(extract("charsetx; charset=\"a b\""), Some("a b"));
+    assert_eq!(extract("charset=\"unterminated"), None);
+    assert_eq!(extract("charset="), None);
+    assert_eq!(extract("text/html"), None);
+}
+
+fn text(tokens: &[Token<'_>]) -> String {
+    tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Text(text, _) => Some(&**text),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn late_meta_decodes_the_input_again() {
+    let mut bytes = format!("<p>{}<meta charset=koi8-r><p>", "x".repeat(1024)).into_bytes();
+    bytes.extend_from_slice(&KOI8_R.encode("Привет").0);
+
+    let decoded = tokenize_bytes(&bytes, None);
+    assert_eq!(
+        decoded.encoding,
+        decision(KOI8_R, Confidence::Certain, EncodingSource::MetaChange)
+    );
+    assert!(text(&decoded.tokens).ends_with("Привет"));
+
+    // A `<meta>` agreeing with the guess only makes it certain.
+    let decoded = tokenize_bytes(
+        format!("<p>{}<meta charset=windows-1252>", "x".repeat(1024)).as_bytes(),
+        None,
+    );
+    assert_eq!(
+        decoded.encoding,
+        decision(WINDOWS_1252, Confidence::Certain, EncodingSource::Default)
+    );
+
+    // A certain encoding is kept.
+    let decoded = tokenize_bytes(&bytes, Some("utf-8"));
+    assert_eq!(
+        decoded.encoding,
+        decision(UTF_8, Confidence::Certain, EncodingSource::TransportLayer)
+    );
+    assert!(text(&decoded.tokens).ends_with('\u{FFFD}'));
+}
+
+#[test]
+fn late_utf_16_meta_means_utf_8() {
+    let mut bytes = format!("<p>{}<meta charset=utf-16><p>", "x".repeat(1024)).into_bytes();
+    bytes.extend_from_slice("é".as_bytes());
+
+    let decoded = tokenize_bytes(&bytes, None);
+    assert_eq!(
+        decoded.encoding,
+        decision(UTF_8, Confidence::Certain, EncodingSource::MetaChange)
+    );
+    assert!(text(&decoded.tokens).ends_with('é'));
+}
+
+#[test]
+fn late_meta_with_unknown_charset_falls_back_to_content() {
+    let mut bytes = format!(
+        r#"<p>{}<meta charset="bogus" http-equiv="content-type" content="text/html; charset=koi8-r"><p>"#,
+        "x".repeat(1024)
+    )
+    .into_bytes();
+    bytes.extend_from_slice(&KOI8_R.encode("Привет").0);
+
+    let decoded = tokenize_bytes(&bytes, None);
+    assert_eq!(
+        decoded.encoding,
+        decision(KOI8_R, Confidence::Certain, EncodingSource::MetaChange)
+    );
+    assert!(text(&decoded.tokens).ends_with("Привет"));
+}
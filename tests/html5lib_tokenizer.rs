@@ -0,0 +1,446 @@
+//! Conformance against the tokenizer tests of html5lib-tests.
+//!
+//! The fixtures in `tests/html5lib-tests/tokenizer` are vendored from
+//! https://github.com/html5lib/html5lib-tests under the MIT license (see
+//! `tests/html5lib-tests/LICENSE`, and `REVISION` for where the snapshot
+//! comes from). Every case is run once per initial state, both with the
+//! whole input at once and fed a character at a time.
+//!
+//! Parse errors are compared by code for cases that list them in an
+//! `errors` array, and otherwise by the number of `"ParseError"` entries in
+//! their output.
+//!
+//! Run with `cargo test --test html5lib_tokenizer -- --nocapture` to see
+//! the pass rate of each file.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use rust_scraper::parse_error::PositionedError;
+use rust_scraper::tokenizer::{State, StatePolicy, Token, Tokenizer, TokenizerOptions};
+use serde_json::{json, Map, Value};
+
+/// Cases whose tokens match but whose parse errors do not, as file and
+/// description. The vendored fixtures predate some spec changes, which the
+/// tokenizer follows.
+const KNOWN_ERROR_MISMATCHES: &[(&str, &str)] = &[
+    // The `--` in `<!----x` and `<!-- a--b -->` is no longer an error.
+    ("domjs.test", r#"--!NUL in comment "#),
+    ("pendingSpecChanges.test", r#"<!---- >"#),
+    ("test1.test", r#"Comment, Central dash no space"#),
+    ("test1.test", r#"Comment, two central dashes"#),
+    ("test3.test", r#"<!---- "#),
+    ("test3.test", r#"<!----  -->"#),
+    ("test3.test", r#"<!---- -"#),
+    ("test3.test", r#"<!---- --"#),
+    ("test3.test", r#"<!---- -->"#),
+    ("test3.test", r#"<!---- a-->"#),
+    ("test3.test", r#"<!----!"#),
+    ("test3.test", r#"<!----!-"#),
+    ("test3.test", r#"<!----!--"#),
+    ("test3.test", r#"<!----!-->"#),
+    ("test3.test", r#"<!----!a"#),
+    ("test3.test", r#"<!----!a-"#),
+    ("test3.test", r#"<!----!a--"#),
+    ("test3.test", r#"<!----!a-->"#),
+    ("test3.test", r#"<!----""#),
+    ("test3.test", r#"<!----&"#),
+    ("test3.test", r#"<!----'"#),
+    ("test3.test", r#"<!----,"#),
+    ("test3.test", r#"<!-----"#),
+    ("test3.test", r#"<!----."#),
+    ("test3.test", r#"<!----/"#),
+    ("test3.test", r#"<!----0"#),
+    ("test3.test", r#"<!----1"#),
+    ("test3.test", r#"<!----9"#),
+    ("test3.test", r#"<!----<"#),
+    ("test3.test", r#"<!----="#),
+    ("test3.test", r#"<!----?"#),
+    ("test3.test", r#"<!----@"#),
+    ("test3.test", r#"<!----A"#),
+    ("test3.test", r#"<!----B"#),
+    ("test3.test", r#"<!----Y"#),
+    ("test3.test", r#"<!----Z"#),
+    ("test3.test", r#"<!----\u0009"#),
+    ("test3.test", r#"<!----\u000A"#),
+    ("test3.test", r#"<!----\u000B"#),
+    ("test3.test", r#"<!----\u000C"#),
+    ("test3.test", r#"<!----\uDBC0\uDC00"#),
+    ("test3.test", r#"<!----`"#),
+    ("test3.test", r#"<!----a"#),
+    ("test3.test", r#"<!----b"#),
+    ("test3.test", r#"<!----y"#),
+    ("test3.test", r#"<!----z"#),
+    ("test3.test", r#"<!----{"#),
+    // `<!DOCTYPE>` now reports one error, a missing doctype name.
+    ("test2.test", r#"DOCTYPE without name"#),
+    ("test3.test", r#"<!DOCTYPE>"#),
+    // NULs in doctypes and bogus comments and in `<!` and `</` are now
+    // unexpected-null-character errors of their own. That includes the NUL
+    // in `<!doc\0`, whose `<!doc` starts a bogus comment.
+    ("test3.test", r#"<!DOCTYPE a PUBLIC''\u0000"#),
+    ("test3.test", r#"<!DOCTYPE a PUBLIC\u0000"#),
+    ("test3.test", r#"<!DOCTYPE a SYSTEM''\u0000"#),
+    ("test3.test", r#"<!DOCTYPE a SYSTEM\u0000"#),
+    ("test3.test", r#"<!DOCTYPE a \u0000"#),
+    ("test3.test", r#"<!DOCTYPE a a\u0000"#),
+    ("test3.test", r#"<!DOCTYPEa PUBLIC''\u0000"#),
+    ("test3.test", r#"<!DOCTYPEa PUBLIC\u0000"#),
+    ("test3.test", r#"<!DOCTYPEa SYSTEM''\u0000"#),
+    ("test3.test", r#"<!DOCTYPEa SYSTEM\u0000"#),
+    ("test3.test", r#"<!DOCTYPEa \u0000"#),
+    ("test3.test", r#"<!DOCTYPEa a\u0000"#),
+    ("test3.test", r#"<!\u0000"#),
+    ("test3.test", r#"</\u0000"#),
+    ("test3.test", r#"<?\u0000"#),
+    ("test4.test", r#"U+0000 in lookahead region"#),
+];
+
+#[derive(Default)]
+struct Tally {
+    cases: usize,
+    tokens_passed: usize,
+    errors_passed: usize,
+    skipped: usize,
+}
+
+#[test]
+fn html5lib_tokenizer() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/html5lib-tests/tokenizer");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "test"))
+        .collect();
+    paths.sort();
+
+    let mut total = Tally::default();
+    let mut failures = Vec::new();
+    let mut error_mismatches = BTreeSet::new();
+
+    for path in paths {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let fixture: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let mut tally = Tally::default();
+
+        for (key, cases) in fixture.as_object().unwrap() {
+            let cases = cases.as_array().unwrap();
+            match key.as_str() {
+                "tests" => {
+                    for case in cases {
+                        run_case(
+                            &name,
+                            case,
+                            &mut tally,
+                            &mut failures,
+                            &mut error_mismatches,
+                        );
+                    }
+                }
+                // Expected output after coercing to an XML infoset, which
+                // the tokenizer doesn't do.
+                "xmlViolationTests" => {
+                    tally.cases += cases.len();
+                    tally.skipped += cases.len();
+                }
+                _ => panic!("{name}: unknown top-level key {key:?}"),
+            }
+        }
+
+        println!(
+            "{name}: tokens {}/{}, errors {}/{}{}",
+            tally.tokens_passed,
+            tally.cases,
+            tally.errors_passed,
+            tally.cases,
+            match tally.skipped {
+                0 => String::new(),
+                skipped => format!(", {skipped} skipped"),
+            }
+        );
+
+        total.cases += tally.cases;
+        total.tokens_passed += tally.tokens_passed;
+        total.errors_passed += tally.errors_passed;
+        total.skipped += tally.skipped;
+    }
+
+    println!(
+        "total: tokens {}/{}, errors {}/{}, {} skipped",
+        total.tokens_passed, total.cases, total.errors_passed, total.cases, total.skipped
+    );
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+
+    let known: BTreeSet<_> = KNOWN_ERROR_MISMATCHES
+        .iter()
+        .map(|&(file, description)| (file.to_owned(), description.to_owned()))
+        .collect();
+    let unexpected: Vec<_> = error_mismatches.difference(&known).collect();
+    let fixed: Vec<_> = known.difference(&error_mismatches).collect();
+    assert!(
+        unexpected.is_empty(),
+        "cases with mismatched parse errors: {unexpected:#?}"
+    );
+    assert!(
+        fixed.is_empty(),
+        "known mismatches that now pass, remove them from KNOWN_ERROR_MISMATCHES: {fixed:#?}"
+    );
+}
+
+fn run_case(
+    file: &str,
+    case: &Value,
+    tally: &mut Tally,
+    failures: &mut Vec<String>,
+    error_mismatches: &mut BTreeSet<(String, String)>,
+) {
+    let double_escaped = case["doubleEscaped"].as_bool().unwrap_or(false);
+    let states = match case["initialStates"].as_array() {
+        Some(states) => states.iter().map(|s| s.as_str().unwrap()).collect(),
+        None => vec!["Data state"],
+    };
+
+    // Inputs with lone surrogates can't be represented as a `&str`.
+    let (input, output) = match double_escaped {
+        true => match (
+            unescape(case["input"].as_str().unwrap()),
+            unescape_value(&case["output"]),
+        ) {
+            (Some(input), Some(output)) => (input, output),
+            _ => {
+                tally.cases += states.len();
+                tally.skipped += states.len();
+                return;
+            }
+        },
+        false => (
+            case["input"].as_str().unwrap().to_owned(),
+            case["output"].clone(),
+        ),
+    };
+
+    let expected_tokens = expected_tokens(&output);
+    let last_start_tag = case["lastStartTag"].as_str();
+
+    for state_name in states {
+        tally.cases += 1;
+
+        let state = match state_name {
+            "Data state" => State::Data,
+            "PLAINTEXT state" => State::Plaintext,
+            "RCDATA state" => State::Rcdata,
+            "RAWTEXT state" => State::Rawtext,
+            "Script data state" => State::ScriptData,
+            "CDATA section state" => State::CDataSection,
+            _ => panic!("unknown initial state {state_name:?}"),
+        };
+
+        let (tokens, errors) = tokenize(&input, state, last_start_tag, false);
+        let (streamed_tokens, streamed_errors) = tokenize(&input, state, last_start_tag, true);
+
+        let description = format!(
+            "{file}: {} [{state_name}]\n  input: {input:?}",
+            case["description"]
+        );
+
+        if tokens != expected_tokens {
+            failures.push(format!(
+                "{description}\n  got:  {}\n  want: {}",
+                Value::Array(tokens),
+                Value::Array(expected_tokens.clone())
+            ));
+            continue;
+        }
+
+        if streamed_tokens != tokens || streamed_errors != errors {
+            failures.push(format!(
+                "{description}\n  streaming a character at a time gave different output"
+            ));
+            continue;
+        }
+
+        tally.tokens_passed += 1;
+
+        if errors_match(case, &output, &errors) {
+            tally.errors_passed += 1;
+        } else {
+            let description = case["description"].as_str().unwrap().to_owned();
+            error_mismatches.insert((file.to_owned(), description));
+        }
+    }
+}
+
+fn tokenize(
+    input: &str,
+    state: State,
+    last_start_tag: Option<&str>,
+    streamed: bool,
+) -> (Vec<Value>, Vec<PositionedError>) {
+    let options = TokenizerOptions {
+        state_policy: StatePolicy::Manual,
+        ..Default::default()
+    };
+
+    let mut tokenizer = match streamed {
+        true => Tokenizer::with_options(&options),
+        false => Tokenizer::borrowing(input, &options),
+    };
+    tokenizer.set_state(state);
+    tokenizer.set_last_start_tag_name(last_start_tag);
+
+    let mut tokens = Vec::new();
+
+    if streamed {
+        let mut buf = [0; 4];
+        for ch in input.chars() {
+            tokenizer.feed(ch.encode_utf8(&mut buf));
+            tokens.extend(tokenizer.by_ref().map(to_json));
+        }
+        tokenizer.end();
+    }
+
+    tokens.extend(tokenizer.by_ref().map(to_json));
+    (coalesce_characters(tokens), tokenizer.take_errors())
+}
+
+/// A token in the fixtures' format, e.g. `["StartTag", "a", {"href": "x"}]`.
+fn to_json(token: Token) -> Value {
+    match token {
+        Token::Doctype(doctype) => json!([
+            "DOCTYPE",
+            doctype.name(),
+            doctype.public_id(),
+            doctype.system_id(),
+            !doctype.force_quirks()
+        ]),
+        Token::StartTag(tag) => {
+            let attrs: Map<String, Value> = tag
+                .attrs()
+                .iter()
+                .map(|attr| (attr.name().to_owned(), attr.value().into()))
+                .collect();
+
+            match tag.is_self_closing() {
+                true => json!(["StartTag", tag.name(), attrs, true]),
+                false => json!(["StartTag", tag.name(), attrs]),
+            }
+        }
+        Token::EndTag(tag) => json!(["EndTag", tag.name()]),
+        Token::Character(ch, _) => json!(["Character", ch.to_string()]),
+        Token::Text(text, _) => json!(["Character", text]),
+        Token::Comment(text, _) => json!(["Comment", text]),
+        Token::EndOfFile(_) => Value::Null,
+    }
+}
+
+fn expected_tokens(output: &Value) -> Vec<Value> {
+    let tokens = output
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|token| token.is_array())
+        .cloned()
+        .collect();
+
+    coalesce_characters(tokens)
+}
+
+/// Merges adjacent character tokens and drops the end-of-file token, which
+/// the fixtures leave out.
+fn coalesce_characters(tokens: Vec<Value>) -> Vec<Value> {
+    let mut out: Vec<Value> = Vec::new();
+
+    for token in tokens {
+        if token.is_null() {
+            continue;
+        }
+
+        if token[0] == "Character" {
+            if let Some(last) = out.last_mut().filter(|last| last[0] == "Character") {
+                let text = format!(
+                    "{}{}",
+                    last[1].as_str().unwrap(),
+                    token[1].as_str().unwrap()
+                );
+                last[1] = text.into();
+                continue;
+            }
+        }
+
+        out.push(token);
+    }
+
+    out
+}
+
+/// Compares error codes where the case lists them, and otherwise the number
+/// of `"ParseError"` entries in its output.
+fn errors_match(case: &Value, output: &Value, errors: &[PositionedError]) -> bool {
+    match case["errors"].as_array() {
+        Some(expected) => {
+            let mut expected: Vec<&str> = expected
+                .iter()
+                .map(|error| error["code"].as_str().unwrap())
+                .collect();
+            let mut got: Vec<&str> = errors.iter().map(|error| error.error.code()).collect();
+
+            if case["ignoreErrorOrder"].as_bool().unwrap_or(false) {
+                expected.sort_unstable();
+                got.sort_unstable();
+            }
+
+            got == expected
+        }
+        None => {
+            let expected = output
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|token| *token == "ParseError")
+                .count();
+
+            errors.len() == expected
+        }
+    }
+}
+
+/// Undoes the extra `\uHHHH` escaping of `doubleEscaped` cases, or returns
+/// `None` if the result has a lone surrogate.
+fn unescape(text: &str) -> Option<String> {
+    let mut units = Vec::new();
+    let mut rest = text;
+
+    while let Some(ch) = rest.chars().next() {
+        match rest.strip_prefix("\\u") {
+            Some(escape) => {
+                units.push(u16::from_str_radix(&escape[..4], 16).unwrap());
+                rest = &escape[4..];
+            }
+            None => {
+                let mut buf = [0; 2];
+                units.extend_from_slice(ch.encode_utf16(&mut buf));
+                rest = &rest[ch.len_utf8()..];
+            }
+        }
+    }
+
+    String::from_utf16(&units).ok()
+}
+
+fn unescape_value(value: &Value) -> Option<Value> {
+    Some(match value {
+        Value::String(text) => Value::String(unescape(text)?),
+        Value::Array(values) => {
+            Value::Array(values.iter().map(unescape_value).collect::<Option<_>>()?)
+        }
+        Value::Object(entries) => Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| Some((unescape(key)?, unescape_value(value)?)))
+                .collect::<Option<_>>()?,
+        ),
+        value => value.clone(),
+    })
+}
@@ -0,0 +1,127 @@
+//! Conformance against the tree construction tests of html5lib-tests.
+//!
+//! The fixtures in `tests/html5lib-tests/tree-construction` come from the
+//! same snapshot as the tokenizer ones (see `tests/html5lib-tests/REVISION`).
+//! Each case's input is parsed as a document and the resulting tree compared
+//! with the expected one in the fixtures' format. Fragment cases and cases
+//! that expect scripting to be enabled are skipped, as the parser has no
+//! fragment parsing and runs with scripting disabled. Parse errors are not
+//! compared.
+//!
+//! Run with `cargo test --test html5lib_tree_construction -- --nocapture`
+//! to see the pass rate of each file.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+#[derive(Default)]
+struct Case {
+    data: String,
+    document: String,
+    skip: bool,
+}
+
+#[test]
+fn html5lib_tree_construction() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/html5lib-tests/tree-construction");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "dat"))
+        .collect();
+    paths.sort();
+
+    let (mut total_cases, mut total_passed, mut total_skipped) = (0, 0, 0);
+    let mut failures = Vec::new();
+
+    for path in paths {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let (mut cases, mut passed, mut skipped) = (0, 0, 0);
+
+        for case in parse_cases(&name, &fs::read_to_string(&path).unwrap()) {
+            cases += 1;
+            if case.skip {
+                skipped += 1;
+                continue;
+            }
+
+            let tree = common::tree(&rust_scraper::parse_document(&case.data));
+
+            if tree == case.document {
+                passed += 1;
+            } else {
+                failures.push(format!(
+                    "{name}: {:?}\n  got:\n{tree}  want:\n{}",
+                    case.data, case.document
+                ));
+            }
+        }
+
+        println!(
+            "{name}: {passed}/{}{}",
+            cases - skipped,
+            match skipped {
+                0 => String::new(),
+                skipped => format!(", {skipped} skipped"),
+            }
+        );
+
+        total_cases += cases;
+        total_passed += passed;
+        total_skipped += skipped;
+    }
+
+    println!(
+        "total: {total_passed}/{}, {total_skipped} skipped",
+        total_cases - total_skipped
+    );
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+/// Splits a `.dat` file into its cases. Each starts with a `#data` section
+/// and is separated from the next one by a blank line.
+fn parse_cases(file: &str, text: &str) -> Vec<Case> {
+    let text = text.strip_prefix("#data\n").unwrap_or(text);
+    let mut cases = Vec::new();
+
+    for chunk in text.split("\n\n#data\n") {
+        let mut case = Case::default();
+        let mut section = "#data";
+
+        for line in chunk.split('\n') {
+            match line {
+                "#errors" | "#new-errors" | "#document" | "#script-off" => {
+                    section = line;
+                    continue;
+                }
+                "#document-fragment" | "#script-on" => {
+                    case.skip = true;
+                    section = line;
+                    continue;
+                }
+                _ if line.starts_with('#') && section.contains("errors") => {
+                    panic!("{file}: unknown section {line:?}")
+                }
+                _ => {}
+            }
+
+            let buffer = match section {
+                "#data" => &mut case.data,
+                "#document" => &mut case.document,
+                _ => continue,
+            };
+            buffer.push_str(line);
+            buffer.push('\n');
+        }
+
+        // The newline before the next section is not part of the input.
+        case.data.pop();
+        case.document = case.document.trim_end_matches('\n').to_owned() + "\n";
+        cases.push(case);
+    }
+
+    cases
+}
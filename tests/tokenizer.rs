@@ -0,0 +1,443 @@
+//! Behaviour of the tokenizer that the html5lib-tests fixtures don't
+//! check: spans, positions of errors, borrowing, text runs and sinks.
+
+use std::borrow::Cow;
+
+use rust_scraper::parse_error::Span;
+use rust_scraper::tokenizer::{
+    tokenize, tokenize_with_errors, tokenize_with_options, RunResult, State, Token, TokenSink,
+    TokenSinkResult, Tokenizer, TokenizerOptions,
+};
+
+/// A span as `line:column-line:column start..end`, the positions followed
+/// by the byte range.
+fn describe(span: Span) -> String {
+    format!("{span} {:?}", span.bytes())
+}
+
+fn spans(tokens: &[Token<'_>]) -> Vec<String> {
+    tokens.iter().map(|token| describe(token.span())).collect()
+}
+
+#[test]
+fn token_spans_meet() {
+    let tokens = tokenize("<!DOCTYPE html><p class=\"x\">hi<!--c--></p>");
+    assert_eq!(
+        spans(&tokens),
+        [
+            "1:1-1:16 0..15",
+            "1:16-1:29 15..28",
+            "1:29-1:31 28..30",
+            "1:31-1:39 30..38",
+            "1:39-1:43 38..42",
+            "1:43-1:43 42..42",
+        ]
+    );
+}
+
+#[test]
+fn attribute_name_and_value_spans() {
+    let tokens = tokenize("<p class=\"x\" id=y hidden data-e=''>");
+    let Token::StartTag(tag) = &tokens[0] else {
+        panic!("not a start tag: {:?}", tokens[0]);
+    };
+    let attributes: Vec<_> = tag
+        .attrs()
+        .iter()
+        .map(|attribute| {
+            format!(
+                "{} {} / {}",
+                attribute.name(),
+                describe(attribute.name_span()),
+                describe(attribute.value_span())
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        attributes,
+        [
+            // Quoted values' spans are inside the quotes.
+            "class 1:4-1:9 3..8 / 1:11-1:12 10..11",
+            "id 1:14-1:16 13..15 / 1:17-1:18 16..17",
+            // A missing value has an empty span where the name ends.
+            "hidden 1:19-1:25 18..24 / 1:25-1:25 24..24",
+            "data-e 1:26-1:32 25..31 / 1:34-1:34 33..33",
+        ]
+    );
+}
+
+#[test]
+fn line_and_column_after_newlines() {
+    // CRLF and a lone CR are each one newline, but the byte offsets are
+    // those of the input as given.
+    let tokens = tokenize("a\r\nb<p>\rc<br>\né");
+    assert_eq!(
+        tokens,
+        [
+            Token::Text("a\nb".into(), Span::default()),
+            tokens[1].clone(),
+            Token::Text("\n".into(), Span::default()),
+            Token::Text("c".into(), Span::default()),
+            tokens[4].clone(),
+            Token::Text("\n".into(), Span::default()),
+            Token::Text("é".into(), Span::default()),
+            Token::EndOfFile(Span::default()),
+        ]
+    );
+    assert_eq!(
+        spans(&tokens),
+        [
+            "1:1-2:2 0..4",
+            "2:2-2:5 4..7",
+            "2:5-3:1 7..8",
+            "3:1-3:2 8..9",
+            "3:2-3:6 9..13",
+            "3:6-4:1 13..14",
+            "4:1-4:2 14..16",
+            "4:2-4:2 16..16",
+        ]
+    );
+}
+
+#[test]
+fn text_spans_across_chunks() {
+    let mut tokenizer = Tokenizer::new();
+    tokenizer.feed("<p>Hel");
+    let mut tokens: Vec<_> = tokenizer.by_ref().collect();
+    tokenizer.feed("lo\r");
+    tokens.extend(tokenizer.by_ref());
+    tokenizer.feed("\nwörld</p>");
+    tokenizer.end();
+    tokens.extend(tokenizer.by_ref());
+
+    assert_eq!(
+        tokens[1],
+        Token::Text("Hello\nwörld".into(), Span::default())
+    );
+    assert_eq!(
+        spans(&tokens),
+        [
+            "1:1-1:4 0..3",
+            "1:4-2:6 3..16",
+            "2:6-2:10 16..20",
+            "2:10-2:10 20..20",
+        ]
+    );
+}
+
+#[test]
+fn node_spans() {
+    let document = rust_scraper::parse_document("<p id=a>x</x>y</p>");
+    let p = document.elements_by_tag_name("p").next().unwrap();
+    let body = document.node(p).parent().unwrap();
+    let text = document.node(p).first_child().unwrap();
+
+    // An element has the span of its start tag.
+    assert_eq!(describe(document.node(p).span()), "1:1-1:9 0..8");
+    let attribute = &document
+        .element(p)
+        .unwrap()
+        .attributes()
+        .iter()
+        .next()
+        .unwrap();
+    assert_eq!(describe(attribute.name_span), "1:4-1:6 3..5");
+    assert_eq!(describe(attribute.value_span), "1:7-1:8 6..7");
+
+    // Text appended to a text node extends its span.
+    assert_eq!(describe(document.node(text).span()), "1:9-1:15 8..14");
+
+    // Implied elements have an empty span.
+    assert_eq!(describe(document.node(body).span()), "1:1-1:1 0..0");
+}
+
+/// Errors as `line:column: code @offset`.
+fn errors(html: &str) -> Vec<String> {
+    let (_, errors) = tokenize_with_errors(html);
+    errors
+        .iter()
+        .map(|error| format!("{error} @{}", error.position.offset))
+        .collect()
+}
+
+#[test]
+fn error_positions() {
+    // Errors are at the character where they are found, counting lines and
+    // columns like spans do.
+    assert_eq!(
+        errors("a\r\nb</>\nc\0"),
+        [
+            "2:4: missing-end-tag-name @6",
+            "3:2: unexpected-null-character @9",
+        ]
+    );
+}
+
+#[test]
+fn character_reference_error_positions() {
+    // A numeric reference is checked once the character after it has been
+    // consumed, a named one at the last character of its name.
+    assert_eq!(
+        errors("x\n&#0;&ampy &#x110000;\r\n&#;"),
+        [
+            "2:5: null-character-reference @6",
+            "2:8: missing-semicolon-after-character-reference @9",
+            "2:21: character-reference-outside-unicode-range @22",
+            "3:3: absence-of-digits-in-numeric-character-reference @26",
+        ]
+    );
+}
+
+#[test]
+fn eof_error_positions() {
+    assert_eq!(errors("é\n<a b='"), ["2:7: eof-in-tag @9"]);
+    assert_eq!(errors("\n<!--x"), ["2:6: eof-in-comment @6"]);
+    assert_eq!(errors("<p>\n</"), ["2:3: eof-before-tag-name @6"]);
+}
+
+/// The text of the text and comment tokens.
+fn texts<'t, 'a>(tokens: &'t [Token<'a>]) -> Vec<&'t Cow<'a, str>> {
+    tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Text(text, _) | Token::Comment(text, _) => Some(text),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn unchanged_text_is_borrowed() {
+    let tokens = tokenize("plain<p>x\0y<!--c--><style>a { }</style>");
+    let texts = texts(&tokens);
+
+    assert_eq!(texts, ["plain", "x", "\0", "y", "c", "a { }"]);
+    for text in texts {
+        assert!(matches!(text, Cow::Borrowed(_)), "{text:?} is owned");
+    }
+}
+
+#[test]
+fn changed_text_is_owned() {
+    let tokens = tokenize("a&amp;b<!--x&amp;--><title>c\0</title>d\r\ne<!--\r\n-->");
+    let texts = texts(&tokens);
+
+    // Character references aren't decoded in comments, so that one can be
+    // borrowed.
+    assert_eq!(texts, ["a&b", "x&amp;", "c\u{FFFD}", "d\ne", "\n"]);
+    assert!(matches!(texts[0], Cow::Owned(_)));
+    assert!(matches!(texts[1], Cow::Borrowed(_)));
+    for text in &texts[2..] {
+        assert!(matches!(text, Cow::Owned(_)), "{text:?} is borrowed");
+    }
+}
+
+#[test]
+fn fed_text_is_owned() {
+    let mut tokenizer = Tokenizer::new();
+    tokenizer.feed("plain");
+    tokenizer.end();
+
+    let tokens: Vec<_> = tokenizer.collect();
+    assert!(matches!(&tokens[0], Token::Text(Cow::Owned(text), _) if text == "plain"));
+}
+
+/// Pauses after `</pause>`, and tokenizes the content of `<raw>` as RAWTEXT.
+#[derive(Default)]
+struct Sink {
+    tokens: Vec<String>,
+}
+
+impl<'a> TokenSink<'a> for Sink {
+    fn process_token(&mut self, token: Token<'a>) -> TokenSinkResult {
+        self.tokens.push(token.to_string());
+        match token {
+            Token::EndTag(tag) if tag.name() == "pause" => TokenSinkResult::Pause,
+            Token::StartTag(tag) if tag.name() == "raw" => {
+                TokenSinkResult::SwitchTo(State::Rawtext)
+            }
+            _ => TokenSinkResult::Continue,
+        }
+    }
+}
+
+#[test]
+fn sink_pauses_and_resumes() {
+    let mut tokenizer =
+        Tokenizer::borrowing("a</pause>b<!--c--></pause>", &TokenizerOptions::default());
+    let mut sink = Sink::default();
+
+    assert_eq!(tokenizer.run(&mut sink), RunResult::Paused);
+    assert_eq!(sink.tokens, ["a", "</pause>"]);
+
+    assert_eq!(tokenizer.run(&mut sink), RunResult::Paused);
+    assert_eq!(sink.tokens, ["a", "</pause>", "b", "<!--c-->", "</pause>"]);
+
+    assert_eq!(tokenizer.run(&mut sink), RunResult::Done);
+    assert_eq!(sink.tokens.len(), 6);
+}
+
+#[test]
+fn sink_pauses_on_fed_input() {
+    let mut tokenizer = Tokenizer::new();
+    let mut sink = Sink::default();
+
+    tokenizer.feed("<p></pause><i>");
+    assert_eq!(tokenizer.run(&mut sink), RunResult::Paused);
+    assert_eq!(sink.tokens, ["<p>", "</pause>"]);
+    assert_eq!(tokenizer.run(&mut sink), RunResult::NeedsInput);
+    assert_eq!(sink.tokens, ["<p>", "</pause>", "<i>"]);
+
+    tokenizer.end();
+    assert_eq!(tokenizer.run(&mut sink), RunResult::Done);
+}
+
+#[test]
+fn sink_switches_state() {
+    let mut tokenizer =
+        Tokenizer::borrowing("<raw><b>&amp;</raw><b>", &TokenizerOptions::default());
+    let mut sink = Sink::default();
+
+    assert_eq!(tokenizer.run(&mut sink), RunResult::Done);
+    // `Display` escapes the text again.
+    assert_eq!(
+        sink.tokens,
+        ["<raw>", "&lt;b&gt;&amp;amp;", "</raw>", "<b>", ""]
+    );
+}
+
+/// The tokens, text and characters quoted and tags as markup.
+fn runs(html: &str, options: &TokenizerOptions) -> Vec<String> {
+    tokenize_with_options(html, options)
+        .0
+        .iter()
+        .map(|token| match token {
+            Token::Text(text, _) => format!("{text:?}"),
+            Token::Character(ch, span) => format!("{ch:?} {span}"),
+            token => token.to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn nul_is_a_run_of_its_own() {
+    assert_eq!(
+        runs("a\0\0b<p>\0", &TokenizerOptions::default()),
+        [r#""a""#, r#""\0""#, r#""\0""#, r#""b""#, "<p>", r#""\0""#, ""]
+    );
+    // Where it is replaced, it joins the run like any other character.
+    assert_eq!(
+        runs("<title>a\0b</title>", &TokenizerOptions::default()),
+        ["<title>", "\"a\u{FFFD}b\"", "</title>", ""]
+    );
+}
+
+#[test]
+fn leading_whitespace_is_a_run_of_its_own() {
+    assert_eq!(
+        runs(" \n a b \t<p>\t<b> x", &TokenizerOptions::default()),
+        [
+            r#"" \n ""#,
+            r#""a b \t""#,
+            "<p>",
+            r#""\t""#,
+            "<b>",
+            r#"" ""#,
+            r#""x""#,
+            "",
+        ]
+    );
+}
+
+#[test]
+fn character_tokens() {
+    let options = TokenizerOptions {
+        character_tokens: true,
+        ..TokenizerOptions::default()
+    };
+
+    // A character from a reference or a CRLF spans all of it.
+    assert_eq!(
+        runs("a&amp;\r\n\0<br>&#x20;", &options),
+        [
+            "'a' 1:1-1:2",
+            "'&' 1:2-1:7",
+            "'\\n' 1:7-2:1",
+            "'\\0' 2:1-2:2",
+            "<br>",
+            "' ' 2:6-2:12",
+            "",
+        ]
+    );
+}
+
+fn display(html: &str) -> Vec<String> {
+    let mut tokens: Vec<_> = tokenize(html).iter().map(Token::to_string).collect();
+    assert_eq!(
+        tokens.pop().as_deref(),
+        Some(""),
+        "end of file displays as nothing"
+    );
+    tokens
+}
+
+#[test]
+fn display_doctypes() {
+    assert_eq!(display("<!doctype HTML>"), ["<!DOCTYPE html>"]);
+    assert_eq!(
+        display(r#"<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.01//EN" 'http://x'>"#),
+        [r#"<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.01//EN" "http://x">"#]
+    );
+    assert_eq!(
+        display(r#"<!DOCTYPE html PUBLIC "">"#),
+        [r#"<!DOCTYPE html PUBLIC "">"#]
+    );
+    // An identifier containing `"` goes in single quotes.
+    assert_eq!(
+        display(r#"<!DOCTYPE html SYSTEM 'a"b'>"#),
+        [r#"<!DOCTYPE html SYSTEM 'a"b'>"#]
+    );
+
+    let tokens = tokenize("<!DOCTYPE html PUBLIC>");
+    let Token::Doctype(doctype) = &tokens[0] else {
+        panic!("not a doctype: {:?}", tokens[0]);
+    };
+    assert!(doctype.force_quirks());
+    assert_eq!(doctype.to_string(), "<!DOCTYPE html>");
+    assert_eq!(display("<!DOCTYPE>"), ["<!DOCTYPE>"]);
+}
+
+#[test]
+fn display_comments() {
+    // Comment text is not escaped.
+    assert_eq!(
+        display("<!--a-b--><?php x?><!-- & < -->"),
+        ["<!--a-b-->", "<!--?php x?-->", "<!-- & < -->"]
+    );
+}
+
+#[test]
+fn display_tags() {
+    // An end tag displays without the attributes and slash it was given.
+    assert_eq!(display("</P class=x/>"), ["</p>"]);
+    assert_eq!(display("<BR/><br>"), ["<br />", "<br>"]);
+    // Attribute values are always double-quoted.
+    assert_eq!(
+        display(r#"<a href='x"y&amp;z' b=<c d e=&nbsp;>"#),
+        [r#"<a href="x&quot;y&amp;z" b="&lt;c" d="" e="&nbsp;">"#]
+    );
+}
+
+#[test]
+fn display_text() {
+    assert_eq!(
+        display("a &amp; b &lt; &gt; &quot; \u{A0}'"),
+        ["a &amp; b &lt; &gt; \" &nbsp;'"]
+    );
+    // Even where the HTML Standard leaves it unescaped.
+    assert_eq!(
+        display("<script>a<b&amp;</script>"),
+        ["<script>", "a&lt;b&amp;amp;", "</script>"]
+    );
+}
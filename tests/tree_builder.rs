@@ -0,0 +1,706 @@
+//! Tree construction cases the html5lib-tests snapshot misses, or that
+//! guard against regressions in particular algorithms.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use rust_scraper::dom::{NodeData, QuirksMode, ShadowRootMode};
+use rust_scraper::parse_document;
+use rust_scraper::tree_builder::parse_document_with_errors;
+
+fn quirks_mode(html: &str) -> QuirksMode {
+    parse_document(html).quirks_mode()
+}
+
+#[test]
+fn quirks_mode_detection() {
+    assert_eq!(quirks_mode("<!DOCTYPE html><p>"), QuirksMode::NoQuirks);
+    assert_eq!(
+        quirks_mode("<!doctype HTML SYSTEM \"about:legacy-compat\">"),
+        QuirksMode::NoQuirks
+    );
+    assert_eq!(
+        quirks_mode(
+            r#"<!DOCTYPE HTML PUBLIC "-//W3C//DTD HTML 4.01//EN" "http://www.w3.org/TR/html4/strict.dtd">"#
+        ),
+        QuirksMode::NoQuirks
+    );
+
+    // No doctype, a missing or wrong name, and force-quirks all mean quirks.
+    assert_eq!(quirks_mode("<p>"), QuirksMode::Quirks);
+    assert_eq!(quirks_mode("<!DOCTYPE>"), QuirksMode::Quirks);
+    assert_eq!(quirks_mode("<!DOCTYPE svg>"), QuirksMode::Quirks);
+    assert_eq!(quirks_mode("<!DOCTYPE html PUBLIC>"), QuirksMode::Quirks);
+
+    // Public and system identifiers from the spec's lists.
+    assert_eq!(
+        quirks_mode(r#"<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.0 Transitional//EN">"#),
+        QuirksMode::Quirks
+    );
+    assert_eq!(
+        quirks_mode(r#"<!DOCTYPE html PUBLIC "-//w3o//dtd w3 html strict 3.0//en//">"#),
+        QuirksMode::Quirks
+    );
+    assert_eq!(
+        quirks_mode(
+            r#"<!DOCTYPE html SYSTEM "http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd">"#
+        ),
+        QuirksMode::Quirks
+    );
+
+    // The HTML 4.01 transitional doctype depends on the system identifier.
+    assert_eq!(
+        quirks_mode(r#"<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN">"#),
+        QuirksMode::Quirks
+    );
+    assert_eq!(
+        quirks_mode(
+            r#"<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN" "http://www.w3.org/TR/html4/loose.dtd">"#
+        ),
+        QuirksMode::LimitedQuirks
+    );
+    assert_eq!(
+        quirks_mode(r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN">"#),
+        QuirksMode::LimitedQuirks
+    );
+
+    // Only the first doctype counts.
+    assert_eq!(
+        quirks_mode("<!DOCTYPE html><!DOCTYPE foo>"),
+        QuirksMode::NoQuirks
+    );
+    assert_eq!(quirks_mode("<p><!DOCTYPE html>"), QuirksMode::Quirks);
+}
+
+#[test]
+fn implied_html_head_and_body() {
+    assert_eq!(
+        common::tree(&parse_document("<title>t</title>x")),
+        "\
+| <html>
+|   <head>
+|     <title>
+|       \"t\"
+|   <body>
+|     \"x\"
+"
+    );
+    assert_eq!(
+        common::tree(&parse_document("")),
+        "\
+| <html>
+|   <head>
+|   <body>
+"
+    );
+}
+
+#[test]
+fn leading_newline_in_pre_and_textarea() {
+    assert_eq!(
+        common::tree(&parse_document(
+            "<pre>\n\nx</pre><textarea>\ny</textarea><listing>\nz"
+        )),
+        "\
+| <html>
+|   <head>
+|   <body>
+|     <pre>
+|       \"\nx\"
+|     <textarea>
+|       \"y\"
+|     <listing>
+|       \"z\"
+"
+    );
+}
+
+#[test]
+fn raw_text_elements_switch_the_tokenizer() {
+    assert_eq!(
+        common::tree(&parse_document(
+            "<title><b>&amp;</b></title><style><!--</style><textarea></p>&lt;</textarea><xmp><i></xmp>"
+        )),
+        "\
+| <html>
+|   <head>
+|     <title>
+|       \"<b>&</b>\"
+|     <style>
+|       \"<!--\"
+|   <body>
+|     <textarea>
+|       \"</p><\"
+|     <xmp>
+|       \"<i>\"
+"
+    );
+}
+
+#[test]
+fn select_ignores_most_tags() {
+    assert_eq!(
+        common::tree(&parse_document(
+            "<select><b>x</b><option>1<option>2<optgroup><option>3</select>y"
+        )),
+        "\
+| <html>
+|   <head>
+|   <body>
+|     <select>
+|       \"x\"
+|       <option>
+|         \"1\"
+|       <option>
+|         \"2\"
+|       <optgroup>
+|         <option>
+|           \"3\"
+|     \"y\"
+"
+    );
+}
+
+#[test]
+fn frameset_replaces_the_body_until_content_is_seen() {
+    assert_eq!(
+        common::tree(&parse_document(
+            "<!DOCTYPE html><div></div><frameset><frame></frameset>"
+        )),
+        "\
+| <!DOCTYPE html>
+| <html>
+|   <head>
+|   <frameset>
+|     <frame>
+"
+    );
+    assert_eq!(
+        common::tree(&parse_document(
+            "<!DOCTYPE html><div>x</div><frameset><frame></frameset>"
+        )),
+        "\
+| <!DOCTYPE html>
+| <html>
+|   <head>
+|   <body>
+|     <div>
+|       \"x\"
+"
+    );
+    assert_eq!(
+        common::tree(&parse_document(
+            "<!DOCTYPE html> <frameset><frame><noframes><p></noframes></frameset>x"
+        )),
+        "\
+| <!DOCTYPE html>
+| <html>
+|   <head>
+|   <frameset>
+|     <frame>
+|     <noframes>
+|       \"<p>\"
+"
+    );
+}
+
+fn body(html: &str) -> String {
+    let tree = common::tree(&parse_document(html));
+    let (_, body) = tree.split_once("|   <body>\n").unwrap();
+    body.to_owned()
+}
+
+#[test]
+fn adoption_agency_reparents_misnested_formatting() {
+    assert_eq!(
+        body("<b><i>x</b>y</i>"),
+        "\
+|     <b>
+|       <i>
+|         \"x\"
+|     <i>
+|       \"y\"
+"
+    );
+    assert_eq!(
+        body("<a><p>X</a>Y"),
+        "\
+|     <a>
+|     <p>
+|       <a>
+|         \"X\"
+|       \"Y\"
+"
+    );
+    assert_eq!(
+        body("<b>1<p>2</b>3</p>"),
+        "\
+|     <b>
+|       \"1\"
+|     <p>
+|       <b>
+|         \"2\"
+|       \"3\"
+"
+    );
+}
+
+#[test]
+fn formatting_elements_are_reconstructed() {
+    assert_eq!(
+        body("<p><b><i><u>1</p>2"),
+        "\
+|     <p>
+|       <b>
+|         <i>
+|           <u>
+|             \"1\"
+|     <b>
+|       <i>
+|         <u>
+|           \"2\"
+"
+    );
+}
+
+#[test]
+fn noahs_ark_keeps_three_identical_formatting_elements() {
+    assert_eq!(
+        body("<p><b class=x><b class=x><b class=x><b class=x><p>X"),
+        "\
+|     <p>
+|       <b>
+|         class=\"x\"
+|         <b>
+|           class=\"x\"
+|           <b>
+|             class=\"x\"
+|             <b>
+|               class=\"x\"
+|     <p>
+|       <b>
+|         class=\"x\"
+|         <b>
+|           class=\"x\"
+|           <b>
+|             class=\"x\"
+|             \"X\"
+"
+    );
+}
+
+#[test]
+fn nested_a_in_table_is_foster_parented() {
+    assert_eq!(
+        body("<a><table><a></table>"),
+        "\
+|     <a>
+|       <a>
+|       <table>
+"
+    );
+}
+
+#[test]
+fn adoption_agency_inner_loop_drops_distant_formatting_elements() {
+    // `em` is the fourth node above the furthest block, so it is removed
+    // from the active formatting elements instead of being cloned.
+    assert_eq!(
+        body("<b><em><foo><foo><foo><aside></b>x"),
+        "\
+|     <b>
+|       <em>
+|         <foo>
+|           <foo>
+|             <foo>
+|     <aside>
+|       <b>
+|       \"x\"
+"
+    );
+    assert_eq!(
+        body("<a><b><big><em><strong><div>X</a>"),
+        "\
+|     <a>
+|       <b>
+|         <big>
+|           <em>
+|             <strong>
+|     <big>
+|       <em>
+|         <strong>
+|           <div>
+|             <a>
+|               \"X\"
+"
+    );
+}
+
+#[test]
+fn text_in_table_is_foster_parented() {
+    assert_eq!(
+        body("<table>X<tr><td>a</table>"),
+        "\
+|     \"X\"
+|     <table>
+|       <tbody>
+|         <tr>
+|           <td>
+|             \"a\"
+"
+    );
+}
+
+#[test]
+fn row_in_caption_closes_it() {
+    assert_eq!(
+        body("<table><caption>c<tr>r</table>"),
+        "\
+|     \"r\"
+|     <table>
+|       <caption>
+|         \"c\"
+|       <tbody>
+|         <tr>
+"
+    );
+}
+
+#[test]
+fn col_outside_colgroup_implies_one() {
+    assert_eq!(
+        body("<table><colgroup><col></colgroup><col></table>"),
+        "\
+|     <table>
+|       <colgroup>
+|         <col>
+|       <colgroup>
+|         <col>
+"
+    );
+}
+
+#[test]
+fn cell_in_select_in_table_closes_the_select() {
+    assert_eq!(
+        body("<table><tr><td><select><td>x</table>"),
+        "\
+|     <table>
+|       <tbody>
+|         <tr>
+|           <td>
+|             <select>
+|           <td>
+|             \"x\"
+"
+    );
+}
+
+#[test]
+fn svg_tag_and_attribute_names_are_adjusted() {
+    assert_eq!(
+        body("<svg viewbox=0><foreignobject clippathunits=a></foreignobject><clippath/></svg>"),
+        "\
+|     <svg svg>
+|       viewBox=\"0\"
+|       <svg foreignObject>
+|         clipPathUnits=\"a\"
+|       <svg clipPath>
+"
+    );
+    // Outside SVG the names stay lowercase.
+    assert_eq!(
+        body("<foreignobject clippathunits=a>"),
+        "\
+|     <foreignobject>
+|       clippathunits=\"a\"
+"
+    );
+}
+
+#[test]
+fn foreign_attributes_get_namespaces() {
+    assert_eq!(
+        body("<svg><a xlink:href=x xml:lang=en xmlns:xlink=y xlink:foo=z></a></svg>"),
+        "\
+|     <svg svg>
+|       <svg a>
+|         xlink href=\"x\"
+|         xlink:foo=\"z\"
+|         xml lang=\"en\"
+|         xmlns xlink=\"y\"
+"
+    );
+}
+
+#[test]
+fn html_start_tags_break_out_of_foreign_content() {
+    assert_eq!(
+        body("<svg><g><p>x"),
+        "\
+|     <svg svg>
+|       <svg g>
+|     <p>
+|       \"x\"
+"
+    );
+    // `<font>` only breaks out with a presentational attribute.
+    assert_eq!(
+        body("<svg><font></font><font color=red>"),
+        "\
+|     <svg svg>
+|       <svg font>
+|     <font>
+|       color=\"red\"
+"
+    );
+}
+
+#[test]
+fn annotation_xml_is_an_integration_point_for_html_encodings() {
+    assert_eq!(
+        body("<math><annotation-xml encoding=Text/HTML><div>x</div></annotation-xml></math>"),
+        "\
+|     <math math>
+|       <math annotation-xml>
+|         encoding=\"Text/HTML\"
+|         <div>
+|           \"x\"
+"
+    );
+    assert_eq!(
+        body("<math><annotation-xml><div>x</div></annotation-xml></math>"),
+        "\
+|     <math math>
+|       <math annotation-xml>
+|     <div>
+|       \"x\"
+"
+    );
+}
+
+#[test]
+fn cdata_sections_are_only_text_in_foreign_content() {
+    assert_eq!(
+        body("<svg><![CDATA[a<b]]></svg><![CDATA[c]]>"),
+        "\
+|     <svg svg>
+|       \"a<b\"
+|     <!-- [CDATA[c]] -->
+"
+    );
+}
+
+#[test]
+fn template_contents_are_a_separate_fragment() {
+    let document = parse_document("<template><p>x</p></template><div>y</div>");
+    let template = document.elements_by_tag_name("template").next().unwrap();
+    let contents = document.node(template).template_contents().unwrap();
+
+    assert!(matches!(
+        document.node(contents).data(),
+        NodeData::DocumentFragment
+    ));
+    assert_eq!(document.node(template).first_child(), None);
+    assert_eq!(document.node(contents).parent(), None);
+    assert_eq!(
+        common::children(&document, contents),
+        "\
+| <p>
+|   \"x\"
+"
+    );
+    // Searches of the document don't enter template contents.
+    assert_eq!(document.elements_by_tag_name("p").count(), 0);
+    assert_eq!(document.elements_by_tag_name("div").count(), 1);
+}
+
+#[test]
+fn template_in_table_switches_template_modes() {
+    assert_eq!(
+        body("<table><template><td>x</template><tr>"),
+        "\
+|     <table>
+|       <template>
+|         content
+|           <td>
+|             \"x\"
+|       <tbody>
+|         <tr>
+"
+    );
+}
+
+#[test]
+fn deeply_nested_templates_are_closed_at_the_end() {
+    let html = "<template>".repeat(50_000);
+    assert_eq!(
+        parse_document(&html)
+            .elements_by_tag_name("template")
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn declarative_shadow_root_is_attached_to_its_parent() {
+    let document = parse_document(
+        "<div><template shadowrootmode=open shadowrootdelegatesfocus><p>x</p></template><span>y</span></div>",
+    );
+    let host = document.elements_by_tag_name("div").next().unwrap();
+    let shadow_root = document.node(host).shadow_root().unwrap();
+
+    assert!(matches!(
+        document.node(shadow_root).data(),
+        NodeData::ShadowRoot {
+            mode: ShadowRootMode::Open,
+            delegates_focus: true,
+            clonable: false,
+            serializable: false,
+        }
+    ));
+    assert_eq!(document.node(shadow_root).host(), Some(host));
+    assert_eq!(
+        common::children(&document, shadow_root),
+        "\
+| <p>
+|   \"x\"
+"
+    );
+    // The template itself is not inserted.
+    assert_eq!(
+        common::children(&document, host),
+        "\
+| <span>
+|   \"y\"
+"
+    );
+}
+
+#[test]
+fn declarative_shadow_root_falls_back_to_a_template() {
+    // An invalid mode.
+    let document = parse_document("<div><template shadowrootmode=other>x</template></div>");
+    let host = document.elements_by_tag_name("div").next().unwrap();
+    assert_eq!(document.node(host).shadow_root(), None);
+    assert_eq!(
+        common::children(&document, host),
+        "\
+| <template>
+|   shadowrootmode=\"other\"
+|   content
+|     \"x\"
+"
+    );
+
+    // A host that can't have a shadow root.
+    let document = parse_document("<a><template shadowrootmode=open>x</template></a>");
+    let host = document.elements_by_tag_name("a").next().unwrap();
+    assert_eq!(document.node(host).shadow_root(), None);
+    assert_eq!(
+        common::children(&document, host),
+        "\
+| <template>
+|   shadowrootmode=\"open\"
+|   content
+|     \"x\"
+"
+    );
+
+    // A host that already has one keeps the first.
+    let document = parse_document(
+        "<div><template shadowrootmode=closed>1</template><template shadowrootmode=open>x</template></div>",
+    );
+    let host = document.elements_by_tag_name("div").next().unwrap();
+    let shadow_root = document.node(host).shadow_root().unwrap();
+    assert!(matches!(
+        document.node(shadow_root).data(),
+        NodeData::ShadowRoot {
+            mode: ShadowRootMode::Closed,
+            ..
+        }
+    ));
+    assert_eq!(
+        common::children(&document, host),
+        "\
+| <template>
+|   shadowrootmode=\"open\"
+|   content
+|     \"x\"
+"
+    );
+
+    // The topmost element is only the current node when parsing fragments,
+    // which the parser doesn't do. The closest a document gets is a
+    // template in the head, which is not a valid host.
+    assert_eq!(
+        common::tree(&parse_document(
+            "<template shadowrootmode=open>x</template>"
+        )),
+        "\
+| <html>
+|   <head>
+|     <template>
+|       shadowrootmode=\"open\"
+|       content
+|         \"x\"
+|   <body>
+"
+    );
+}
+
+#[test]
+fn only_errors_with_codes_are_reported() {
+    let (_, errors) = parse_document_with_errors("<b><i></b></i></p><div/>&amp");
+    let codes: Vec<_> = errors.iter().map(|error| error.error.code()).collect();
+    assert_eq!(
+        codes,
+        [
+            "non-void-html-element-start-tag-with-trailing-solidus",
+            "missing-semicolon-after-character-reference",
+        ]
+    );
+}
+
+#[test]
+fn deep_nesting_parses_in_linear_time() {
+    // Each of these took tens of seconds when the checks of the stack of
+    // open elements walked all of it for every tag.
+    let inputs = [
+        "<div>".repeat(100_000),
+        "</p><div>".repeat(100_000),
+        "<ul><li>".repeat(50_000),
+        format!("<b>{}", "<div>x".repeat(100_000)),
+        "<div><a>x".repeat(50_000),
+    ];
+
+    for html in inputs {
+        let start = Instant::now();
+        parse_document(&html);
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed < Duration::from_secs(10),
+            "{}... took {elapsed:?}",
+            &html[..20]
+        );
+    }
+}
+
+#[test]
+fn cdata_after_text_depends_on_where_the_text_went() {
+    // The text reopens `b`, an HTML element, so what follows it is no
+    // longer in foreign content.
+    assert_eq!(
+        body("<svg><desc><p><b></p>text<![CDATA[y]]>"),
+        "\
+|     <svg svg>
+|       <svg desc>
+|         <p>
+|           <b>
+|         <b>
+|           \"text\"
+|           <!-- [CDATA[y]] -->
+"
+    );
+}